@@ -9,60 +9,137 @@
 
 use phf::PhfMap;
 
-use std::mem::replace;
+use std::mem;
+use std::cmp::max;
+use std::hash::{Hash, Writer};
+use std::sync::{Arc, Weak, Mutex};
+use std::sync::one::{Once, ONCE_INIT};
+use std::collections::hashmap::HashMap;
 use std::fmt::{Show, Formatter, FormatError};
 
 static static_atom_map: PhfMap<uint> = static_atom_map!();
 static static_atom_array: &'static [&'static str] = static_atom_array!();
 
-// Assume that a string which can be interned always is.
-// FIXME: Revisit this assumption when we have dynamic interning.
 /// Interned string.
 ///
+/// Strings in the static list (see `macros/atom/data.rs`) become
+/// `Static`; everything else is interned at runtime in a global,
+/// thread-safe table and becomes `Dynamic`.  A given string is always
+/// interned the same way, so two atoms are equal iff they are
+/// pointer-equal.
+///
 /// Don't pattern-match this directly! Use the `atom!()` pattern macro.
-#[deriving(Clone, PartialEq, Eq)]
+#[deriving(Clone)]
 pub enum Atom {
     Static(uint),
-    // dynamic interning goes here
-    Owned(String),
+    Dynamic(Arc<String>),
+}
+
+/// The table of dynamically interned strings.
+///
+/// Entries are weak, so a string is freed once the last `Atom` referring
+/// to it is dropped.  Dead entries are swept out lazily, after enough
+/// insertions that the cost of the sweep is amortized.
+struct Interner {
+    table: HashMap<String, Weak<String>>,
+
+    /// Strong references to pre-seeded atoms, which are never freed.
+    pinned: Vec<Arc<String>>,
+
+    /// Number of insertions since the last sweep.
+    inserted: uint,
+}
+
+impl Interner {
+    fn new() -> Interner {
+        Interner {
+            table: HashMap::new(),
+            pinned: vec!(),
+            inserted: 0,
+        }
+    }
+
+    fn intern(&mut self, s: &str) -> Arc<String> {
+        match self.table.find_equiv(&s).and_then(|w| w.upgrade()) {
+            Some(a) => return a,
+            None => (),
+        }
+
+        if self.inserted >= max(64, self.table.len()) {
+            self.sweep();
+        }
+
+        let a = Arc::new(s.to_string());
+        self.table.insert(s.to_string(), a.downgrade());
+        self.inserted += 1;
+        a
+    }
+
+    fn sweep(&mut self) {
+        let dead: Vec<String> = self.table.iter()
+            .filter(|&(_, w)| w.upgrade().is_none())
+            .map(|(k, _)| k.clone())
+            .collect();
+        for k in dead.iter() {
+            self.table.remove(k);
+        }
+        self.inserted = 0;
+    }
+}
+
+static mut global_interner: *const Mutex<Interner> = 0 as *const Mutex<Interner>;
+static global_interner_init: Once = ONCE_INIT;
+
+fn with_interner<R>(f: |&mut Interner| -> R) -> R {
+    unsafe {
+        global_interner_init.doit(|| {
+            let interner: Box<Mutex<Interner>> = box Mutex::new(Interner::new());
+            global_interner = mem::transmute(interner);
+        });
+        let mut guard = (*global_interner).lock();
+        f(&mut *guard)
+    }
+}
+
+fn intern(s: &str) -> Arc<String> {
+    with_interner(|i| i.intern(s))
 }
 
 impl Atom {
     pub fn from_str(s: &str) -> Atom {
         match static_atom_map.find(&s) {
             Some(&k) => Static(k),
-            None => Owned(s.to_string()),
+            None => Dynamic(intern(s)),
         }
     }
 
     pub fn from_buf(s: String) -> Atom {
-        match static_atom_map.find(&s.as_slice()) {
-            Some(&k) => Static(k),
-            None => Owned(s),
-        }
+        Atom::from_str(s.as_slice())
     }
 
     /// Like `Atom::from_buf(replace(s, String::new()))` but avoids
-    /// allocating a new `String` when the string is interned --
-    /// just truncates the old one.
+    /// allocating a new `String` -- just truncates the old one.
     pub fn take_from_buf(s: &mut String) -> Atom {
-        match static_atom_map.find(&s.as_slice()) {
-            Some(&k) => {
-                s.truncate(0);
-                Static(k)
-            }
-            None => {
-                Owned(replace(s, String::new()))
-            }
-        }
+        let atom = Atom::from_str(s.as_slice());
+        s.truncate(0);
+        atom
     }
 
-    #[inline(always)]
-    fn fast_partial_eq(&self, other: &Atom) -> Option<bool> {
-        match (self, other) {
-            (&Static(x), &Static(y)) => Some(x == y),
-            _ => None,
-        }
+    /// Intern each string, and keep it interned for the life of the
+    /// program.
+    ///
+    /// Applications can use this to pre-seed the names they expect to
+    /// see, e.g. custom elements or `data-` attributes, so that the
+    /// parser never has to allocate for them.
+    pub fn preseed(strs: &[&str]) {
+        with_interner(|i| {
+            for &s in strs.iter() {
+                if static_atom_map.find(&s).is_none() {
+                    let a = i.intern(s);
+                    i.pinned.push(a);
+                }
+            }
+        })
     }
 }
 
@@ -74,7 +151,7 @@ impl Str for Atom {
     fn as_slice<'t>(&'t self) -> &'t str {
         match *self {
             Static(i) => get_static(i),
-            Owned(ref s) => s.as_slice(),
+            Dynamic(ref s) => s.as_slice(),
         }
     }
 }
@@ -83,40 +160,60 @@ impl StrAllocating for Atom {
     fn into_string(self) -> String {
         match self {
             Static(i) => get_static(i).to_string(),
-            Owned(s) => s.into_string(),
+            Dynamic(s) => s.deref().clone(),
         }
     }
 
     fn to_string(&self) -> String {
         match *self {
             Static(i) => get_static(i).to_string(),
-            Owned(ref s) => s.clone(),
+            Dynamic(ref s) => s.deref().clone(),
         }
     }
 
     fn into_owned(self) -> String {
         match self {
             Static(i) => get_static(i).to_string(),
-            Owned(s) => s,
+            Dynamic(s) => s.deref().clone(),
+        }
+    }
+}
+
+impl PartialEq for Atom {
+    #[inline]
+    fn eq(&self, other: &Atom) -> bool {
+        match (self, other) {
+            (&Static(x), &Static(y)) => x == y,
+            (&Dynamic(ref x), &Dynamic(ref y))
+                => (&**x as *const String) == (&**y as *const String),
+            _ => false,
         }
     }
 }
 
+impl Eq for Atom { }
+
+impl<S: Writer> Hash<S> for Atom {
+    fn hash(&self, state: &mut S) {
+        self.as_slice().hash(state)
+    }
+}
+
 impl PartialOrd for Atom {
     fn partial_cmp(&self, other: &Atom) -> Option<Ordering> {
-        match self.fast_partial_eq(other) {
-            Some(true) => Some(Equal),
-            _ => self.as_slice().partial_cmp(&other.as_slice()),
+        if self == other {
+            return Some(Equal);
         }
+        self.as_slice().partial_cmp(&other.as_slice())
     }
 }
 
 impl Ord for Atom {
     fn cmp(&self, other: &Atom) -> Ordering {
-        match self.fast_partial_eq(other) {
-            Some(true) => Equal,
-            _ => self.as_slice().cmp(&other.as_slice()),
+        if self == other {
+            return Equal;
         }
+        self.as_slice().cmp(&other.as_slice())
     }
 }
 
@@ -132,7 +229,7 @@ impl Show for Atom {
 #[allow(non_snake_case_functions)]
 mod test {
     use super::*; // public items
-    use super::{get_static}; // private items
+    use super::{get_static, with_interner}; // private items
 
     #[test]
     fn interned() {
@@ -145,11 +242,35 @@ mod test {
     #[test]
     fn not_interned() {
         match Atom::from_str("asdfghjk") {
-            Owned(b) => assert_eq!(b.as_slice(), "asdfghjk"),
+            Dynamic(b) => assert_eq!(b.as_slice(), "asdfghjk"),
             _ => fail!("wrong interning"),
         }
     }
 
+    #[test]
+    fn dynamic_pointer_eq() {
+        match (Atom::from_str("qwertyui"), Atom::from_buf("qwertyui".to_string())) {
+            (Dynamic(x), Dynamic(y)) => assert!((&*x as *const String) == (&*y as *const String)),
+            _ => fail!("wrong interning"),
+        }
+    }
+
+    #[test]
+    fn dynamic_ne_static() {
+        assert!(Atom::from_str("body") != Atom::from_str("bodyx"));
+        assert!(Atom::from_str("zxcvbnm") != Atom::from_str("zxcvbn"));
+    }
+
+    #[test]
+    fn preseed() {
+        Atom::preseed(["my-custom-element", "body"]);
+        with_interner(|i| {
+            assert!(i.pinned.iter().any(|a| a.as_slice() == "my-custom-element"));
+            assert!(!i.pinned.iter().any(|a| a.as_slice() == "body"));
+        });
+        assert_eq!(Atom::from_str("my-custom-element").as_slice(), "my-custom-element");
+    }
+
     #[test]
     fn as_slice() {
         assert_eq!(Atom::from_str("").as_slice(), "");
@@ -0,0 +1,43 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Interned names and namespaces, shared between html5ever and the crates
+//! which consume its output.
+//!
+//! Link this crate as `atom`, so that the `atom!()` macro from
+//! `html5ever-macros` can find it:
+//!
+//! ```rust
+//! #[phase(plugin)]
+//! extern crate macros = "html5ever-macros";
+//! extern crate atom = "html5ever-atom";
+//! ```
+//!
+//! Every crate then shares the same static atom set and the same dynamic
+//! interning table, so names produced by the parser compare by pointer
+//! with names produced anywhere else.
+
+#![crate_name="html5ever-atom"]
+#![crate_type="dylib"]
+
+#![feature(phase)]
+
+#[phase(plugin)]
+extern crate phf_mac;
+
+#[phase(plugin)]
+extern crate macros = "html5ever-macros";
+
+extern crate phf;
+
+pub use atom::{Atom, Static, Dynamic};
+pub use namespace::Namespace;
+
+pub mod atom;
+pub mod namespace;
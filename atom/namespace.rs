@@ -0,0 +1,72 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use atom::Atom;
+
+/// A name / attribute namespace.
+#[deriving(PartialEq, Eq, Clone, Hash, Show)]
+pub enum Namespace {
+    Null,
+    HTML,
+    XML,
+    XMLNS,
+    XLink,
+    SVG,
+    MathML,
+    Other(Atom),
+}
+
+impl Namespace {
+    /// Get the namespace identified by a URL.
+    pub fn from_str(url: &str) -> Namespace {
+        match url {
+            "" => Null,
+            "http://www.w3.org/1999/xhtml" => HTML,
+            "http://www.w3.org/XML/1998/namespace" => XML,
+            "http://www.w3.org/2000/xmlns/" => XMLNS,
+            "http://www.w3.org/1999/xlink" => XLink,
+            "http://www.w3.org/2000/svg" => SVG,
+            "http://www.w3.org/1998/Math/MathML" => MathML,
+            _ => Other(Atom::from_str(url)),
+        }
+    }
+
+    /// The URL identifying this namespace.
+    pub fn to_str<'a>(&'a self) -> &'a str {
+        match *self {
+            Null => "",
+            HTML => "http://www.w3.org/1999/xhtml",
+            XML => "http://www.w3.org/XML/1998/namespace",
+            XMLNS => "http://www.w3.org/2000/xmlns/",
+            XLink => "http://www.w3.org/1999/xlink",
+            SVG => "http://www.w3.org/2000/svg",
+            MathML => "http://www.w3.org/1998/Math/MathML",
+            Other(ref x) => x.as_slice(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for ns in [Null, HTML, XML, XMLNS, XLink, SVG, MathML].iter() {
+            assert_eq!(Namespace::from_str(ns.to_str()), *ns);
+        }
+    }
+
+    #[test]
+    fn other() {
+        let ns = Namespace::from_str("urn:example");
+        assert_eq!(ns.to_str(), "urn:example");
+        assert_eq!(ns, Namespace::from_str("urn:example"));
+    }
+}
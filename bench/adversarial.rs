@@ -0,0 +1,112 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Inputs which would take quadratic time with a naive implementation.
+//! Each is run at two sizes, so a quadratic path shows up as a 100x
+//! slowdown rather than 10x.
+
+use std::default::Default;
+
+use test::{black_box, Bencher, TestDesc, TestDescAndFn};
+use test::{DynTestName, DynBenchFn, TDynBenchFn};
+
+use html5ever::sink::rcdom::RcDom;
+use html5ever::{parse, one_input};
+
+struct Bench {
+    input: String,
+}
+
+impl TDynBenchFn for Bench {
+    fn run(&self, bh: &mut Bencher) {
+        bh.iter(|| {
+            let dom: RcDom = parse(one_input(self.input.clone()), Default::default());
+            black_box(dom);
+        });
+    }
+}
+
+fn repeat(s: &str, n: uint) -> String {
+    let mut out = String::with_capacity(s.len() * n);
+    for _ in range(0, n) {
+        out.push_str(s);
+    }
+    out
+}
+
+// A tag with many distinct attributes.
+fn many_attrs(n: uint) -> String {
+    let mut out = "<p".to_string();
+    for i in range(0, n) {
+        out.push_str(format!(" a{}=x", i).as_slice());
+    }
+    out.push_str(">");
+    out
+}
+
+// A tag with the same attribute many times.
+fn duplicate_attrs(n: uint) -> String {
+    ["<p", repeat(" a=x", n).as_slice(), ">"].concat()
+}
+
+// Stray <html> tags, each merging many attributes into the root.
+fn merged_html_attrs(n: uint) -> String {
+    let tag = many_attrs(n).replace("<p", "<html");
+    repeat(tag.as_slice(), 10)
+}
+
+// Deeply nested formatting elements.
+fn nested_formatting(n: uint) -> String {
+    [repeat("<b><i>", n).as_slice(), "x", repeat("<p>y", 10).as_slice()].concat()
+}
+
+// Many <a> elements, each closing the previous one.
+fn many_anchors(n: uint) -> String {
+    repeat("<a href=x>y", n)
+}
+
+// A quirky doctype with a very long public identifier.
+fn long_doctype(n: uint) -> String {
+    ["<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.0 Transitional//",
+        repeat("X", n).as_slice(), "\">"].concat()
+}
+
+fn make_bench(name: &str, n: uint, input: String) -> TestDescAndFn {
+    TestDescAndFn {
+        desc: TestDesc {
+            name: DynTestName(format!("adversarial {:s} n={:6u}", name, n)),
+            ignore: false,
+            should_fail: false,
+        },
+        testfn: DynBenchFn(box Bench {
+            input: input,
+        }),
+    }
+}
+
+pub fn tests() -> Vec<TestDescAndFn> {
+    let mut tests = vec!();
+
+    let inputs: &[(&str, fn(uint) -> String)] = &[
+        ("many attributes", many_attrs),
+        ("duplicate attributes", duplicate_attrs),
+        ("merged <html> attributes", merged_html_attrs),
+        ("nested formatting", nested_formatting),
+        ("many <a>", many_anchors),
+        ("long doctype", long_doctype),
+    ];
+
+    for &(name, f) in inputs.iter() {
+        for &n in [1000, 10000].iter() {
+            tests.push(make_bench(name, n, f(n)));
+        }
+    }
+
+    tests
+}
@@ -18,12 +18,13 @@ use std::os;
 use test::test_main;
 
 mod tokenizer;
+mod adversarial;
 
 fn main() {
     let mut tests = vec!();
 
     tests.push_all_move(tokenizer::tests());
-    // more to follow
+    tests.push_all_move(adversarial::tests());
 
     let args: Vec<String> = os::args().move_iter().collect();
     test_main(args.as_slice(), tests);
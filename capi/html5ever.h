@@ -0,0 +1,101 @@
+/* Generated by scripts/gen-capi-header.py; do not edit. */
+
+#ifndef HTML5EVER_H
+#define HTML5EVER_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+struct h5e_tokenizer;
+struct h5e_parser;
+
+#define H5E_NO_QUIRKS 0
+#define H5E_LIMITED_QUIRKS 1
+#define H5E_QUIRKS 2
+
+struct h5e_buf {
+    const char *data;
+    size_t len;
+};
+
+struct h5e_token_ops {
+    void (*do_doctype)(void *user, struct h5e_buf name, struct h5e_buf public_id, struct h5e_buf system_id, int force_quirks);
+    void (*do_start_tag)(void *user, struct h5e_buf name, int self_closing, size_t num_attrs);
+    void (*do_tag_attr)(void *user, struct h5e_buf name, struct h5e_buf value);
+    void (*do_end_tag)(void *user, struct h5e_buf name);
+    void (*do_comment)(void *user, struct h5e_buf text);
+    void (*do_chars)(void *user, struct h5e_buf text);
+    void (*do_null_char)(void *user);
+    void (*do_eof)(void *user);
+    void (*do_error)(void *user, struct h5e_buf message);
+};
+
+struct h5e_attr {
+    struct h5e_buf name;
+    struct h5e_buf value;
+};
+
+struct h5e_tree_ops {
+    void (*parse_error)(void *user, struct h5e_buf message);
+    void * (*get_document)(void *user);
+    int (*same_node)(void *user, void *x, void *y);
+    struct h5e_buf (*elem_name)(void *user, void *target);
+    void (*set_quirks_mode)(void *user, int mode);
+    void * (*create_element)(void *user, struct h5e_buf name, const struct h5e_attr *attrs, size_t num_attrs);
+    void (*append_text)(void *user, void *parent, struct h5e_buf text);
+    void (*append_comment)(void *user, void *parent, struct h5e_buf text);
+    void (*append_element)(void *user, void *parent, void *child);
+    void (*append_doctype_to_document)(void *user, struct h5e_buf name, struct h5e_buf public_id, struct h5e_buf system_id);
+    void (*add_attrs_if_missing)(void *user, void *target, const struct h5e_attr *attrs, size_t num_attrs);
+    void (*remove_from_parent)(void *user, void *target);
+    void (*reparent_children)(void *user, void *node, void *new_parent);
+    void (*mark_script_already_started)(void *user, void *target);
+    void (*root_element_inserted)(void *user, void *root, const struct h5e_attr *attrs, size_t num_attrs);
+    void (*associate_with_form)(void *user, void *target, void *form);
+    int (*append_before_sibling)(void *user, void *sibling, void *child);
+};
+
+/* Create a tokenizer.  `ops` must remain valid until the tokenizer is */
+/* freed.  `user` is passed as the first argument to every callback. */
+/* Returns NULL if `ops` is null. */
+struct h5e_tokenizer *h5e_tokenizer_new(const struct h5e_token_ops *ops, void *user);
+
+/* Free a tokenizer created by `h5e_tokenizer_new`.  Does nothing if */
+/* `tok` is NULL. */
+void h5e_tokenizer_free(struct h5e_tokenizer *tok);
+
+/* Feed a chunk of UTF-8 input.  A character may be split across */
+/* chunks. */
+void h5e_tokenizer_feed(struct h5e_tokenizer *tok, struct h5e_buf buf);
+
+/* Signal the end of input. */
+void h5e_tokenizer_end(struct h5e_tokenizer *tok);
+
+/* Create a parser.  `ops` must remain valid until the parser is freed. */
+/* `user` is passed as the first argument to every callback.  Returns */
+/* NULL if `ops` or one of its required callbacks is null. */
+/* */
+/* `<svg>`, `<math>`, `<template>` and `<isindex>` tags are dropped, */
+/* because the tree builder can't handle them yet and must not fail */
+/* across the C boundary.  Their contents are parsed as HTML. */
+struct h5e_parser *h5e_parser_new(const struct h5e_tree_ops *ops, void *user);
+
+/* Free a parser created by `h5e_parser_new`.  Does nothing if */
+/* `parser` is NULL. */
+void h5e_parser_free(struct h5e_parser *parser);
+
+/* Feed a chunk of UTF-8 input.  A character may be split across */
+/* chunks. */
+void h5e_parser_feed(struct h5e_parser *parser, struct h5e_buf buf);
+
+/* Signal the end of input. */
+void h5e_parser_end(struct h5e_parser *parser);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,293 @@
+/* Copyright 2014 The html5ever Project Developers. See the
+ * COPYRIGHT file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+/* Exercise the C API by building a simple tree and printing it. */
+
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "html5ever.h"
+
+struct node {
+    char *name;  /* NULL for text and the document */
+    char *text;
+    struct node *parent, *first_child, *last_child, *next_sibling;
+};
+
+struct dom {
+    struct node *document;
+    int quirks_mode;
+    int errors;
+};
+
+static char *copy_buf(struct h5e_buf buf) {
+    char *s = malloc(buf.len + 1);
+    memcpy(s, buf.data, buf.len);
+    s[buf.len] = '\0';
+    return s;
+}
+
+static struct node *new_node(char *name, char *text) {
+    struct node *n = calloc(1, sizeof(struct node));
+    n->name = name;
+    n->text = text;
+    return n;
+}
+
+static void free_node(struct node *n) {
+    struct node *c, *next;
+    for (c = n->first_child; c; c = next) {
+        next = c->next_sibling;
+        free_node(c);
+    }
+    free(n->name);
+    free(n->text);
+    free(n);
+}
+
+static void append(struct node *parent, struct node *child) {
+    child->parent = parent;
+    if (parent->last_child)
+        parent->last_child->next_sibling = child;
+    else
+        parent->first_child = child;
+    parent->last_child = child;
+}
+
+static void parse_error(void *user, struct h5e_buf message) {
+    ((struct dom *) user)->errors++;
+}
+
+static void *get_document(void *user) {
+    return ((struct dom *) user)->document;
+}
+
+static int same_node(void *user, void *x, void *y) {
+    return x == y;
+}
+
+static struct h5e_buf elem_name(void *user, void *target) {
+    struct h5e_buf buf;
+    struct node *n = target;
+    buf.data = n->name;
+    buf.len = strlen(n->name);
+    return buf;
+}
+
+static void set_quirks_mode(void *user, int mode) {
+    ((struct dom *) user)->quirks_mode = mode;
+}
+
+static void *create_element(void *user, struct h5e_buf name,
+                            const struct h5e_attr *attrs, size_t num_attrs) {
+    return new_node(copy_buf(name), NULL);
+}
+
+static void append_text(void *user, void *parent, struct h5e_buf text) {
+    struct node *p = parent, *last = p->last_child;
+    char *t;
+    if (last && !last->name) {
+        size_t len = strlen(last->text);
+        t = realloc(last->text, len + text.len + 1);
+        memcpy(t + len, text.data, text.len);
+        t[len + text.len] = '\0';
+        last->text = t;
+    } else {
+        append(p, new_node(NULL, copy_buf(text)));
+    }
+}
+
+static void append_element(void *user, void *parent, void *child) {
+    append(parent, child);
+}
+
+static int append_before_sibling(void *user, void *sibling, void *child) {
+    struct node *s = sibling, *c = child, *p = s->parent, **link;
+    if (!p)
+        return 0;
+    for (link = &p->first_child; *link != s; link = &(*link)->next_sibling)
+        ;
+    c->parent = p;
+    c->next_sibling = s;
+    *link = c;
+    return 1;
+}
+
+static void remove_from_parent(void *user, void *target) {
+    struct node *n = target, *p = n->parent, **link, *prev = NULL;
+    if (!p)
+        return;
+    for (link = &p->first_child; *link != n; link = &(*link)->next_sibling)
+        prev = *link;
+    *link = n->next_sibling;
+    if (p->last_child == n)
+        p->last_child = prev;
+    n->parent = NULL;
+    n->next_sibling = NULL;
+}
+
+static void reparent_children(void *user, void *node, void *new_parent) {
+    struct node *n = node, *c, *next;
+    for (c = n->first_child; c; c = next) {
+        next = c->next_sibling;
+        c->next_sibling = NULL;
+        append(new_parent, c);
+    }
+    n->first_child = n->last_child = NULL;
+}
+
+static void print_node(struct node *n, char *out) {
+    struct node *c;
+    if (n->name)
+        sprintf(out + strlen(out), "<%s>", n->name);
+    else if (n->text)
+        strcat(out, n->text);
+    for (c = n->first_child; c; c = c->next_sibling)
+        print_node(c, out);
+    if (n->name)
+        sprintf(out + strlen(out), "</%s>", n->name);
+}
+
+static struct h5e_buf buf(const char *s) {
+    struct h5e_buf b;
+    b.data = s;
+    b.len = strlen(s);
+    return b;
+}
+
+/* Tokens are written to a string, one per line. */
+
+static void do_start_tag(void *user, struct h5e_buf name, int self_closing, size_t num_attrs) {
+    char *out = user;
+    sprintf(out + strlen(out), "start %.*s %d\n", (int) name.len, name.data, (int) num_attrs);
+}
+
+static void do_tag_attr(void *user, struct h5e_buf name, struct h5e_buf value) {
+    char *out = user;
+    sprintf(out + strlen(out), "attr %.*s=%.*s\n",
+            (int) name.len, name.data, (int) value.len, value.data);
+}
+
+static void do_end_tag(void *user, struct h5e_buf name) {
+    char *out = user;
+    sprintf(out + strlen(out), "end %.*s\n", (int) name.len, name.data);
+}
+
+static void do_chars(void *user, struct h5e_buf text) {
+    char *out = user;
+    sprintf(out + strlen(out), "chars %.*s\n", (int) text.len, text.data);
+}
+
+static void test_tokenizer(void) {
+    struct h5e_token_ops ops;
+    struct h5e_tokenizer *tok;
+    char out[1024] = "";
+
+    memset(&ops, 0, sizeof(ops));
+    ops.do_start_tag = do_start_tag;
+    ops.do_tag_attr = do_tag_attr;
+    ops.do_end_tag = do_end_tag;
+    ops.do_chars = do_chars;
+
+    tok = h5e_tokenizer_new(&ops, out);
+    /* U+2603 SNOWMAN, split across chunks. */
+    h5e_tokenizer_feed(tok, buf("<a href=x>\xe2\x98"));
+    h5e_tokenizer_feed(tok, buf("\x83</a>"));
+    h5e_tokenizer_end(tok);
+    h5e_tokenizer_free(tok);
+
+    printf("%s", out);
+    assert(!strcmp(out, "start a 1\nattr href=x\nchars \xe2\x98\x83\nend a\n"));
+
+    assert(h5e_tokenizer_new(NULL, out) == NULL);
+    h5e_tokenizer_free(NULL);
+}
+
+int main(void) {
+    struct h5e_tree_ops ops;
+    struct dom dom;
+    struct h5e_parser *parser;
+    char out[1024] = "";
+
+    test_tokenizer();
+
+    memset(&ops, 0, sizeof(ops));
+    ops.parse_error = parse_error;
+    ops.get_document = get_document;
+    ops.same_node = same_node;
+    ops.elem_name = elem_name;
+    ops.set_quirks_mode = set_quirks_mode;
+    ops.create_element = create_element;
+    ops.append_text = append_text;
+    ops.append_element = append_element;
+    ops.append_before_sibling = append_before_sibling;
+    ops.remove_from_parent = remove_from_parent;
+    ops.reparent_children = reparent_children;
+
+    dom.document = new_node(NULL, NULL);
+    dom.quirks_mode = H5E_NO_QUIRKS;
+    dom.errors = 0;
+
+    parser = h5e_parser_new(&ops, &dom);
+    h5e_parser_feed(parser, buf("<title>Test</title><p>Hello, "));
+    h5e_parser_feed(parser, buf("<b>world<p>again</b>!"));
+    h5e_parser_end(parser);
+    h5e_parser_free(parser);
+
+    print_node(dom.document, out);
+    printf("%s\n", out);
+    assert(!strcmp(out, "<html><head><title>Test</title></head>"
+                        "<body><p>Hello, <b>world</b></p>"
+                        "<p><b>again</b>!</p></body></html>"));
+
+    /* No doctype, so we're in quirks mode. */
+    assert(dom.quirks_mode == H5E_QUIRKS);
+    assert(dom.errors > 0);
+
+    free_node(dom.document);
+
+    /* U+00E9, split across chunks. */
+    dom.document = new_node(NULL, NULL);
+    out[0] = '\0';
+    parser = h5e_parser_new(&ops, &dom);
+    h5e_parser_feed(parser, buf("<p>caf\xc3"));
+    h5e_parser_feed(parser, buf("\xa9"));
+    h5e_parser_end(parser);
+    h5e_parser_free(parser);
+
+    print_node(dom.document, out);
+    printf("%s\n", out);
+    assert(!strcmp(out, "<html><head></head><body><p>caf\xc3\xa9</p></body></html>"));
+    free_node(dom.document);
+
+    /* Tags the tree builder doesn't support are dropped. */
+    dom.document = new_node(NULL, NULL);
+    out[0] = '\0';
+    parser = h5e_parser_new(&ops, &dom);
+    h5e_parser_feed(parser, buf("<svg><p>a</p></svg><math>b</math>"));
+    h5e_parser_feed(parser, buf("<template>c</template><isindex>"));
+    h5e_parser_end(parser);
+    h5e_parser_free(parser);
+
+    print_node(dom.document, out);
+    printf("%s\n", out);
+    assert(!strcmp(out, "<html><head></head><body><p>a</p>bc</body></html>"));
+    free_node(dom.document);
+
+    h5e_parser_free(NULL);
+
+    /* A required callback is missing. */
+    ops.create_element = NULL;
+    assert(h5e_parser_new(&ops, &dom) == NULL);
+
+    return 0;
+}
@@ -23,17 +23,16 @@ use std::default::Default;
 use html5ever::sink::rcdom::RcDom;
 use html5ever::driver::ParseOpts;
 use html5ever::tree_builder::TreeBuilderOpts;
-use html5ever::{parse, one_input, serialize};
+use html5ever::{parse_reader, serialize};
 
 fn main() {
-    let input = io::stdin().read_to_str().unwrap();
-    let dom: RcDom = parse(one_input(input), ParseOpts {
+    let dom: RcDom = parse_reader(&mut io::stdin(), ParseOpts {
         tree_builder: TreeBuilderOpts {
             drop_doctype: true,
             ..Default::default()
         },
         ..Default::default()
-    });
+    }).ok().expect("reading input failed");
 
     // The validator.nu HTML2HTML always prints a doctype at the very beginning.
     io::stdout().write_str("<!DOCTYPE html>\n")
@@ -50,9 +50,11 @@ impl TreeSink<uint> for Sink {
     fn append_text(&mut self, _parent: uint, _text: String) { }
     fn append_comment(&mut self, _parent: uint, _text: String) { }
     fn append_element(&mut self, _parent: uint, _child: uint) { }
+    fn append_before_sibling(&mut self, _sibling: uint, _new_node: uint) -> Result<(), uint> { Ok(()) }
     fn append_doctype_to_document(&mut self, _name: String, _public_id: String, _system_id: String) { }
     fn add_attrs_if_missing(&mut self, _target: uint, _attrs: Vec<Attribute>) { }
     fn remove_from_parent(&mut self, _target: uint) { }
+    fn reparent_children(&mut self, _node: uint, _new_parent: uint) { }
     fn mark_script_already_started(&mut self, _node: uint) { }
 }
 
@@ -14,7 +14,7 @@ use std::default::Default;
 use std::string::String;
 
 use html5ever::sink::rcdom::{RcDom, Handle, Document, Doctype, Text, Comment, Element};
-use html5ever::{parse, one_input};
+use html5ever::parse_reader;
 
 // This is not proper HTML serialization, of course.
 
@@ -50,8 +50,8 @@ fn walk(indent: uint, handle: Handle) {
 }
 
 fn main() {
-    let input = io::stdin().read_to_str().unwrap();
-    let dom: RcDom = parse(one_input(input), Default::default());
+    let dom: RcDom = parse_reader(&mut io::stdin(), Default::default())
+        .ok().expect("reading input failed");
     walk(0, dom.document);
 
     if !dom.errors.is_empty() {
@@ -66,6 +66,12 @@ impl TreeSink<uint> for Sink {
         println!("Append element {:u} to {:u}", child, parent);
     }
 
+    fn append_before_sibling(&mut self, sibling: uint, new_node: uint) -> Result<(), uint> {
+        println!("Append element {:u} before {:u}", new_node, sibling);
+        // We don't track parents, so assume `sibling` has one.
+        Ok(())
+    }
+
     fn append_doctype_to_document(&mut self, name: String, public_id: String, system_id: String) {
         println!("Append doctype: {:s} {:s} {:s}", name, public_id, system_id);
     }
@@ -81,6 +87,10 @@ impl TreeSink<uint> for Sink {
         println!("Remove {:u} from parent", target);
     }
 
+    fn reparent_children(&mut self, node: uint, new_parent: uint) {
+        println!("Move children from {:u} to {:u}", node, new_parent);
+    }
+
     fn mark_script_already_started(&mut self, node: uint) {
         println!("Mark script {:u} as already started", node);
     }
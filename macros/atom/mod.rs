@@ -76,6 +76,9 @@ impl MacResult for AtomResult {
 }
 
 // Translate `atom!(title)` or `atom!("font-weight")` into an `Atom` constant or pattern.
+//
+// The expansion names the `html5ever-atom` crate as `::atom`, so any crate
+// using this macro needs `extern crate atom = "html5ever-atom";` at its root.
 pub fn expand_atom(cx: &mut ExtCtxt, sp: Span, tt: &[TokenTree]) -> Box<MacResult> {
     let usage = "Usage: atom!(html) or atom!(\"font-weight\")";
     let name = match tt {
@@ -87,7 +90,7 @@ pub fn expand_atom(cx: &mut ExtCtxt, sp: Span, tt: &[TokenTree]) -> Box<MacResul
         format!("Unknown static atom {:s}", name.get()).as_slice());
 
     box AtomResult {
-        expr: quote_expr!(&mut *cx, ::util::atom::Static($i)),
-        pat: quote_pat!(&mut *cx, ::util::atom::Static($i)),
+        expr: quote_expr!(&mut *cx, ::atom::Static($i)),
+        pat: quote_pat!(&mut *cx, ::atom::Static($i)),
     } as Box<MacResult>
 }
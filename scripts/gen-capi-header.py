@@ -0,0 +1,146 @@
+#!/usr/bin/env python
+# Copyright 2014 The html5ever Project Developers. See the
+# COPYRIGHT file at the top-level directory of this distribution.
+#
+# Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+# http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+# <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+# option. This file may not be copied, modified, or distributed
+# except according to those terms.
+
+import os
+import re
+import sys
+
+# Generate capi/html5ever.h from the Rust sources in src/capi.
+#
+# This understands only the small subset of Rust used there:
+#   #[repr(C)] pub struct h5e_foo { pub field: Type, ... }
+#   pub struct h5e_foo { ... }             (opaque)
+#   pub static H5E_FOO: c_int = N;
+#   #[no_mangle] pub unsafe extern "C" fn h5e_foo(arg: Type, ...) -> Type
+
+SOURCES = ['mod.rs', 'tokenizer.rs', 'tree_builder.rs']
+
+SIMPLE_TYPES = {
+    'c_int': 'int',
+    'size_t': 'size_t',
+    'u8': 'char',
+    'c_void': 'void',
+}
+
+def split_args(s):
+    # Split on commas not nested in parentheses.
+    args, depth, cur = [], 0, ''
+    for c in s:
+        if c == ',' and depth == 0:
+            args.append(cur)
+            cur = ''
+            continue
+        if c == '(':
+            depth += 1
+        elif c == ')':
+            depth -= 1
+        cur += c
+    if cur.strip():
+        args.append(cur)
+    return [a.strip() for a in args if a.strip()]
+
+def c_type(ty):
+    ty = ty.strip()
+    m = re.match(r'^\*(const|mut)\s+(.*)$', ty)
+    if m:
+        inner = c_type(m.group(2))
+        if m.group(1) == 'const':
+            inner = 'const ' + inner
+        return inner + ' *'
+    if ty in SIMPLE_TYPES:
+        return SIMPLE_TYPES[ty]
+    if ty.startswith('h5e_'):
+        return 'struct ' + ty
+    raise Exception('unknown type: %s' % ty)
+
+def c_decl(ty, name):
+    # Declare `name` with Rust type `ty`.
+    m = re.match(r'^Option<extern\s+"C"\s+fn\s*\((.*)\)\s*(->\s*(.*))?>$', ty.strip())
+    if m:
+        ret = 'void' if m.group(3) is None else c_type(m.group(3))
+        return '%s (*%s)(%s)' % (ret, name, c_params(m.group(1)))
+    t = c_type(ty)
+    sep = '' if t.endswith('*') else ' '
+    return t + sep + name
+
+def c_params(s):
+    params = []
+    for arg in split_args(s):
+        name, ty = arg.split(':', 1)
+        params.append(c_decl(ty, name.strip()))
+    return ', '.join(params) or 'void'
+
+def main(src_dir, out):
+    opaque, structs, consts, fns = [], [], [], []
+
+    for name in SOURCES:
+        with open(os.path.join(src_dir, name)) as f:
+            src = f.read()
+
+        for m in re.finditer(r'(#\[repr\(C\)\]\s*)?pub struct (h5e_\w+)\s*\{(.*?)\n\}', src, re.S):
+            if not m.group(1):
+                opaque.append(m.group(2))
+                continue
+            fields = []
+            for line in m.group(3).split('\n'):
+                fm = re.match(r'^\s*pub (\w+):\s*(.*),\s*$', line)
+                if fm:
+                    fields.append(c_decl(fm.group(2), fm.group(1)))
+            structs.append((m.group(2), fields))
+
+        for m in re.finditer(r'pub static (H5E_\w+): c_int = (-?\d+);', src):
+            consts.append((m.group(1), m.group(2)))
+
+        lines = src.split('\n')
+        for i, line in enumerate(lines):
+            if line.strip() != '#[no_mangle]':
+                continue
+            m = re.match(r'^pub unsafe extern "C" fn (h5e_\w+)\((.*)\)\s*(->\s*(.*?))?\s*\{$', lines[i+1])
+            ret = 'void' if m.group(4) is None else c_type(m.group(4))
+            doc, j = [], i - 1
+            while lines[j].startswith('///'):
+                doc.insert(0, lines[j][3:].strip())
+                j -= 1
+            fns.append((doc, ret, m.group(1), c_params(m.group(2))))
+
+    w = out.write
+    w('/* Generated by scripts/gen-capi-header.py; do not edit. */\n\n')
+    w('#ifndef HTML5EVER_H\n#define HTML5EVER_H\n\n')
+    w('#include <stddef.h>\n\n')
+    w('#ifdef __cplusplus\nextern "C" {\n#endif\n\n')
+
+    for name in opaque:
+        w('struct %s;\n' % name)
+    w('\n')
+
+    for name, value in consts:
+        w('#define %s %s\n' % (name, value))
+    w('\n')
+
+    for name, fields in structs:
+        w('struct %s {\n' % name)
+        for f in fields:
+            w('    %s;\n' % f)
+        w('};\n\n')
+
+    for doc, ret, name, params in fns:
+        for line in doc:
+            w(('/* %s */\n' % line).replace('  */', ' */'))
+        sep = '' if ret.endswith('*') else ' '
+        w('%s%s%s(%s);\n\n' % (ret, sep, name, params))
+
+    w('#ifdef __cplusplus\n}\n#endif\n\n')
+    w('#endif\n')
+
+if __name__ == '__main__':
+    if len(sys.argv) != 2:
+        sys.stderr.write('Usage: %s path/to/src/capi > html5ever.h\n' % sys.argv[0])
+        sys.exit(1)
+    main(sys.argv[1], sys.stdout)
@@ -0,0 +1,79 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! C API for the tokenizer and tree builder.
+//!
+//! The C declarations live in `capi/html5ever.h`, which is generated
+//! from this module by `scripts/gen-capi-header.py`.
+//!
+//! Strings cross the boundary as `h5e_buf`: a pointer and a length in
+//! bytes, UTF-8 encoded and not NUL-terminated.  A buffer passed to a
+//! callback is only valid for the duration of that callback.
+
+#![allow(non_camel_case_types)]
+
+use util::utf8::Utf8Decoder;
+
+use libc::size_t;
+
+use std::ptr;
+use std::slice;
+
+pub mod tokenizer;
+pub mod tree_builder;
+
+/// A borrowed UTF-8 string.
+#[repr(C)]
+pub struct h5e_buf {
+    pub data: *const u8,
+    pub len: size_t,
+}
+
+impl h5e_buf {
+    /// A buffer with a null pointer, used for absent values.
+    pub fn null() -> h5e_buf {
+        h5e_buf {
+            data: ptr::null(),
+            len: 0,
+        }
+    }
+
+    pub fn from_slice(s: &str) -> h5e_buf {
+        h5e_buf {
+            data: s.as_ptr(),
+            len: s.len() as size_t,
+        }
+    }
+
+    pub fn from_opt(s: &Option<String>) -> h5e_buf {
+        match *s {
+            Some(ref s) => h5e_buf::from_slice(s.as_slice()),
+            None => h5e_buf::null(),
+        }
+    }
+
+    /// Copy the contents into a `String`, replacing invalid UTF-8
+    /// with U+FFFD.
+    pub unsafe fn to_string(&self) -> String {
+        if self.data.is_null() {
+            return String::new();
+        }
+        slice::raw::buf_as_slice(self.data, self.len as uint, |bytes|
+            String::from_utf8_lossy(bytes).into_string())
+    }
+
+    /// Decode the contents with `decoder`, which holds back a UTF-8
+    /// sequence split at the end of the buffer until the next call.
+    pub unsafe fn decode(&self, decoder: &mut Utf8Decoder) -> String {
+        if self.data.is_null() {
+            return String::new();
+        }
+        slice::raw::buf_as_slice(self.data, self.len as uint, |bytes| decoder.decode(bytes))
+    }
+}
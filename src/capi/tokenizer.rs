@@ -0,0 +1,132 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use capi::h5e_buf;
+
+use tokenizer::{TokenSink, Token, Tokenizer, Doctype, Tag, StartTag, EndTag};
+use tokenizer::{DoctypeToken, TagToken, CommentToken, CharacterTokens};
+use tokenizer::{NullCharacterToken, EOFToken, ParseError};
+use util::utf8::Utf8Decoder;
+
+use libc::{c_void, c_int, size_t};
+
+use std::mem;
+use std::ptr;
+use std::default::Default;
+
+/// Callbacks which receive tokens.  Any of them may be null.
+#[repr(C)]
+pub struct h5e_token_ops {
+    pub do_doctype: Option<extern "C" fn(user: *mut c_void, name: h5e_buf, public_id: h5e_buf, system_id: h5e_buf, force_quirks: c_int)>,
+    pub do_start_tag: Option<extern "C" fn(user: *mut c_void, name: h5e_buf, self_closing: c_int, num_attrs: size_t)>,
+    pub do_tag_attr: Option<extern "C" fn(user: *mut c_void, name: h5e_buf, value: h5e_buf)>,
+    pub do_end_tag: Option<extern "C" fn(user: *mut c_void, name: h5e_buf)>,
+    pub do_comment: Option<extern "C" fn(user: *mut c_void, text: h5e_buf)>,
+    pub do_chars: Option<extern "C" fn(user: *mut c_void, text: h5e_buf)>,
+    pub do_null_char: Option<extern "C" fn(user: *mut c_void)>,
+    pub do_eof: Option<extern "C" fn(user: *mut c_void)>,
+    pub do_error: Option<extern "C" fn(user: *mut c_void, message: h5e_buf)>,
+}
+
+struct CTokenSink {
+    ops: *const h5e_token_ops,
+    user: *mut c_void,
+}
+
+impl TokenSink for CTokenSink {
+    fn process_token(&mut self, token: Token) {
+        macro_rules! call ( ($name:ident $(, $arg:expr)*) => (
+            unsafe {
+                match (*self.ops).$name {
+                    None => (),
+                    Some(f) => f(self.user $(, $arg)*),
+                }
+            }
+        ))
+
+        match token {
+            DoctypeToken(Doctype { name, public_id, system_id, force_quirks }) => {
+                call!(do_doctype, h5e_buf::from_opt(&name),
+                    h5e_buf::from_opt(&public_id), h5e_buf::from_opt(&system_id),
+                    force_quirks as c_int);
+            }
+
+            TagToken(Tag { kind, name, self_closing, attrs }) => match kind {
+                StartTag => {
+                    call!(do_start_tag, h5e_buf::from_slice(name.as_slice()),
+                        self_closing as c_int, attrs.len() as size_t);
+                    for attr in attrs.iter() {
+                        call!(do_tag_attr, h5e_buf::from_slice(attr.name.as_slice()),
+                            h5e_buf::from_slice(attr.value.as_slice()));
+                    }
+                }
+                EndTag => call!(do_end_tag, h5e_buf::from_slice(name.as_slice())),
+            },
+
+            CommentToken(text) => call!(do_comment, h5e_buf::from_slice(text.as_slice())),
+            CharacterTokens(text) => call!(do_chars, h5e_buf::from_slice(text.as_slice())),
+            NullCharacterToken => call!(do_null_char),
+            EOFToken => call!(do_eof),
+            ParseError(msg) => call!(do_error, h5e_buf::from_slice(msg.as_slice())),
+        }
+    }
+}
+
+/// A tokenizer which sends tokens to C callbacks.
+pub struct h5e_tokenizer {
+    tok: Tokenizer<CTokenSink>,
+    decoder: Utf8Decoder,
+}
+
+/// Create a tokenizer.  `ops` must remain valid until the tokenizer is
+/// freed.  `user` is passed as the first argument to every callback.
+/// Returns NULL if `ops` is null.
+#[no_mangle]
+pub unsafe extern "C" fn h5e_tokenizer_new(ops: *const h5e_token_ops, user: *mut c_void) -> *mut h5e_tokenizer {
+    if ops.is_null() {
+        return ptr::mut_null();
+    }
+    let sink = CTokenSink {
+        ops: ops,
+        user: user,
+    };
+    let tok = box h5e_tokenizer {
+        tok: Tokenizer::new(sink, Default::default()),
+        decoder: Utf8Decoder::new(),
+    };
+    mem::transmute(tok)
+}
+
+/// Free a tokenizer created by `h5e_tokenizer_new`.  Does nothing if
+/// `tok` is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn h5e_tokenizer_free(tok: *mut h5e_tokenizer) {
+    if tok.is_null() {
+        return;
+    }
+    let _: Box<h5e_tokenizer> = mem::transmute(tok);
+}
+
+/// Feed a chunk of UTF-8 input.  A character may be split across
+/// chunks.
+#[no_mangle]
+pub unsafe extern "C" fn h5e_tokenizer_feed(tok: *mut h5e_tokenizer, buf: h5e_buf) {
+    let input = buf.decode(&mut (*tok).decoder);
+    (*tok).tok.feed(input);
+}
+
+/// Signal the end of input.
+#[no_mangle]
+pub unsafe extern "C" fn h5e_tokenizer_end(tok: *mut h5e_tokenizer) {
+    let rest = (*tok).decoder.finish();
+    if !rest.is_empty() {
+        (*tok).tok.feed(rest);
+    }
+    (*tok).tok.end();
+}
@@ -0,0 +1,245 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use capi::h5e_buf;
+
+use atom::Atom;
+use atom::namespace::{Namespace, HTML};
+use tokenizer::{Attribute, Tokenizer};
+use tree_builder::{TreeBuilder, TreeSink, QuirksMode, Quirks, LimitedQuirks, NoQuirks};
+use util::unsupported::DropUnsupported;
+use util::utf8::Utf8Decoder;
+
+use libc::{c_void, c_int, size_t};
+
+use std::mem;
+use std::ptr;
+use std::default::Default;
+
+pub static H5E_NO_QUIRKS: c_int = 0;
+pub static H5E_LIMITED_QUIRKS: c_int = 1;
+pub static H5E_QUIRKS: c_int = 2;
+
+/// An attribute name and value.
+#[repr(C)]
+pub struct h5e_attr {
+    pub name: h5e_buf,
+    pub value: h5e_buf,
+}
+
+/// Callbacks which build the tree, mirroring `TreeSink`.
+///
+/// A node handle is an arbitrary `void *`, which must stay valid until
+/// the parser is freed.  `get_document`, `same_node`, `elem_name` and
+/// `create_element` are required; the rest may be null.
+///
+/// `append_before_sibling` returns nonzero if it inserted `child`, or
+/// zero if `sibling` has no parent.
+#[repr(C)]
+pub struct h5e_tree_ops {
+    pub parse_error: Option<extern "C" fn(user: *mut c_void, message: h5e_buf)>,
+    pub get_document: Option<extern "C" fn(user: *mut c_void) -> *mut c_void>,
+    pub same_node: Option<extern "C" fn(user: *mut c_void, x: *mut c_void, y: *mut c_void) -> c_int>,
+    pub elem_name: Option<extern "C" fn(user: *mut c_void, target: *mut c_void) -> h5e_buf>,
+    pub set_quirks_mode: Option<extern "C" fn(user: *mut c_void, mode: c_int)>,
+    pub create_element: Option<extern "C" fn(user: *mut c_void, name: h5e_buf, attrs: *const h5e_attr, num_attrs: size_t) -> *mut c_void>,
+    pub append_text: Option<extern "C" fn(user: *mut c_void, parent: *mut c_void, text: h5e_buf)>,
+    pub append_comment: Option<extern "C" fn(user: *mut c_void, parent: *mut c_void, text: h5e_buf)>,
+    pub append_element: Option<extern "C" fn(user: *mut c_void, parent: *mut c_void, child: *mut c_void)>,
+    pub append_doctype_to_document: Option<extern "C" fn(user: *mut c_void, name: h5e_buf, public_id: h5e_buf, system_id: h5e_buf)>,
+    pub add_attrs_if_missing: Option<extern "C" fn(user: *mut c_void, target: *mut c_void, attrs: *const h5e_attr, num_attrs: size_t)>,
+    pub remove_from_parent: Option<extern "C" fn(user: *mut c_void, target: *mut c_void)>,
+    pub reparent_children: Option<extern "C" fn(user: *mut c_void, node: *mut c_void, new_parent: *mut c_void)>,
+    pub mark_script_already_started: Option<extern "C" fn(user: *mut c_void, target: *mut c_void)>,
+    pub root_element_inserted: Option<extern "C" fn(user: *mut c_void, root: *mut c_void, attrs: *const h5e_attr, num_attrs: size_t)>,
+    pub associate_with_form: Option<extern "C" fn(user: *mut c_void, target: *mut c_void, form: *mut c_void)>,
+    pub append_before_sibling: Option<extern "C" fn(user: *mut c_void, sibling: *mut c_void, child: *mut c_void) -> c_int>,
+}
+
+struct CTreeSink {
+    ops: *const h5e_tree_ops,
+    user: *mut c_void,
+
+    // The required callbacks, checked for null by `h5e_parser_new`.
+    get_document: extern "C" fn(user: *mut c_void) -> *mut c_void,
+    same_node: extern "C" fn(user: *mut c_void, x: *mut c_void, y: *mut c_void) -> c_int,
+    elem_name: extern "C" fn(user: *mut c_void, target: *mut c_void) -> h5e_buf,
+    create_element: extern "C" fn(user: *mut c_void, name: h5e_buf, attrs: *const h5e_attr, num_attrs: size_t) -> *mut c_void,
+}
+
+fn c_attrs(attrs: &[Attribute]) -> Vec<h5e_attr> {
+    attrs.iter().map(|a| h5e_attr {
+        name: h5e_buf::from_slice(a.name.as_slice()),
+        value: h5e_buf::from_slice(a.value.as_slice()),
+    }).collect()
+}
+
+// Call an optional callback.
+macro_rules! call ( ($name:ident $(, $arg:expr)*) => (
+    unsafe {
+        match (*self.ops).$name {
+            None => (),
+            Some(f) => f(self.user $(, $arg)*),
+        }
+    }
+))
+
+impl TreeSink<*mut c_void> for CTreeSink {
+    fn parse_error(&mut self, msg: String) {
+        call!(parse_error, h5e_buf::from_slice(msg.as_slice()));
+    }
+
+    fn get_document(&mut self) -> *mut c_void {
+        (self.get_document)(self.user)
+    }
+
+    fn same_node(&self, x: *mut c_void, y: *mut c_void) -> bool {
+        (self.same_node)(self.user, x, y) != 0
+    }
+
+    fn elem_name(&self, target: *mut c_void) -> (Namespace, Atom) {
+        let name = (self.elem_name)(self.user, target);
+        (HTML, Atom::from_buf(unsafe { name.to_string() }))
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        call!(set_quirks_mode, match mode {
+            NoQuirks => H5E_NO_QUIRKS,
+            LimitedQuirks => H5E_LIMITED_QUIRKS,
+            Quirks => H5E_QUIRKS,
+        });
+    }
+
+    // The tree builder only creates HTML elements, so there's no
+    // namespace to pass on.
+    fn create_element(&mut self, _ns: Namespace, name: Atom, attrs: Vec<Attribute>) -> *mut c_void {
+        let attrs = c_attrs(attrs.as_slice());
+        (self.create_element)(self.user, h5e_buf::from_slice(name.as_slice()),
+            attrs.as_ptr(), attrs.len() as size_t)
+    }
+
+    fn append_text(&mut self, parent: *mut c_void, text: String) {
+        call!(append_text, parent, h5e_buf::from_slice(text.as_slice()));
+    }
+
+    fn append_comment(&mut self, parent: *mut c_void, text: String) {
+        call!(append_comment, parent, h5e_buf::from_slice(text.as_slice()));
+    }
+
+    fn append_element(&mut self, parent: *mut c_void, child: *mut c_void) {
+        call!(append_element, parent, child);
+    }
+
+    fn append_before_sibling(&mut self, sibling: *mut c_void, new_node: *mut c_void) -> Result<(), *mut c_void> {
+        let inserted = unsafe {
+            match (*self.ops).append_before_sibling {
+                None => 0,
+                Some(f) => f(self.user, sibling, new_node),
+            }
+        };
+        if inserted != 0 { Ok(()) } else { Err(new_node) }
+    }
+
+    fn append_doctype_to_document(&mut self, name: String, public_id: String, system_id: String) {
+        call!(append_doctype_to_document, h5e_buf::from_slice(name.as_slice()),
+            h5e_buf::from_slice(public_id.as_slice()), h5e_buf::from_slice(system_id.as_slice()));
+    }
+
+    fn add_attrs_if_missing(&mut self, target: *mut c_void, attrs: Vec<Attribute>) {
+        let attrs = c_attrs(attrs.as_slice());
+        call!(add_attrs_if_missing, target, attrs.as_ptr(), attrs.len() as size_t);
+    }
+
+    fn remove_from_parent(&mut self, target: *mut c_void) {
+        call!(remove_from_parent, target);
+    }
+
+    fn reparent_children(&mut self, node: *mut c_void, new_parent: *mut c_void) {
+        call!(reparent_children, node, new_parent);
+    }
+
+    fn mark_script_already_started(&mut self, target: *mut c_void) {
+        call!(mark_script_already_started, target);
+    }
+
+    fn root_element_inserted(&mut self, root: *mut c_void, attrs: &[Attribute]) {
+        let attrs = c_attrs(attrs);
+        call!(root_element_inserted, root, attrs.as_ptr(), attrs.len() as size_t);
+    }
+
+    fn associate_with_form(&mut self, target: *mut c_void, form: *mut c_void) {
+        call!(associate_with_form, target, form);
+    }
+}
+
+/// A parser (tokenizer and tree builder) which builds a tree through
+/// C callbacks.
+pub struct h5e_parser {
+    tok: Tokenizer<DropUnsupported<TreeBuilder<*mut c_void, CTreeSink>>>,
+    decoder: Utf8Decoder,
+}
+
+/// Create a parser.  `ops` must remain valid until the parser is freed.
+/// `user` is passed as the first argument to every callback.  Returns
+/// NULL if `ops` or one of its required callbacks is null.
+///
+/// `<svg>`, `<math>`, `<template>` and `<isindex>` tags are dropped,
+/// because the tree builder can't handle them yet and must not fail
+/// across the C boundary.  Their contents are parsed as HTML.
+#[no_mangle]
+pub unsafe extern "C" fn h5e_parser_new(ops: *const h5e_tree_ops, user: *mut c_void) -> *mut h5e_parser {
+    if ops.is_null() {
+        return ptr::mut_null();
+    }
+    let sink = match ((*ops).get_document, (*ops).same_node, (*ops).elem_name, (*ops).create_element) {
+        (Some(get_document), Some(same_node), Some(elem_name), Some(create_element)) => CTreeSink {
+            ops: ops,
+            user: user,
+            get_document: get_document,
+            same_node: same_node,
+            elem_name: elem_name,
+            create_element: create_element,
+        },
+        _ => return ptr::mut_null(),
+    };
+    let tb = TreeBuilder::new(sink, Default::default());
+    let parser = box h5e_parser {
+        tok: Tokenizer::new(DropUnsupported { inner: tb }, Default::default()),
+        decoder: Utf8Decoder::new(),
+    };
+    mem::transmute(parser)
+}
+
+/// Free a parser created by `h5e_parser_new`.  Does nothing if
+/// `parser` is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn h5e_parser_free(parser: *mut h5e_parser) {
+    if parser.is_null() {
+        return;
+    }
+    let _: Box<h5e_parser> = mem::transmute(parser);
+}
+
+/// Feed a chunk of UTF-8 input.  A character may be split across
+/// chunks.
+#[no_mangle]
+pub unsafe extern "C" fn h5e_parser_feed(parser: *mut h5e_parser, buf: h5e_buf) {
+    let input = buf.decode(&mut (*parser).decoder);
+    (*parser).tok.feed(input);
+}
+
+/// Signal the end of input.
+#[no_mangle]
+pub unsafe extern "C" fn h5e_parser_end(parser: *mut h5e_parser) {
+    let rest = (*parser).decoder.finish();
+    if !rest.is_empty() {
+        (*parser).tok.feed(rest);
+    }
+    (*parser).tok.end();
+}
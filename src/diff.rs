@@ -0,0 +1,523 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Structural diffs between two parsed documents.
+//!
+//! The children of each pair of corresponding nodes are aligned by a
+//! longest common subsequence of identical subtrees.  Unmatched children
+//! with the same element name (or two text nodes) are then paired up and
+//! compared recursively, and the rest are deletions and insertions.  A
+//! deleted subtree which is identical to an inserted one becomes a move.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let old: RcDom = parse(one_input(yesterday), Default::default());
+//! let new: RcDom = parse(one_input(today), Default::default());
+//! for edit in diff(&old.document, &new.document).iter() {
+//!     ...
+//! }
+//! let html = render_diff(&old.document, &new.document);
+//! ```
+
+use atom::Atom;
+use atom::namespace::HTML;
+use sink::rcdom::{Handle, Document, Doctype, Text, Comment, Element};
+use serialize::{Serializable, Serializer, AttrRef, serialize};
+use tokenizer::Attribute;
+use util::str::is_ascii_whitespace;
+
+use std::cmp::max;
+use std::default::Default;
+use std::io::{Writer, IoResult, MemWriter};
+
+/// The position of a node, as the index of each ancestor within its
+/// parent, starting below the root of the diff.
+pub type Path = Vec<uint>;
+
+/// One step of an edit script.
+///
+/// `Delete`, `UpdateText`, `UpdateAttribute` and the source of `Move`
+/// give paths in the old document.  `Insert` and the destination of
+/// `Move` give paths in the new document.
+#[deriving(Clone)]
+pub enum Edit {
+    /// Insert a copy of this node from the new document, with its
+    /// descendants.
+    Insert(Path, Handle),
+
+    /// Delete a node and its descendants.
+    Delete(Path),
+
+    /// Move a node and its descendants.
+    Move(Path, Path),
+
+    /// Change the data of a text or comment node, from the first string
+    /// to the second.
+    UpdateText(Path, String, String),
+
+    /// Change an attribute of an element.  `None` means the attribute is
+    /// absent.
+    UpdateAttribute(Path, Atom, Option<String>, Option<String>),
+}
+
+fn same_attrs(x: &[Attribute], y: &[Attribute]) -> bool {
+    x.len() == y.len() && x.iter().all(|a| y.iter().any(|b| a == b))
+}
+
+// Are these identical subtrees?  Attribute order doesn't matter.
+fn same_tree(x: &Handle, y: &Handle) -> bool {
+    let (x, y) = (x.borrow(), y.borrow());
+    let same = match (&x.node, &y.node) {
+        (&Document, &Document) => true,
+        (&Doctype(ref n1, ref p1, ref s1), &Doctype(ref n2, ref p2, ref s2))
+            => n1 == n2 && p1 == p2 && s1 == s2,
+        (&Text(ref t1), &Text(ref t2)) => t1 == t2,
+        (&Comment(ref t1), &Comment(ref t2)) => t1 == t2,
+        (&Element(ref n1, ref a1), &Element(ref n2, ref a2))
+            => n1 == n2 && same_attrs(a1.as_slice(), a2.as_slice()),
+        _ => false,
+    };
+    same && x.children.len() == y.children.len()
+        && x.children.iter().zip(y.children.iter()).all(|(a, b)| same_tree(a, b))
+}
+
+// Can these nodes be compared as changed versions of each other?
+fn similar(x: &Handle, y: &Handle) -> bool {
+    match (&x.borrow().node, &y.borrow().node) {
+        (&Document, &Document) | (&Doctype(..), &Doctype(..))
+        | (&Text(_), &Text(_)) | (&Comment(_), &Comment(_)) => true,
+        (&Element(ref n1, _), &Element(ref n2, _)) => n1 == n2,
+        _ => false,
+    }
+}
+
+// How a child of the old node corresponds to a child of the new one.
+enum Step {
+    Same(uint, uint),
+    Changed(uint, uint),
+    Deleted(uint),
+    Inserted(uint),
+}
+
+// Align two lists of children, in the order they should be rendered.
+fn align(old: &[Handle], new: &[Handle]) -> Vec<Step> {
+    let (n, m) = (old.len(), new.len());
+    let w = m + 1;
+
+    // eq[i*w + j]: are old[i] and new[j] the same subtree?
+    // lcs[i*w + j]: length of the LCS of old[i..] and new[j..].
+    let mut eq = Vec::from_elem(n * w, false);
+    let mut lcs = Vec::from_elem((n + 1) * w, 0u);
+    for i in range(0, n).rev() {
+        for j in range(0, m).rev() {
+            let same = same_tree(&old[i], &new[j]);
+            *eq.get_mut(i*w + j) = same;
+            *lcs.get_mut(i*w + j) = if same {
+                *lcs.get((i+1)*w + j + 1) + 1
+            } else {
+                max(*lcs.get((i+1)*w + j), *lcs.get(i*w + j + 1))
+            };
+        }
+    }
+
+    let mut anchors = vec!();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if *eq.get(i*w + j) {
+            anchors.push((i, j));
+            i += 1;
+            j += 1;
+        } else if *lcs.get((i+1)*w + j) >= *lcs.get(i*w + j + 1) {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    anchors.push((n, m));
+
+    // Between anchors, pair up similar nodes in order.
+    let mut steps = vec!();
+    let (mut pi, mut pj) = (0, 0);
+    for &(ai, aj) in anchors.iter() {
+        let mut next = pj;
+        for oi in range(pi, ai) {
+            match range(next, aj).find(|&nj| similar(&old[oi], &new[nj])) {
+                Some(nj) => {
+                    steps.extend(range(next, nj).map(|k| Inserted(k)));
+                    steps.push(Changed(oi, nj));
+                    next = nj + 1;
+                }
+                None => steps.push(Deleted(oi)),
+            }
+        }
+        steps.extend(range(next, aj).map(|k| Inserted(k)));
+        if ai < n {
+            steps.push(Same(ai, aj));
+        }
+        pi = ai + 1;
+        pj = aj + 1;
+    }
+    steps
+}
+
+fn child_path(path: &Path, i: uint) -> Path {
+    let mut p = path.clone();
+    p.push(i);
+    p
+}
+
+struct Differ {
+    // Removed edits are left as None until the end.
+    edits: Vec<Option<Edit>>,
+
+    // Deleted and inserted nodes, with the index of their edit, for
+    // finding moves.
+    deleted: Vec<(uint, Handle)>,
+    inserted: Vec<(uint, Handle)>,
+}
+
+impl Differ {
+    fn push(&mut self, edit: Edit) -> uint {
+        self.edits.push(Some(edit));
+        self.edits.len() - 1
+    }
+
+    fn delete(&mut self, path: Path, node: &Handle) {
+        let i = self.push(Delete(path));
+        self.deleted.push((i, node.clone()));
+    }
+
+    fn insert(&mut self, path: Path, node: &Handle) {
+        let i = self.push(Insert(path, node.clone()));
+        self.inserted.push((i, node.clone()));
+    }
+
+    // Diff two similar nodes.
+    fn nodes(&mut self, old: &Handle, new: &Handle, old_path: Path, new_path: Path) {
+        match (&old.borrow().node, &new.borrow().node) {
+            (&Text(ref t1), &Text(ref t2)) | (&Comment(ref t1), &Comment(ref t2)) => {
+                if t1 != t2 {
+                    self.push(UpdateText(old_path, t1.clone(), t2.clone()));
+                }
+                return;
+            }
+
+            (&Doctype(..), &Doctype(..)) => {
+                if !same_tree(old, new) {
+                    self.delete(old_path, old);
+                    self.insert(new_path, new);
+                }
+                return;
+            }
+
+            (&Element(_, ref a1), &Element(_, ref a2)) => {
+                for a in a1.iter() {
+                    match a2.iter().find(|b| b.name == a.name) {
+                        Some(b) if b.value == a.value => (),
+                        b => {
+                            self.push(UpdateAttribute(old_path.clone(), a.name.name.clone(),
+                                Some(a.value.clone()), b.map(|b| b.value.clone())));
+                        }
+                    }
+                }
+                for b in a2.iter().filter(|b| !a1.iter().any(|a| a.name == b.name)) {
+                    self.push(UpdateAttribute(old_path.clone(), b.name.name.clone(),
+                        None, Some(b.value.clone())));
+                }
+            }
+
+            _ => (),
+        }
+
+        let (old, new) = (old.borrow(), new.borrow());
+        let (oc, nc) = (old.children.as_slice(), new.children.as_slice());
+        for step in align(oc, nc).move_iter() {
+            match step {
+                Same(..) => (),
+                Changed(i, j) => self.nodes(&oc[i], &nc[j],
+                    child_path(&old_path, i), child_path(&new_path, j)),
+                Deleted(i) => self.delete(child_path(&old_path, i), &oc[i]),
+                Inserted(j) => self.insert(child_path(&new_path, j), &nc[j]),
+            }
+        }
+    }
+
+    // Replace each deletion which matches an insertion with a move.
+    fn find_moves(&mut self) {
+        let mut inserted = self.inserted.clone();
+        for &(di, ref node) in self.deleted.iter() {
+            let pos = match inserted.iter().position(|&(_, ref other)| same_tree(node, other)) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let (ii, _) = inserted.remove(pos).expect("no insertion");
+            let from = match self.edits.get_mut(di).take() {
+                Some(Delete(p)) => p,
+                _ => fail!("not a deletion"),
+            };
+            let to = match self.edits.get_mut(ii).take() {
+                Some(Insert(p, _)) => p,
+                _ => fail!("not an insertion"),
+            };
+            *self.edits.get_mut(di) = Some(Move(from, to));
+        }
+    }
+}
+
+/// Compute an edit script which turns `old` into `new`.
+///
+/// The roots are compared as corresponding nodes, so they should both
+/// be documents, or elements with the same name.
+pub fn diff(old: &Handle, new: &Handle) -> Vec<Edit> {
+    let mut d = Differ {
+        edits: vec!(),
+        deleted: vec!(),
+        inserted: vec!(),
+    };
+    d.nodes(old, new, vec!(), vec!());
+    d.find_moves();
+    d.edits.move_iter().filter_map(|e| e).collect()
+}
+
+// Elements which are shown as their new version rather than marked up,
+// because <ins> and <del> wouldn't work inside them.
+fn is_opaque(name: &Atom) -> bool {
+    match *name {
+        atom!(head) | atom!(title) | atom!(textarea) | atom!(script) | atom!(style)
+        | atom!(xmp) | atom!(iframe) | atom!(noembed) | atom!(noframes)
+        | atom!(noscript) | atom!(plaintext) | atom!(option) | atom!(optgroup)
+            => true,
+        _ => false,
+    }
+}
+
+// Elements which can't be wrapped in <ins> or <del>, so the markers go
+// inside them instead.
+fn marks_inside(name: &Atom) -> bool {
+    match *name {
+        atom!(thead) | atom!(tbody) | atom!(tfoot) | atom!(tr) | atom!(td) | atom!(th)
+        | atom!(caption) | atom!(colgroup) | atom!(li) | atom!(dt) | atom!(dd)
+            => true,
+        _ => false,
+    }
+}
+
+// Elements which can't hold markers either way.  They are shown if
+// inserted and left out if deleted, like a doctype.
+fn is_unmarkable(name: &Atom) -> bool {
+    match *name {
+        atom!(head) | atom!(col) | atom!(option) | atom!(optgroup) => true,
+        _ => false,
+    }
+}
+
+/// The new version of a document, with deleted content wrapped in `<del>`
+/// and inserted content wrapped in `<ins>`.  Table rows and cells, list
+/// items and the like get the markers inside them instead, and `<head>`
+/// and `<option>` elements are shown unmarked if inserted and left out
+/// if deleted.
+///
+/// Changed text is shown as the old text deleted and the new text
+/// inserted, and moved content is shown deleted from its old place and
+/// inserted in the new one.  Attribute changes are not marked.
+pub struct Diffed {
+    pub old: Handle,
+    pub new: Handle,
+}
+
+impl Diffed {
+    // Show `node` without markers if it was inserted, and not at all if
+    // it was deleted.
+    fn unmarked<'wr, Wr: Writer>(&self, serializer: &mut Serializer<'wr, Wr>,
+            tag: Atom, node: &Handle) -> IoResult<()> {
+        if tag == atom!(ins) {
+            node.serialize(serializer, true)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn wrapped<'wr, Wr: Writer>(&self, serializer: &mut Serializer<'wr, Wr>,
+            tag: Atom, node: &Handle) -> IoResult<()> {
+        let n = node.borrow();
+        match n.node {
+            // Whitespace between rows and cells can't be marked, and
+            // marking it elsewhere shows nothing.
+            Text(ref t) if t.as_slice().chars().all(is_ascii_whitespace)
+                => return self.unmarked(serializer, tag, node),
+            Doctype(..) => return self.unmarked(serializer, tag, node),
+            Element(ref name, _) if is_unmarkable(name) => return self.unmarked(serializer, tag, node),
+            Element(ref name, ref attrs) if marks_inside(name) => {
+                try!(serializer.start_elem(HTML, name.clone(),
+                    attrs.iter().map(|at| (&at.name, at.value.as_slice()))));
+                for child in n.children.iter() {
+                    try!(self.wrapped(serializer, tag.clone(), child));
+                }
+                return serializer.end_elem(HTML, name.clone());
+            }
+            _ => (),
+        }
+        let no_attrs: Vec<AttrRef> = vec!();
+        try!(serializer.start_elem(HTML, tag.clone(), no_attrs.move_iter()));
+        try!(node.serialize(serializer, true));
+        serializer.end_elem(HTML, tag)
+    }
+
+    fn serialize_children<'wr, Wr: Writer>(&self, serializer: &mut Serializer<'wr, Wr>,
+            old: &Handle, new: &Handle) -> IoResult<()> {
+        let (old, new) = (old.borrow(), new.borrow());
+        let (oc, nc) = (old.children.as_slice(), new.children.as_slice());
+        for step in align(oc, nc).move_iter() {
+            try!(match step {
+                Same(_, j) => nc[j].serialize(serializer, true),
+                Changed(i, j) => self.serialize_pair(serializer, &oc[i], &nc[j]),
+                Deleted(i) => self.wrapped(serializer, atom!(del), &oc[i]),
+                Inserted(j) => self.wrapped(serializer, atom!(ins), &nc[j]),
+            });
+        }
+        Ok(())
+    }
+
+    // Serialize two similar nodes.
+    fn serialize_pair<'wr, Wr: Writer>(&self, serializer: &mut Serializer<'wr, Wr>,
+            old: &Handle, new: &Handle) -> IoResult<()> {
+        if same_tree(old, new) {
+            return new.serialize(serializer, true);
+        }
+        match (&old.borrow().node, &new.borrow().node) {
+            (&Document, _) => self.serialize_children(serializer, old, new),
+
+            (_, &Element(ref name, _)) if is_opaque(name) => new.serialize(serializer, true),
+
+            (_, &Element(ref name, ref attrs)) => {
+                try!(serializer.start_elem(HTML, name.clone(),
+                    attrs.iter().map(|at| (&at.name, at.value.as_slice()))));
+                try!(self.serialize_children(serializer, old, new));
+                serializer.end_elem(HTML, name.clone())
+            }
+
+            (&Text(_), &Text(_)) => {
+                try!(self.wrapped(serializer, atom!(del), old));
+                self.wrapped(serializer, atom!(ins), new)
+            }
+
+            _ => new.serialize(serializer, true),
+        }
+    }
+}
+
+impl Serializable for Diffed {
+    fn serialize<'wr, Wr: Writer>(&self, serializer: &mut Serializer<'wr, Wr>, incl_self: bool) -> IoResult<()> {
+        if incl_self {
+            self.serialize_pair(serializer, &self.old, &self.new)
+        } else {
+            self.serialize_children(serializer, &self.old, &self.new)
+        }
+    }
+}
+
+/// Render the changes from `old` to `new` as HTML, as described for
+/// `Diffed`.
+pub fn render_diff(old: &Handle, new: &Handle) -> String {
+    let node = Diffed {
+        old: old.clone(),
+        new: new.clone(),
+    };
+
+    let mut w = MemWriter::new();
+    serialize(&mut w, &node, Default::default()).ok().expect("couldn't write to memory");
+    String::from_utf8(w.unwrap()).ok().expect("serializer wrote invalid UTF-8")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, render_diff, Insert, Delete, Move, UpdateText, UpdateAttribute};
+
+    use driver::{parse, one_input};
+    use sink::rcdom::{RcDom, Handle, Element, Text};
+
+    use std::default::Default;
+
+    fn doc(html: &str) -> Handle {
+        let dom: RcDom = parse(one_input(html.to_string()), Default::default());
+        dom.document
+    }
+
+    fn opt(v: &Option<String>) -> String {
+        match *v {
+            Some(ref v) => v.clone(),
+            None => "(none)".to_string(),
+        }
+    }
+
+    fn edits(old: &str, new: &str) -> Vec<String> {
+        diff(&doc(old), &doc(new)).iter().map(|e| match *e {
+            Insert(ref p, ref node) => format!("insert {} {}", p, match node.borrow().node {
+                Element(ref name, _) => name.as_slice().to_string(),
+                Text(_) => "#text".to_string(),
+                _ => "#other".to_string(),
+            }),
+            Delete(ref p) => format!("delete {}", p),
+            Move(ref from, ref to) => format!("move {} -> {}", from, to),
+            UpdateText(ref p, ref old, ref new) => format!("update-text {} {} -> {}", p, old, new),
+            UpdateAttribute(ref p, ref name, ref old, ref new)
+                => format!("update-attribute {} {} {} -> {}", p, name.as_slice(), opt(old), opt(new)),
+        }).collect()
+    }
+
+    fn strs(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    test_eq!(identical, edits("<p>a</p><p>b</p>", "<p>a</p><p>b</p>"), strs([]))
+
+    test_eq!(update_text, edits("<p>a</p>", "<p>b</p>"),
+        strs(["update-text [0, 1, 0, 0] a -> b"]))
+
+    test_eq!(update_attribute, edits("<p class=x id=a>t</p>", "<p class=y title=z>t</p>"),
+        strs(["update-attribute [0, 1, 0] class x -> y",
+              "update-attribute [0, 1, 0] id a -> (none)",
+              "update-attribute [0, 1, 0] title (none) -> z"]))
+
+    test_eq!(insert_delete, edits("<p>a</p><p>b</p>", "<p>a</p><div>c</div>"),
+        strs(["delete [0, 1, 1]", "insert [0, 1, 1] div"]))
+
+    test_eq!(move_node, edits("<div id=x>x</div><p>a</p>", "<p>a</p><div id=x>x</div>"),
+        strs(["move [0, 1, 0] -> [0, 1, 1]"]))
+
+    test_eq!(nested, edits("<ul><li>a</li><li>b</li></ul>", "<ul><li>a</li><li>c</li><li>d</li></ul>"),
+        strs(["update-text [0, 1, 0, 1, 0] b -> c", "insert [0, 1, 0, 2] li"]))
+
+    test_eq!(render_change, render_diff(&doc("<p>a b</p><p>x</p>"), &doc("<p>a c</p>")),
+        "<html><head></head><body><p><del>a b</del><ins>a c</ins></p><del><p>x</p></del></body></html>".to_string())
+
+    test_eq!(render_insert, render_diff(&doc("<p>a</p>"), &doc("<p>a</p><p>b</p>")),
+        "<html><head></head><body><p>a</p><ins><p>b</p></ins></body></html>".to_string())
+
+    test_eq!(render_rows, render_diff(
+            &doc("<table><tr><td>a</td></tr></table>"),
+            &doc("<table><tr><td>a</td></tr>\n<tr><td>b</td></tr></table>")),
+        concat!("<html><head></head><body><table><tbody><tr><td>a</td></tr>\n",
+            "<tr><td><ins>b</ins></td></tr></tbody></table></body></html>").to_string())
+
+    test_eq!(render_cells, render_diff(
+            &doc("<table><tr><td>a</td><td><p>b</p></td></tr></table>"),
+            &doc("<table><tr><td>a</td></tr></table>")),
+        concat!("<html><head></head><body><table><tbody><tr><td>a</td>",
+            "<td><del><p>b</p></del></td></tr></tbody></table></body></html>").to_string())
+
+    test_eq!(render_list_items, render_diff(&doc("<ul><li>a</li></ul>"), &doc("<ul><li>a</li><li>b</li></ul>")),
+        "<html><head></head><body><ul><li>a</li><li><ins>b</ins></li></ul></body></html>".to_string())
+
+    test_eq!(render_options, render_diff(
+            &doc("<select><option>a<option>b</select>"),
+            &doc("<select><option>c</select>")),
+        "<html><head></head><body><select><option>c</option></select></body></html>".to_string())
+}
@@ -12,6 +12,10 @@
 use tokenizer::{TokenizerOpts, Tokenizer, TokenSink};
 use tree_builder::{TreeBuilderOpts, TreeBuilder, TreeSink};
 
+use util::utf8::Utf8Decoder;
+
+use std::io;
+use std::io::{Reader, IoResult};
 use std::default::Default;
 use std::option;
 
@@ -53,6 +57,69 @@ pub struct ParseOpts {
     pub tree_builder: TreeBuilderOpts,
 }
 
+/// A tokenizer and tree builder which own their sink.
+///
+/// Unlike `parse_to`, this doesn't need all the input up front, and it
+/// can be stored or moved around while the document is still arriving.
+///
+/// ## Example
+///
+/// ```rust
+/// let sink: RcDom = Default::default();
+/// let mut parser = Parser::new(sink, Default::default());
+/// for chunk in chunks {
+///     parser.feed(chunk);
+/// }
+/// parser.end();
+/// let dom = parser.into_sink();
+/// ```
+pub struct Parser<Handle, Sink> {
+    tok: Tokenizer<TreeBuilder<Handle, Sink>>,
+}
+
+impl<Handle: Clone, Sink: TreeSink<Handle>> Parser<Handle, Sink> {
+    /// Create a parser which sends tree modifications to `sink`.
+    pub fn new(sink: Sink, opts: ParseOpts) -> Parser<Handle, Sink> {
+        let tb = TreeBuilder::new(sink, opts.tree_builder);
+        Parser {
+            tok: Tokenizer::new(tb, opts.tokenizer),
+        }
+    }
+
+    /// Feed a chunk of input.
+    pub fn feed(&mut self, input: String) {
+        self.tok.feed(input);
+    }
+
+    /// Signal the end of input.
+    pub fn end(&mut self) {
+        self.tok.end();
+    }
+
+    /// Get a mutable reference to the sink.
+    pub fn sink<'a>(&'a mut self) -> &'a mut Sink {
+        self.tok.sink().sink()
+    }
+
+    /// Get a mutable reference to the tree builder, for example to ask
+    /// for the document's quirks mode.
+    pub fn tree_builder<'a>(&'a mut self) -> &'a mut TreeBuilder<Handle, Sink> {
+        self.tok.sink()
+    }
+
+    /// Return to the initial state, ready to parse a new document.
+    /// See `TreeBuilder::reset`.
+    pub fn reset(&mut self) {
+        self.tok.sink().reset();
+        self.tok.reset();
+    }
+
+    /// Consume the parser and return the sink.
+    pub fn into_sink(self) -> Sink {
+        self.tok.into_sink().into_sink()
+    }
+}
+
 /// Parse and send results to a `TreeSink`.
 ///
 /// ## Example
@@ -70,12 +137,109 @@ pub fn parse_to<
         mut input: It,
         opts: ParseOpts) {
 
-    let mut tb  = TreeBuilder::new(sink, opts.tree_builder);
-    let mut tok = Tokenizer::new(&mut tb, opts.tokenizer);
+    let mut parser = Parser::new(sink, opts);
     for s in input {
-        tok.feed(s);
+        parser.feed(s);
     }
-    tok.end();
+    parser.end();
+}
+
+/// Parse many documents in turn, reusing one tokenizer and tree builder.
+///
+/// After each document, `done` is called with the sink.  It should take
+/// whatever results it needs and leave the sink ready for a new document,
+/// because the tree builder will immediately ask it for a new `Document`
+/// node.
+///
+/// ## Example
+///
+/// ```rust
+/// let mut sink: RcDom = Default::default();
+/// parse_many_to(&mut sink, pages.move_iter().map(one_input), Default::default(), |dom| {
+///     let dom = mem::replace(dom, Default::default());
+///     process(dom);
+/// });
+/// ```
+pub fn parse_many_to<
+        Handle: Clone,
+        Sink: TreeSink<Handle>,
+        It: Iterator<String>,
+        Docs: Iterator<It>
+    >(
+        sink: &mut Sink,
+        mut docs: Docs,
+        opts: ParseOpts,
+        done: |&mut Sink|) {
+
+    let mut parser = Parser::new(sink, opts);
+    let mut first = true;
+    for mut input in docs {
+        if !first {
+            parser.reset();
+        }
+        first = false;
+
+        for s in input {
+            parser.feed(s);
+        }
+        parser.end();
+        done(&mut **parser.sink());
+    }
+}
+
+/// Chunk size used by `parse_reader`, in bytes.
+pub static DEFAULT_CHUNK_SIZE: uint = 16 * 1024;
+
+/// Read UTF-8 input from a `Reader` and send results to a `TreeSink`.
+///
+/// Input is read `chunk_size` bytes at a time, and each chunk is fed to
+/// the parser as it arrives.  Invalid UTF-8 is replaced with U+FFFD.
+/// An I/O error stops parsing and is returned; end of file is not an
+/// error.
+///
+/// ## Example
+///
+/// ```rust
+/// let mut sink = MySink;
+/// try!(parse_reader_to(&mut sink, &mut io::stdin(), 4096, Default::default()));
+/// ```
+pub fn parse_reader_to<
+        Handle: Clone,
+        Sink: TreeSink<Handle>,
+        R: Reader
+    >(
+        sink: &mut Sink,
+        reader: &mut R,
+        chunk_size: uint,
+        opts: ParseOpts) -> IoResult<()> {
+
+    let mut parser = Parser::new(sink, opts);
+    try!(feed_from_reader(&mut parser, reader, chunk_size));
+    parser.end();
+    Ok(())
+}
+
+fn feed_from_reader<
+        Handle: Clone,
+        Sink: TreeSink<Handle>,
+        R: Reader
+    >(
+        parser: &mut Parser<Handle, Sink>,
+        reader: &mut R,
+        chunk_size: uint) -> IoResult<()> {
+
+    assert!(chunk_size > 0);
+    let mut decoder = Utf8Decoder::new();
+    let mut buf = Vec::from_elem(chunk_size, 0u8);
+    loop {
+        match reader.read(buf.as_mut_slice()) {
+            Ok(n) => parser.feed(decoder.decode(buf.slice_to(n))),
+            Err(ref e) if e.kind == io::EndOfFile => break,
+            Err(e) => return Err(e),
+        }
+    }
+    parser.feed(decoder.finish());
+    Ok(())
 }
 
 /// Results which can be extracted from a `TreeSink`.
@@ -99,10 +263,241 @@ pub fn parse<
         Output: ParseResult<Sink>,
         It: Iterator<String>
     >(
-        input: It,
+        mut input: It,
         opts: ParseOpts) -> Output {
 
-    let mut sink: Sink = Default::default();
-    parse_to(&mut sink, input, opts);
-    ParseResult::get_result(sink)
+    let mut parser: Parser<Handle, Sink> = Parser::new(Default::default(), opts);
+    for s in input {
+        parser.feed(s);
+    }
+    parser.end();
+    ParseResult::get_result(parser.into_sink())
+}
+
+/// Read UTF-8 input from a `Reader` and parse into a type which
+/// implements `ParseResult`, reading `DEFAULT_CHUNK_SIZE` bytes at a time.
+///
+/// ## Example
+///
+/// ```rust
+/// let dom: RcDom = try!(parse_reader(&mut io::stdin(), Default::default()));
+/// ```
+pub fn parse_reader<
+        Handle: Clone,
+        Sink: Default + TreeSink<Handle>,
+        Output: ParseResult<Sink>,
+        R: Reader
+    >(
+        reader: &mut R,
+        opts: ParseOpts) -> IoResult<Output> {
+
+    let mut parser: Parser<Handle, Sink> = Parser::new(Default::default(), opts);
+    try!(feed_from_reader(&mut parser, reader, DEFAULT_CHUNK_SIZE));
+    parser.end();
+    Ok(ParseResult::get_result(parser.into_sink()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Parser, ParseOpts, parse, parse_many_to, parse_reader_to, one_input};
+    use tokenizer::TokenizerOpts;
+    use tree_builder::TreeBuilderOpts;
+    use tree_builder::{Quirks, NoQuirks, MissingDoctype, StandardsDoctype, Html4PublicId};
+    use tree_builder::{ForceQuirksFlag, IframeSrcdoc};
+    use sink::rcdom::RcDom;
+    use serialize::serialize;
+
+    use std::mem;
+    use std::default::Default;
+    use std::io;
+    use std::io::{MemWriter, MemReader, Reader, IoResult, IoError};
+
+    fn to_html(dom: &RcDom) -> String {
+        let mut w = MemWriter::new();
+        serialize(&mut w, &dom.document, Default::default()).unwrap();
+        String::from_utf8(w.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn reuse_matches_fresh() {
+        let docs = [
+            "<!DOCTYPE html><title>one</title><table><tr><td>x",
+            "<p>two<b>bold",
+            "<frameset><frame></frameset>",
+            "<textarea>\nthree",
+        ];
+
+        let mut got = vec!();
+        let mut sink: RcDom = Default::default();
+        parse_many_to(&mut sink, docs.iter().map(|d| one_input(d.to_string())),
+            Default::default(), |dom| {
+                let dom = mem::replace(dom, Default::default());
+                got.push(to_html(&dom));
+            });
+
+        let expected: Vec<String> = docs.iter().map(|d| {
+            let dom: RcDom = parse(one_input(d.to_string()), Default::default());
+            to_html(&dom)
+        }).collect();
+
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn owned_parser() {
+        let sink: RcDom = Default::default();
+        let mut parser = Parser::new(sink, Default::default());
+        for chunk in ["<p>one", "<p>t", "wo"].iter() {
+            parser.feed(chunk.to_string());
+        }
+        parser.end();
+        let dom = parser.into_sink();
+
+        let expected: RcDom = parse(one_input("<p>one<p>two".to_string()), Default::default());
+        assert_eq!(to_html(&dom), to_html(&expected));
+    }
+
+    #[test]
+    fn quirks_reason() {
+        let cases = [
+            ("<p>x", Quirks, MissingDoctype),
+            ("<!DOCTYPE html><p>x", NoQuirks, StandardsDoctype),
+            ("<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01 Frameset//EN\"><p>x", Quirks, Html4PublicId),
+            ("<!DOCTYPE>", Quirks, ForceQuirksFlag),
+        ];
+        for &(html, mode, reason) in cases.iter() {
+            let sink: RcDom = Default::default();
+            let mut parser = Parser::new(sink, Default::default());
+            assert_eq!(parser.tree_builder().quirks_reason(), None);
+            parser.feed(html.to_string());
+            parser.end();
+            assert_eq!(parser.tree_builder().quirks_mode(), mode);
+            assert_eq!(parser.tree_builder().quirks_reason(), Some(reason));
+        }
+
+        let sink: RcDom = Default::default();
+        let opts = ParseOpts {
+            tree_builder: TreeBuilderOpts {
+                iframe_srcdoc: true,
+                .. Default::default()
+            },
+            .. Default::default()
+        };
+        let mut parser = Parser::new(sink, opts);
+        parser.feed("<p>x".to_string());
+        parser.end();
+        assert_eq!(parser.tree_builder().quirks_mode(), NoQuirks);
+        assert_eq!(parser.tree_builder().quirks_reason(), Some(IframeSrcdoc));
+    }
+
+    #[test]
+    fn reader_chunks() {
+        let html = "<p title=\"caf\u00e9\">\u2603 snow\U0001f4a9<b>x</b>";
+        let expected: RcDom = parse(one_input(html.to_string()), Default::default());
+
+        for size in range(1u, 8) {
+            let mut reader = MemReader::new(Vec::from_slice(html.as_bytes()));
+            let mut dom: RcDom = Default::default();
+            parse_reader_to(&mut dom, &mut reader, size, Default::default()).unwrap();
+            assert_eq!(to_html(&dom), to_html(&expected));
+        }
+    }
+
+    struct FailingReader;
+
+    impl Reader for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> IoResult<uint> {
+            Err(IoError {
+                kind: io::BrokenPipe,
+                desc: "broken",
+                detail: None,
+            })
+        }
+    }
+
+    #[test]
+    fn reader_error() {
+        let mut dom: RcDom = Default::default();
+        let res = parse_reader_to(&mut dom, &mut FailingReader, 16, Default::default());
+        assert_eq!(res.unwrap_err().kind, io::BrokenPipe);
+    }
+
+    fn parse_limited(html: &str, tok: TokenizerOpts, tb: TreeBuilderOpts) -> RcDom {
+        parse(one_input(html.to_string()), ParseOpts {
+            tokenizer: tok,
+            tree_builder: tb,
+        })
+    }
+
+    fn body(dom: &RcDom) -> String {
+        let html = to_html(dom);
+        let start = html.as_slice().find_str("<body>").unwrap() + 6;
+        let end = html.as_slice().find_str("</body>").unwrap();
+        html.as_slice().slice(start, end).to_string()
+    }
+
+    #[test]
+    fn tokenizer_limits() {
+        let dom = parse_limited("<p a=1 b=2 c=3 d=4>x<!--comment-->", TokenizerOpts {
+            max_attrs_per_tag: Some(2),
+            max_comment_len: Some(4),
+            ..Default::default()
+        }, Default::default());
+        assert_eq!(body(&dom), "<p a=\"1\" b=\"2\">x<!--comm--></p>".to_string());
+        assert!(dom.errors.iter().any(|e| e.as_slice() == "Too many attributes, dropping the rest"));
+        assert!(dom.errors.iter().any(|e| e.as_slice() == "Comment too long, truncating"));
+
+        let dom = parse_limited("<p title=abcdef>0123456789<b>ab</b>", TokenizerOpts {
+            max_attr_len: Some(3),
+            max_text_len: Some(4),
+            ..Default::default()
+        }, Default::default());
+        assert_eq!(body(&dom), "<p title=\"abc\">0123<b>ab</b></p>".to_string());
+    }
+
+    #[test]
+    fn tree_builder_limits() {
+        let dom = parse_limited("<div><div><div><div>x", Default::default(), TreeBuilderOpts {
+            max_depth: Some(4),
+            ..Default::default()
+        });
+        assert_eq!(body(&dom), "<div><div></div><div></div><div>x</div></div>".to_string());
+
+        // The over-deep element closed its predecessor, so text after its
+        // end tag goes back to the parent.
+        let dom = parse_limited("<div><div><div></div>y", Default::default(), TreeBuilderOpts {
+            max_depth: Some(4),
+            ..Default::default()
+        });
+        assert_eq!(body(&dom), "<div><div></div><div></div>y</div>".to_string());
+
+        // A formatting element closed at the limit isn't reconstructed.
+        let dom = parse_limited("<div><b><i>x</i>y", Default::default(), TreeBuilderOpts {
+            max_depth: Some(4),
+            ..Default::default()
+        });
+        assert_eq!(body(&dom), "<div><b></b><i>x</i>y</div>".to_string());
+
+        // Closing a table at the limit leaves table insertion modes.
+        let dom = parse_limited("<div><table><p>x</p></table>y", Default::default(), TreeBuilderOpts {
+            max_depth: Some(4),
+            ..Default::default()
+        });
+        assert_eq!(body(&dom), "<div><table></table><p>x</p>y</div>".to_string());
+
+        let dom = parse_limited("<p>1<p>2<p>3<p>4", Default::default(), TreeBuilderOpts {
+            max_nodes: Some(7),
+            ..Default::default()
+        });
+        assert_eq!(body(&dom), "<p>1</p><p>2</p>".to_string());
+    }
+
+    #[test]
+    #[should_fail]
+    fn max_depth_too_small() {
+        parse_limited("<p>", Default::default(), TreeBuilderOpts {
+            max_depth: Some(1),
+            ..Default::default()
+        });
+    }
 }
@@ -0,0 +1,795 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Forms, their controls, and form submission.
+//!
+//! Controls are associated with their form owner as a browser would:
+//! by a `form` attribute, by the parser's form element pointer (see
+//! `TreeSink::associate_with_form`), or by being inside the `<form>`.
+//! Their values are the defaults from the markup, and can be changed
+//! before building a submission.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let dom: RcDom = parse(one_input(page), Default::default());
+//! let mut form = forms(&dom.document, "http://example.com/login").move_iter().next().unwrap();
+//! for control in form.controls.mut_iter() {
+//!     if control.name.as_slice() == "user" {
+//!         control.value = "me".to_string();
+//!     }
+//! }
+//! let submission = form.submit(None).unwrap();
+//! ```
+
+use atom::Atom;
+use atom::namespace::HTML;
+use links::base_url;
+use sink::rcdom::{Handle, NodeHandle, Element};
+use tree_builder::listed_tag;
+use util::url::resolve;
+
+use std::ascii::StrAsciiExt;
+
+/// An option of a `<select>`.
+#[deriving(Clone)]
+pub struct SelectOption {
+    pub element: Handle,
+
+    /// The `value` attribute, or else the text.
+    pub value: String,
+
+    /// The `label` attribute, or else the text.
+    pub label: String,
+
+    pub selected: bool,
+
+    /// Whether the option or its `<optgroup>` is disabled.
+    pub disabled: bool,
+}
+
+/// A listed form-associated element: `button`, `fieldset`, `input`,
+/// `object`, `output`, `select` or `textarea`.
+#[deriving(Clone)]
+pub struct Control {
+    pub element: Handle,
+
+    /// The `name` attribute, or the empty string.
+    pub name: String,
+
+    /// As in the DOM `type` property: the type of an `input` or
+    /// `button`, `select-one` or `select-multiple` for a `select`, and
+    /// otherwise the element name.
+    pub kind: String,
+
+    /// The value.  For a `select`, this is the value of the first
+    /// selected option; change `options` to change what's submitted.
+    pub value: String,
+
+    /// The checkedness of a checkbox or radio button.
+    pub checked: bool,
+
+    /// Whether the control is disabled, including by a `<fieldset>`.
+    pub disabled: bool,
+
+    /// The options of a `select`.
+    pub options: Vec<SelectOption>,
+}
+
+/// A form and its controls.
+#[deriving(Clone)]
+pub struct Form {
+    pub element: Handle,
+
+    /// The resolved `action`, or `None` if it couldn't be resolved.
+    pub action: Option<String>,
+
+    /// `get`, `post` or `dialog`.
+    pub method: String,
+
+    /// `application/x-www-form-urlencoded`, `multipart/form-data` or
+    /// `text/plain`.
+    pub enctype: String,
+
+    /// The controls whose form owner is this form, in tree order.
+    pub controls: Vec<Control>,
+
+    document_url: String,
+    base: String,
+}
+
+/// The value of a form data entry.
+#[deriving(Clone, PartialEq, Show)]
+pub enum EntryValue {
+    StringValue(String),
+
+    /// A file, by file name.  No files are selected in a parsed
+    /// document, so a file input gives an entry with an empty file name.
+    FileValue(String),
+}
+
+/// An entry in a form data set.
+#[deriving(Clone, PartialEq, Show)]
+pub struct Entry {
+    pub name: String,
+    pub value: EntryValue,
+}
+
+/// A request which submits a form.
+#[deriving(Clone, PartialEq, Show)]
+pub struct Submission {
+    /// `GET` or `POST`.
+    pub method: String,
+
+    pub url: String,
+
+    /// The `Content-Type` of the body, for a `POST`.
+    pub content_type: Option<String>,
+
+    pub body: Option<String>,
+}
+
+static input_types: &'static [&'static str] = &[
+    "hidden", "text", "search", "tel", "url", "email", "password", "date", "month",
+    "week", "time", "datetime-local", "number", "range", "color", "checkbox", "radio",
+    "file", "submit", "image", "reset", "button",
+];
+
+static methods: &'static [&'static str] = &["get", "post", "dialog"];
+
+static enctypes: &'static [&'static str] = &[
+    "application/x-www-form-urlencoded", "multipart/form-data", "text/plain",
+];
+
+fn elem_name(node: &Handle) -> Option<Atom> {
+    match node.borrow().node {
+        Element(ref name, _) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+// An enumerated attribute's value, lowercased, or `default` if it's
+// missing or not in `values`.
+fn enumerated(value: Option<String>, values: &[&str], default: &str) -> String {
+    let value = value.map(|v| v.as_slice().to_ascii_lower());
+    match value {
+        Some(ref v) if values.iter().any(|&x| x == v.as_slice()) => v.clone(),
+        _ => default.to_string(),
+    }
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    let words: Vec<&str> = s.words().collect();
+    words.connect(" ")
+}
+
+fn strip_newlines(s: &str) -> String {
+    s.chars().filter(|&c| c != '\r' && c != '\n').collect()
+}
+
+/// The form owner of a form-associated element, if any.
+pub fn form_owner(document: &Handle, element: &Handle) -> Option<Handle> {
+    let name = unwrap_or_return!(elem_name(element), None);
+    if listed_tag((HTML, name)) {
+        match element.get_attribute("form") {
+            Some(id) => {
+                // The first element with the ID, if it's a form.
+                let target = document.descendants()
+                    .find(|n| elem_name(n).is_some() && n.get_attribute("id") == Some(id.clone()));
+                return target.and_then(|t| match elem_name(&t) {
+                    Some(atom!(form)) => Some(t),
+                    _ => None,
+                });
+            }
+            None => (),
+        }
+    }
+
+    let associated = element.borrow().form_owner.as_ref().and_then(|f| f.upgrade());
+    match associated {
+        Some(form) => return Some(form),
+        None => (),
+    }
+    element.ancestors().find(|a| elem_name(a) == Some(atom!(form)))
+}
+
+fn is_disabled(element: &Handle) -> bool {
+    if element.get_attribute("disabled").is_some() {
+        return true;
+    }
+
+    // A disabled fieldset disables its descendants, except those in its
+    // first <legend> child.
+    let mut child = element.clone();
+    for anc in element.ancestors() {
+        if elem_name(&anc) == Some(atom!(fieldset)) && anc.get_attribute("disabled").is_some() {
+            let legend = anc.borrow().children.iter()
+                .find(|c| elem_name(*c) == Some(atom!(legend)))
+                .map(|c| c.clone());
+            if !legend.map_or(false, |l| l.same_node(&child)) {
+                return true;
+            }
+        }
+        child = anc;
+    }
+    false
+}
+
+// The option children of a select, and of its optgroup children.
+fn option_elements(select: &Handle) -> Vec<Handle> {
+    let mut out = vec!();
+    for child in select.borrow().children.iter() {
+        match elem_name(child) {
+            Some(atom!(option)) => out.push(child.clone()),
+            Some(atom!(optgroup)) => for c in child.borrow().children.iter() {
+                if elem_name(c) == Some(atom!(option)) {
+                    out.push(c.clone());
+                }
+            },
+            _ => (),
+        }
+    }
+    out
+}
+
+fn select_options(select: &Handle, multiple: bool) -> Vec<SelectOption> {
+    let mut options: Vec<SelectOption> = option_elements(select).move_iter().map(|o| {
+        let text = collapse_whitespace(o.text_content().as_slice());
+        let in_disabled_group = o.parent().map_or(false, |p|
+            elem_name(&p) == Some(atom!(optgroup)) && p.get_attribute("disabled").is_some());
+        SelectOption {
+            value: o.get_attribute("value").unwrap_or(text.clone()),
+            label: o.get_attribute("label").unwrap_or(text),
+            selected: o.get_attribute("selected").is_some(),
+            disabled: in_disabled_group || o.get_attribute("disabled").is_some(),
+            element: o,
+        }
+    }).collect();
+
+    //§ selectedness-setting-algorithm
+    if !multiple {
+        let size = select.get_attribute("size")
+            .and_then(|s| from_str::<uint>(s.as_slice().trim()))
+            .unwrap_or(1);
+        match options.iter().rposition(|o| o.selected) {
+            Some(last) => for (i, o) in options.mut_iter().enumerate() {
+                o.selected = i == last;
+            },
+            None if size <= 1 => match options.mut_iter().find(|o| !o.disabled) {
+                Some(o) => o.selected = true,
+                None => (),
+            },
+            None => (),
+        }
+    }
+    //§ END
+
+    options
+}
+
+fn input_value(kind: &str, value: Option<String>) -> String {
+    match kind {
+        "checkbox" | "radio" => value.unwrap_or("on".to_string()),
+        _ => {
+            // Value sanitization.
+            let value = value.unwrap_or(String::new());
+            match kind {
+                "text" | "search" | "tel" | "password" => strip_newlines(value.as_slice()),
+                "url" | "email" => strip_newlines(value.as_slice()).as_slice().trim().to_string(),
+                "color" => {
+                    let valid = value.len() == 7 && value.as_slice().starts_with("#")
+                        && value.as_slice().slice_from(1).chars().all(|c| c.is_digit_radix(16));
+                    if valid { value.as_slice().to_ascii_lower() } else { "#000000".to_string() }
+                }
+                _ => value,
+            }
+        }
+    }
+}
+
+impl Control {
+    fn new(element: &Handle) -> Control {
+        let name = elem_name(element).expect("not an element");
+        let kind = match name {
+            atom!(input) => enumerated(element.get_attribute("type"), input_types, "text"),
+            atom!(button) => enumerated(element.get_attribute("type"),
+                ["submit", "reset", "button"], "submit"),
+            atom!(select) if element.get_attribute("multiple").is_some() => "select-multiple".to_string(),
+            atom!(select) => "select-one".to_string(),
+            ref n => n.as_slice().to_string(),
+        };
+
+        let options = match name {
+            atom!(select) => select_options(element, kind.as_slice() == "select-multiple"),
+            _ => vec!(),
+        };
+
+        let value = match name {
+            atom!(input) => input_value(kind.as_slice(), element.get_attribute("value")),
+            atom!(button) => element.get_attribute("value").unwrap_or(String::new()),
+            atom!(textarea) | atom!(output) => element.text_content(),
+            atom!(select) => options.iter().find(|o| o.selected)
+                .map_or(String::new(), |o| o.value.clone()),
+            _ => String::new(),
+        };
+
+        let checkable = kind.as_slice() == "checkbox" || kind.as_slice() == "radio";
+        Control {
+            element: element.clone(),
+            name: element.get_attribute("name").unwrap_or(String::new()),
+            kind: kind,
+            value: value,
+            checked: checkable && element.get_attribute("checked").is_some(),
+            disabled: is_disabled(element),
+            options: options,
+        }
+    }
+
+    fn is_button(&self) -> bool {
+        match self.kind.as_slice() {
+            "submit" | "image" | "reset" | "button" => true,
+            _ => false,
+        }
+    }
+
+    fn is_submit_button(&self) -> bool {
+        self.kind.as_slice() == "submit" || self.kind.as_slice() == "image"
+    }
+}
+
+// Only the last checked radio button in each group stays checked.
+fn uncheck_radio_groups(controls: &mut Vec<Control>) {
+    for i in range(0, controls.len()) {
+        let name = {
+            let c = controls.get(i);
+            if c.kind.as_slice() != "radio" || !c.checked || c.name.is_empty() {
+                continue;
+            }
+            c.name.clone()
+        };
+        for c in controls.mut_slice_to(i).mut_iter() {
+            if c.kind.as_slice() == "radio" && c.name == name {
+                c.checked = false;
+            }
+        }
+    }
+}
+
+fn entry(name: &str, value: &str) -> Entry {
+    Entry {
+        name: name.to_string(),
+        value: StringValue(value.to_string()),
+    }
+}
+
+impl Form {
+    fn new(element: &Handle, document_url: &str, base: &str) -> Form {
+        let mut form = Form {
+            element: element.clone(),
+            action: None,
+            method: enumerated(element.get_attribute("method"), methods, "get"),
+            enctype: enumerated(element.get_attribute("enctype"), enctypes, enctypes[0]),
+            controls: vec!(),
+            document_url: document_url.to_string(),
+            base: base.to_string(),
+        };
+        form.action = form.resolve_action(element.get_attribute("action"));
+        form
+    }
+
+    fn resolve_action(&self, action: Option<String>) -> Option<String> {
+        match action {
+            Some(ref a) if !a.is_empty() => resolve(self.base.as_slice(), a.as_slice()),
+            _ => Some(self.document_url.clone()),
+        }
+    }
+
+    /// The form data set submitted with the submit button at index
+    /// `submitter` in `controls`, or with no submitter.
+    pub fn form_data(&self, submitter: Option<uint>) -> Vec<Entry> {
+        //§ constructing-the-form-data-set
+        let mut entries = vec!();
+        for (i, c) in self.controls.iter().enumerate() {
+            let submittable = match elem_name(&c.element) {
+                Some(atom!(button)) | Some(atom!(input)) | Some(atom!(select))
+                | Some(atom!(textarea)) => true,
+                _ => false,
+            };
+            let in_datalist = c.element.ancestors()
+                .any(|a| elem_name(&a).map_or(false, |n| n.as_slice() == "datalist"));
+            if !submittable || c.disabled || in_datalist {
+                continue;
+            }
+            if c.is_button() && submitter != Some(i) {
+                continue;
+            }
+
+            let kind = c.kind.as_slice();
+            if (kind == "checkbox" || kind == "radio") && !c.checked {
+                continue;
+            }
+
+            if kind == "image" {
+                let prefix = if c.name.is_empty() {
+                    String::new()
+                } else {
+                    format!("{}.", c.name)
+                };
+                entries.push(entry(format!("{}x", prefix).as_slice(), "0"));
+                entries.push(entry(format!("{}y", prefix).as_slice(), "0"));
+                continue;
+            }
+
+            if c.name.is_empty() {
+                continue;
+            }
+
+            let name = c.name.as_slice();
+            match kind {
+                "select-one" | "select-multiple" => for o in c.options.iter() {
+                    if o.selected && !o.disabled {
+                        entries.push(entry(name, o.value.as_slice()));
+                    }
+                },
+                "file" => entries.push(Entry {
+                    name: name.to_string(),
+                    value: FileValue(String::new()),
+                }),
+                "hidden" if name.eq_ignore_ascii_case("_charset_")
+                    => entries.push(entry(name, "UTF-8")),
+                _ => entries.push(entry(name, c.value.as_slice())),
+            }
+
+            if kind == "textarea" || kind == "text" || kind == "search" {
+                match c.element.get_attribute("dirname") {
+                    Some(ref d) if !d.is_empty() => entries.push(entry(d.as_slice(), "ltr")),
+                    _ => (),
+                }
+            }
+        }
+        entries
+        //§ END
+    }
+
+    /// The request which submits the form with the submit button at
+    /// index `submitter` in `controls`, or with no submitter.  The
+    /// submitter's `formaction`, `formmethod` and `formenctype` override
+    /// the form's.  Returns `None` for `method=dialog`, if the action
+    /// URL can't be resolved, or if `submitter` is out of range or not a
+    /// submit button.
+    pub fn submit(&self, submitter: Option<uint>) -> Option<Submission> {
+        let sub = match submitter {
+            Some(i) => match self.controls.as_slice().get(i) {
+                Some(c) if c.is_submit_button() => Some(c),
+                _ => return None,
+            },
+            None => None,
+        };
+        let overridden = |attr: &str| sub.and_then(|c| c.element.get_attribute(attr));
+
+        let action = match overridden("formaction") {
+            Some(a) => self.resolve_action(Some(a)),
+            None => self.action.clone(),
+        };
+        let url = unwrap_or_return!(action, None);
+        let method = match overridden("formmethod") {
+            Some(m) => enumerated(Some(m), methods, "get"),
+            None => self.method.clone(),
+        };
+        let enctype = match overridden("formenctype") {
+            Some(e) => enumerated(Some(e), enctypes, enctypes[0]),
+            None => self.enctype.clone(),
+        };
+
+        let entries = self.form_data(submitter);
+        match method.as_slice() {
+            "dialog" => None,
+            "get" => Some(Submission {
+                method: "GET".to_string(),
+                url: with_query(url.as_slice(), urlencoded(entries.as_slice()).as_slice()),
+                content_type: None,
+                body: None,
+            }),
+            _ => {
+                let (content_type, body) = match enctype.as_slice() {
+                    "multipart/form-data" => {
+                        let boundary = choose_boundary(entries.as_slice());
+                        (format!("multipart/form-data; boundary={}", boundary),
+                            multipart(entries.as_slice(), boundary.as_slice()))
+                    }
+                    "text/plain" => ("text/plain".to_string(), text_plain(entries.as_slice())),
+                    _ => (enctype.clone(), urlencoded(entries.as_slice())),
+                };
+                Some(Submission {
+                    method: "POST".to_string(),
+                    url: url,
+                    content_type: Some(content_type),
+                    body: Some(body),
+                })
+            }
+        }
+    }
+}
+
+/// Find the forms in a document at `document_url`, in tree order, with
+/// their controls.
+pub fn forms(document: &Handle, document_url: &str) -> Vec<Form> {
+    let base = base_url(document, document_url);
+    let mut forms: Vec<Form> = document.descendants()
+        .filter(|n| elem_name(n) == Some(atom!(form)))
+        .map(|n| Form::new(&n, document_url, base.as_slice()))
+        .collect();
+
+    for node in document.descendants() {
+        match elem_name(&node) {
+            Some(ref name) if listed_tag((HTML, name.clone())) => (),
+            _ => continue,
+        }
+        let owner = match form_owner(document, &node) {
+            Some(owner) => owner,
+            None => continue,
+        };
+        match forms.mut_iter().find(|f| f.element.same_node(&owner)) {
+            Some(form) => form.controls.push(Control::new(&node)),
+            None => (),
+        }
+    }
+
+    for form in forms.mut_iter() {
+        uncheck_radio_groups(&mut form.controls);
+    }
+    forms
+}
+
+fn with_query(url: &str, query: &str) -> String {
+    let (rest, fragment) = match url.find('#') {
+        Some(i) => (url.slice_to(i), url.slice_from(i)),
+        None => (url, ""),
+    };
+    let rest = match rest.find('?') {
+        Some(i) => rest.slice_to(i),
+        None => rest,
+    };
+    format!("{}?{}{}", rest, query, fragment)
+}
+
+impl Entry {
+    fn value_str<'a>(&'a self) -> &'a str {
+        match self.value {
+            StringValue(ref s) | FileValue(ref s) => s.as_slice(),
+        }
+    }
+}
+
+// Replace every CR, LF, or CRLF with CRLF.
+fn normalize_newlines(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars().peekable();
+    loop {
+        match chars.next() {
+            None => return out,
+            Some('\r') => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                out.push_str("\r\n");
+            }
+            Some('\n') => out.push_str("\r\n"),
+            Some(c) => out.push_char(c),
+        }
+    }
+}
+
+fn percent_encode(s: &str, out: &mut String) {
+    for &b in s.as_bytes().iter() {
+        match b as char {
+            ' ' => out.push_char('+'),
+            'a'..'z' | 'A'..'Z' | '0'..'9' | '*' | '-' | '.' | '_' => out.push_char(b as char),
+            _ => out.push_str(format!("%{:02X}", b).as_slice()),
+        }
+    }
+}
+
+/// Encode a form data set as `application/x-www-form-urlencoded`.
+/// Files are encoded as their file name.
+pub fn urlencoded(entries: &[Entry]) -> String {
+    let mut out = String::new();
+    for (i, e) in entries.iter().enumerate() {
+        if i > 0 {
+            out.push_char('&');
+        }
+        percent_encode(normalize_newlines(e.name.as_slice()).as_slice(), &mut out);
+        out.push_char('=');
+        percent_encode(normalize_newlines(e.value_str()).as_slice(), &mut out);
+    }
+    out
+}
+
+/// Encode a form data set as `text/plain`.  Files are encoded as their
+/// file name.
+pub fn text_plain(entries: &[Entry]) -> String {
+    let mut out = String::new();
+    for e in entries.iter() {
+        out.push_str(normalize_newlines(e.name.as_slice()).as_slice());
+        out.push_char('=');
+        out.push_str(normalize_newlines(e.value_str()).as_slice());
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn escape_part_name(s: &str) -> String {
+    s.replace("\n", "%0A").as_slice().replace("\r", "%0D").as_slice().replace("\"", "%22")
+}
+
+// A boundary which doesn't occur in any entry.
+fn choose_boundary(entries: &[Entry]) -> String {
+    let mut n = 0u;
+    loop {
+        let boundary = format!("html5ever-boundary-{}", n);
+        let b = boundary.as_slice();
+        if !entries.iter().any(|e| e.name.as_slice().contains(b) || e.value_str().contains(b)) {
+            return boundary;
+        }
+        n += 1;
+    }
+}
+
+/// Encode a form data set as `multipart/form-data` with the given
+/// boundary, which must not occur in any entry.  Files are empty.
+pub fn multipart(entries: &[Entry], boundary: &str) -> String {
+    let mut out = String::new();
+    for e in entries.iter() {
+        out.push_str(format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"", boundary,
+            escape_part_name(normalize_newlines(e.name.as_slice()).as_slice())).as_slice());
+        match e.value {
+            StringValue(ref v) => {
+                out.push_str("\r\n\r\n");
+                out.push_str(normalize_newlines(v.as_slice()).as_slice());
+            }
+            FileValue(ref f) => {
+                out.push_str(format!("; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                    escape_part_name(f.as_slice())).as_slice());
+            }
+        }
+        out.push_str("\r\n");
+    }
+    out.push_str(format!("--{}--\r\n", boundary).as_slice());
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{forms, urlencoded, text_plain, entry, Form, Submission};
+
+    use driver::{parse, one_input};
+    use sink::rcdom::RcDom;
+
+    use std::default::Default;
+
+    fn parse_forms(html: &str) -> Vec<Form> {
+        let dom: RcDom = parse(one_input(html.to_string()), Default::default());
+        forms(&dom.document, "http://example.com/dir/page?old=1#top")
+    }
+
+    fn form(html: &str) -> Form {
+        parse_forms(html).move_iter().next().expect("no form")
+    }
+
+    fn strs(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn get(url: &str) -> Option<Submission> {
+        Some(Submission {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            content_type: None,
+            body: None,
+        })
+    }
+
+    fn post(url: &str, content_type: &str, body: &str) -> Option<Submission> {
+        Some(Submission {
+            method: "POST".to_string(),
+            url: url.to_string(),
+            content_type: Some(content_type.to_string()),
+            body: Some(body.to_string()),
+        })
+    }
+
+    #[test]
+    fn owners() {
+        let forms = parse_forms(concat!(
+            "<form id=f><input name=a><input name=e form=nope><select name=s></select></form>",
+            "<input name=b form=f><input name=c>",
+            "<div><form id=g></div><textarea name=t></textarea>"));
+        let names: Vec<Vec<String>> = forms.iter()
+            .map(|f| f.controls.iter().map(|c| c.name.clone()).collect())
+            .collect();
+        assert_eq!(names, vec!(strs(["a", "s", "b"]), strs(["t"])));
+    }
+
+    #[test]
+    fn defaults() {
+        let f = form(concat!(
+            "<form><input name=t value=\"a\nb\"><input type=checkbox name=c checked>",
+            "<input type=checkbox name=d value=x>",
+            "<input type=radio name=r value=1 checked><input type=radio name=r value=2 checked>",
+            "<select name=s><option>  One  two </option><option selected value=2>b</option>",
+            "<option selected>c</option></select>",
+            "<select name=u><option disabled>x</option><option>y</option></select>",
+            "<select name=m multiple><option selected>p</option><option>q</option>",
+            "<option selected>r</option></select>",
+            "<textarea name=ta>\nline1\nline2</textarea><input type=WEIRD name=w></form>"));
+
+        let c = f.controls.as_slice();
+        assert_eq!(c[0].value, "ab".to_string());
+        assert!(c[1].checked && !c[2].checked);
+        assert!(!c[3].checked && c[4].checked);
+        assert_eq!(c[5].value, "c".to_string());
+        assert_eq!(c[5].options.get(0).value, "One two".to_string());
+        assert_eq!(c[6].value, "y".to_string());
+        assert_eq!(c[7].kind, "select-multiple".to_string());
+        assert_eq!(c[8].value, "line1\nline2".to_string());
+        assert_eq!(c[9].kind, "text".to_string());
+
+        assert_eq!(urlencoded(f.form_data(None).as_slice()),
+            "t=ab&c=on&r=2&s=c&u=y&m=p&m=r&ta=line1%0D%0Aline2&w=".to_string());
+    }
+
+    #[test]
+    fn excluded() {
+        let f = form(concat!(
+            "<form><fieldset disabled><legend><input name=a></legend><input name=b></fieldset>",
+            "<input name=c disabled><datalist><input name=d></datalist>",
+            "<input type=hidden name=_charset_><input type=file name=f>",
+            "<input name=dir dirname=dir.dir></form>"));
+        assert_eq!(urlencoded(f.form_data(None).as_slice()),
+            "a=&_charset_=UTF-8&f=&dir=&dir.dir=ltr".to_string());
+    }
+
+    #[test]
+    fn submitters() {
+        let f = form(concat!(
+            "<form action=/search><input name=q value=\"a b&c\">",
+            "<button name=go value=1>Go</button>",
+            "<button name=alt formmethod=POST formaction=other formenctype=multipart/form-data>Alt</button>",
+            "<input type=image name=pos src=x></form>"));
+
+        assert_eq!(f.submit(None), get("http://example.com/search?q=a+b%26c"));
+        assert_eq!(f.submit(Some(1)), get("http://example.com/search?q=a+b%26c&go=1"));
+        assert_eq!(f.submit(Some(3)), get("http://example.com/search?q=a+b%26c&pos.x=0&pos.y=0"));
+        assert_eq!(f.submit(Some(2)), post("http://example.com/dir/other",
+            "multipart/form-data; boundary=html5ever-boundary-0",
+            concat!("--html5ever-boundary-0\r\nContent-Disposition: form-data; name=\"q\"\r\n\r\n",
+                "a b&c\r\n",
+                "--html5ever-boundary-0\r\nContent-Disposition: form-data; name=\"alt\"\r\n\r\n",
+                "\r\n",
+                "--html5ever-boundary-0--\r\n")));
+
+        // Not a submit button, and out of range.
+        assert_eq!(f.submit(Some(0)), None);
+        assert_eq!(f.submit(Some(4)), None);
+    }
+
+    test_eq!(empty_action, form("<form><input name=a value=1></form>").submit(None),
+        get("http://example.com/dir/page?a=1#top"))
+
+    test_eq!(post_urlencoded, form("<form method=post action=x><input name=a value=1></form>").submit(None),
+        post("http://example.com/dir/x", "application/x-www-form-urlencoded", "a=1"))
+
+    test_eq!(dialog, form("<form method=dialog><input name=a></form>").submit(None), None)
+
+    test_eq!(encode_urlencoded, urlencoded([entry("a b", "é*-._~"), entry("n", "x\ny\r")]),
+        "a+b=%C3%A9*-._%7E&n=x%0D%0Ay%0D%0A".to_string())
+
+    test_eq!(encode_text_plain, text_plain([entry("a", "b c"), entry("d", "e\nf")]),
+        "a=b c\r\nd=e\r\nf\r\n".to_string())
+}
@@ -26,29 +26,48 @@ extern crate macros = "html5ever-macros";
 
 extern crate phf;
 extern crate time;
+extern crate arena;
+extern crate libc;
+extern crate atom = "html5ever-atom";
 
-pub use util::atom::Atom;
-pub use util::namespace::Namespace;
+pub use atom::Atom;
+pub use atom::Namespace;
 
-pub use driver::{one_input, ParseOpts, parse_to, parse};
+pub use driver::{one_input, ParseOpts, Parser, parse_to, parse_many_to, parse_reader_to};
+pub use driver::{parse, parse_reader};
 pub use serialize::serialize;
 
 mod util {
     #![macro_escape]
 
     pub mod str;
-    pub mod atom;
-    pub mod namespace;
     pub mod bitset;
+    pub mod utf8;
+    pub mod url;
+    pub mod unsupported;
 }
 
 pub mod tokenizer;
 pub mod tree_builder;
 pub mod serialize;
+pub mod selector;
+pub mod sanitize;
+pub mod text;
+pub mod markdown;
+pub mod diff;
+pub mod rewriter;
+pub mod links;
+pub mod structured;
+pub mod validate;
+pub mod outline;
+pub mod forms;
 
 /// Consumers of the parser API.
 pub mod sink {
     pub mod rcdom;
+    pub mod arenadom;
 }
 
 pub mod driver;
+
+pub mod capi;
@@ -0,0 +1,343 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Extracting the outgoing links and resource references of a document.
+//!
+//! URLs are resolved against the document's base URL, which comes from
+//! the first `<base href>` in tree order, if any.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let dom: RcDom = parse(one_input(page), Default::default());
+//! for link in extract_links(&dom.document, "http://example.com/a/").iter() {
+//!     match link.url {
+//!         Some(ref url) => crawl(url),
+//!         None => (),
+//!     }
+//! }
+//! ```
+
+use atom::Atom;
+use sink::rcdom::{Handle, NodeHandle, Element};
+use util::str::is_ascii_whitespace;
+use util::url::resolve;
+
+use std::ascii::StrAsciiExt;
+
+/// A reference to another resource.
+#[deriving(Clone)]
+pub struct Link {
+    /// The element containing the reference.
+    pub element: Handle,
+
+    /// The attribute containing the reference.
+    pub attribute: Atom,
+
+    /// The element's `rel` attribute, if any.
+    pub rel: Option<String>,
+
+    /// The URL as written.  For `srcset`, meta refresh and `style` this
+    /// is only the URL part of the attribute value.
+    pub raw: String,
+
+    /// The URL resolved against the document's base URL, or `None` if it
+    /// couldn't be resolved.
+    pub url: Option<String>,
+}
+
+/// The base URL of a document at `document_url`, from the first
+/// `<base href>` if there is one.
+pub fn base_url(document: &Handle, document_url: &str) -> String {
+    for node in document.descendants() {
+        let href = match node.borrow().node {
+            Element(atom!(base), _) => node.get_attribute("href"),
+            _ => None,
+        };
+        match href {
+            Some(href) => return resolve(document_url, href.as_slice())
+                .unwrap_or(document_url.to_string()),
+            None => (),
+        }
+    }
+    document_url.to_string()
+}
+
+static HREF: &'static [&'static str] = &["href"];
+static SRC: &'static [&'static str] = &["src"];
+static SRC_POSTER: &'static [&'static str] = &["src", "poster"];
+static DATA: &'static [&'static str] = &["data"];
+static ACTION: &'static [&'static str] = &["action"];
+static FORMACTION: &'static [&'static str] = &["formaction"];
+static CITE: &'static [&'static str] = &["cite"];
+static NONE: &'static [&'static str] = &[];
+
+// The attributes of an element which hold a single URL.
+fn url_attributes(name: &Atom) -> &'static [&'static str] {
+    match *name {
+        atom!(a) | atom!(area) | atom!(link) => HREF,
+        atom!(img) | atom!(source) | atom!(script) | atom!(iframe) | atom!(frame)
+        | atom!(embed) | atom!(track) | atom!(audio) => SRC,
+        atom!(video) => SRC_POSTER,
+        atom!(object) => DATA,
+        atom!(form) => ACTION,
+        atom!(button) | atom!(input) => FORMACTION,
+        atom!(blockquote) | atom!(q) | atom!(del) | atom!(ins) => CITE,
+        _ => NONE,
+    }
+}
+
+/// The URLs in a `srcset` attribute, without their descriptors.
+pub fn parse_srcset(srcset: &str) -> Vec<String> {
+    let mut urls = vec!();
+    let mut chars = srcset.chars().peekable();
+    loop {
+        // Skip whitespace and commas before the URL.
+        while chars.peek().map_or(false, |&c| is_ascii_whitespace(c) || c == ',') {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return urls;
+        }
+
+        let mut url = String::new();
+        while chars.peek().map_or(false, |&c| !is_ascii_whitespace(c)) {
+            url.push_char(chars.next().unwrap());
+        }
+
+        if url.as_slice().ends_with(",") {
+            // No descriptors.
+            let trimmed = url.as_slice().trim_right_chars(',').to_string();
+            url = trimmed;
+        } else {
+            // Skip descriptors, up to a comma outside parentheses.
+            let mut in_parens = false;
+            loop {
+                match chars.next() {
+                    None => break,
+                    Some('(') => in_parens = true,
+                    Some(')') => in_parens = false,
+                    Some(',') if !in_parens => break,
+                    Some(_) => (),
+                }
+            }
+        }
+
+        if !url.is_empty() {
+            urls.push(url);
+        }
+    }
+}
+
+/// The URL in the `content` of a `<meta http-equiv=refresh>`, as in
+/// `5; url=/next`.
+pub fn parse_refresh(content: &str) -> Option<String> {
+    let s = content.trim_left_chars(is_ascii_whitespace);
+    let s = s.trim_left_chars(|c: char| c.is_digit() || c == '.');
+    let s = s.trim_left_chars(is_ascii_whitespace);
+    let s = if s.starts_with(";") || s.starts_with(",") { s.slice_from(1) } else { s };
+    let s = s.trim_left_chars(is_ascii_whitespace);
+    if s.is_empty() {
+        return None;
+    }
+
+    let s = if s.len() >= 3 && s.is_char_boundary(3) && s.slice_to(3).eq_ignore_ascii_case("url") {
+        let rest = s.slice_from(3).trim_left_chars(is_ascii_whitespace);
+        if rest.starts_with("=") {
+            rest.slice_from(1).trim_left_chars(is_ascii_whitespace)
+        } else {
+            s
+        }
+    } else {
+        s
+    };
+
+    let url = match s.chars().next() {
+        Some(q) if q == '"' || q == '\'' => {
+            let rest = s.slice_from(1);
+            match rest.find(q) {
+                Some(i) => rest.slice_to(i),
+                None => rest,
+            }
+        }
+        _ => s.trim_right_chars(is_ascii_whitespace),
+    };
+    Some(url.to_string())
+}
+
+/// The URLs in `url()` functions in CSS, such as a `style` attribute.
+pub fn parse_css_urls(css: &str) -> Vec<String> {
+    let mut urls = vec!();
+    let lower = css.to_ascii_lower();
+    let mut pos = 0;
+    loop {
+        let start = match lower.as_slice().slice_from(pos).find_str("url(") {
+            Some(i) => pos + i + 4,
+            None => return urls,
+        };
+
+        let rest = css.slice_from(start).trim_left_chars(is_ascii_whitespace);
+        let mut url = String::new();
+        let mut chars = rest.char_indices();
+        let quote = match rest.chars().next() {
+            Some(q) if q == '"' || q == '\'' => {
+                chars.next();
+                Some(q)
+            }
+            _ => None,
+        };
+
+        let mut end = rest.len();
+        loop {
+            match chars.next() {
+                None => break,
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, c)) => url.push_char(c),
+                    None => break,
+                },
+                Some((i, c)) if Some(c) == quote => {
+                    end = i + 1;
+                    break;
+                }
+                Some((i, c)) if quote.is_none() && (c == ')' || is_ascii_whitespace(c)) => {
+                    end = i;
+                    break;
+                }
+                Some((_, c)) => url.push_char(c),
+            }
+        }
+
+        if !url.is_empty() {
+            urls.push(url);
+        }
+        pos = css.len() - rest.len() + end;
+    }
+}
+
+/// Find every reference to another resource in a document at
+/// `document_url`, in tree order.
+pub fn extract_links(document: &Handle, document_url: &str) -> Vec<Link> {
+    let base = base_url(document, document_url);
+    let mut links = vec!();
+
+    for node in document.descendants() {
+        let name = match node.borrow().node {
+            Element(ref name, _) => name.clone(),
+            _ => continue,
+        };
+        let rel = node.get_attribute("rel");
+        let add = |links: &mut Vec<Link>, attribute: &str, raw: String| {
+            links.push(Link {
+                element: node.clone(),
+                attribute: Atom::from_str(attribute),
+                rel: rel.clone(),
+                url: resolve(base.as_slice(), raw.as_slice()),
+                raw: raw,
+            });
+        };
+
+        for &attr in url_attributes(&name).iter() {
+            match node.get_attribute(attr) {
+                Some(value) => add(&mut links, attr, value),
+                None => (),
+            }
+        }
+
+        if name == atom!(img) || name == atom!(source) {
+            for url in node.get_attribute("srcset").iter().flat_map(|s| parse_srcset(s.as_slice()).move_iter()) {
+                add(&mut links, "srcset", url);
+            }
+        }
+
+        if name == atom!(meta) {
+            let refresh = node.get_attribute("http-equiv")
+                .map_or(false, |v| v.as_slice().eq_ignore_ascii_case("refresh"));
+            if refresh {
+                match node.get_attribute("content").and_then(|c| parse_refresh(c.as_slice())) {
+                    Some(url) => add(&mut links, "content", url),
+                    None => (),
+                }
+            }
+        }
+
+        for url in node.get_attribute("style").iter().flat_map(|s| parse_css_urls(s.as_slice()).move_iter()) {
+            add(&mut links, "style", url);
+        }
+    }
+
+    links
+}
+
+#[cfg(test)]
+mod test {
+    use super::{extract_links, base_url, parse_srcset, parse_refresh, parse_css_urls};
+
+    use driver::{parse, one_input};
+    use sink::rcdom::{RcDom, Handle};
+
+    use std::default::Default;
+
+    fn doc(html: &str) -> Handle {
+        let dom: RcDom = parse(one_input(html.to_string()), Default::default());
+        dom.document
+    }
+
+    fn strs(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn links(html: &str) -> Vec<String> {
+        extract_links(&doc(html), "http://example.com/dir/page.html").iter().map(|l| {
+            format!("{} {} {}", l.attribute.as_slice(), l.raw,
+                l.url.clone().unwrap_or("(none)".to_string()))
+        }).collect()
+    }
+
+    test_eq!(srcset, parse_srcset("a.png 1x, b.png 2x,c.png,, d(1).png 100w"),
+        strs(["a.png", "b.png", "c.png", "d(1).png"]))
+    test_eq!(srcset_parens, parse_srcset("a.png (x, y) 1x, b.png"), strs(["a.png", "b.png"]))
+
+    test_eq!(refresh, parse_refresh("5; url=/next"), Some("/next".to_string()))
+    test_eq!(refresh_quoted, parse_refresh("0;URL = 'a b'"), Some("a b".to_string()))
+    test_eq!(refresh_bare, parse_refresh("3, /x "), Some("/x".to_string()))
+    test_eq!(refresh_none, parse_refresh("5"), None)
+
+    test_eq!(css, parse_css_urls("background: URL( \"a\\\").png\" ); x: url(b.png) url()"),
+        strs(["a\").png", "b.png"]))
+
+    test_eq!(no_base, base_url(&doc("<p>x"), "http://e/a/b"), "http://e/a/b".to_string())
+    test_eq!(first_base, base_url(&doc("<base target=x><base href=/c/><base href=/d/>"), "http://e/a/b"),
+        "http://e/c/".to_string())
+
+    test_eq!(elements, links(concat!(
+            "<a href=x>1</a><img src=\"/i.png\" srcset=\"i2.png 2x\">",
+            "<link rel=stylesheet href=s.css><script src=//cdn.example.org/s.js></script>",
+            "<iframe src=f.html></iframe><form action=\"\"></form>",
+            "<meta http-equiv=Refresh content=\"1; url=../up\">",
+            "<div style=\"background: url(bg.png)\"></div><a>no href</a>")),
+        strs(["href x http://example.com/dir/x",
+              "src /i.png http://example.com/i.png",
+              "srcset i2.png http://example.com/dir/i2.png",
+              "href s.css http://example.com/dir/s.css",
+              "src //cdn.example.org/s.js http://cdn.example.org/s.js",
+              "src f.html http://example.com/dir/f.html",
+              "action  http://example.com/dir/page.html",
+              "content ../up http://example.com/up",
+              "style bg.png http://example.com/dir/bg.png"]))
+
+    test_eq!(with_base, links("<base href=\"http://other.example/b/\"><a href=c>c</a>"),
+        strs(["href c http://other.example/b/c"]))
+
+    #[test]
+    fn rel() {
+        let links = extract_links(&doc("<link rel=icon href=i.ico>"), "http://e/");
+        assert_eq!(links.len(), 1);
+        assert_eq!(links.get(0).rel, Some("icon".to_string()));
+    }
+}
@@ -0,0 +1,776 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Converting a parsed document to CommonMark.
+//!
+//! Headings, emphasis, links, images, lists, code, block quotes and
+//! horizontal rules map directly to Markdown.  Tables become GitHub-style
+//! pipe tables when every cell is simple inline content.  Anything else
+//! which can't be represented, such as `<sub>` or `<dl>`, is written out
+//! as inline or block HTML.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let dom: RcDom = parse(one_input(my_str), Default::default());
+//! let md = to_markdown(&dom.document, Default::default());
+//! ```
+
+use atom::Atom;
+use sink::rcdom::{Handle, NodeHandle, Document, Doctype, Text, Comment, Element};
+use serialize::{Serializable, Serializer, serialize};
+use util::str::is_ascii_whitespace;
+
+use std::cmp::max;
+use std::default::Default;
+use std::io::{Writer, IoResult, MemWriter};
+
+/// How to write links and images.
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum LinkStyle {
+    /// `[text](url "title")`
+    InlineLinks,
+
+    /// `[text][1]`, with `[1]: url "title"` at the end of the document.
+    ReferenceLinks,
+}
+
+/// Markdown conversion options, with an impl for Default.
+#[deriving(Clone)]
+pub struct MarkdownOpts {
+    /// How to write links and images.
+    pub link_style: LinkStyle,
+
+    /// The bullet for unordered list items: `-`, `*` or `+`.
+    pub bullet: char,
+}
+
+impl Default for MarkdownOpts {
+    fn default() -> MarkdownOpts {
+        MarkdownOpts {
+            link_style: InlineLinks,
+            bullet: '-',
+        }
+    }
+}
+
+// Elements which are dropped, along with their contents.
+fn is_skipped(name: &Atom) -> bool {
+    match *name {
+        atom!(head) | atom!(title) | atom!(meta) | atom!(link) | atom!(base)
+        | atom!(script) | atom!(style) | atom!(template) | atom!(noscript)
+            => true,
+        _ => false,
+    }
+}
+
+// Elements which start a new block.
+fn is_block(name: &Atom) -> bool {
+    match *name {
+        atom!(html) | atom!(body) | atom!(address) | atom!(article) | atom!(aside)
+        | atom!(blockquote) | atom!(center) | atom!(dd) | atom!(details)
+        | atom!(dialog) | atom!(dir) | atom!(div) | atom!(dl) | atom!(dt)
+        | atom!(fieldset) | atom!(figcaption) | atom!(figure) | atom!(footer)
+        | atom!(form) | atom!(h1) | atom!(h2) | atom!(h3) | atom!(h4)
+        | atom!(h5) | atom!(h6) | atom!(header) | atom!(hgroup) | atom!(hr)
+        | atom!(li) | atom!(listing) | atom!(main) | atom!(menu) | atom!(nav)
+        | atom!(ol) | atom!(p) | atom!(plaintext) | atom!(pre) | atom!(section)
+        | atom!(summary) | atom!(table) | atom!(ul) | atom!(xmp)
+            => true,
+        _ => false,
+    }
+}
+
+// Inline elements with no Markdown equivalent, which are written as HTML.
+fn is_inline_html(name: &Atom) -> bool {
+    match *name {
+        atom!(sub) | atom!(sup) | atom!(u) | atom!(ins) | atom!(del) | atom!(s)
+        | atom!(strike) | atom!(mark) | atom!(kbd) | atom!(abbr)
+        | atom!(input) | atom!(select) | atom!(textarea) | atom!(button)
+        | atom!(iframe) | atom!(video) | atom!(audio) | atom!(object)
+        | atom!(embed) | atom!(svg) | atom!(math) | atom!(canvas)
+            => true,
+        _ => false,
+    }
+}
+
+fn elem_name(node: &Handle) -> Option<Atom> {
+    match node.borrow().node {
+        Element(ref name, _) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+fn is_block_node(node: &Handle) -> bool {
+    elem_name(node).map_or(false, |n| is_block(&n))
+}
+
+fn is_document(node: &Handle) -> bool {
+    match node.borrow().node {
+        Document => true,
+        _ => false,
+    }
+}
+
+fn is_whitespace_text(node: &Handle) -> bool {
+    match node.borrow().node {
+        Text(ref t) => t.as_slice().chars().all(is_ascii_whitespace),
+        _ => false,
+    }
+}
+
+// Serializes a node including its own tags.
+struct WithSelf(Handle);
+
+impl Serializable for WithSelf {
+    fn serialize<'wr, Wr: Writer>(&self, serializer: &mut Serializer<'wr, Wr>, _incl_self: bool) -> IoResult<()> {
+        let WithSelf(ref node) = *self;
+        node.serialize(serializer, true)
+    }
+}
+
+fn to_html(node: &Handle) -> String {
+    let mut w = MemWriter::new();
+    serialize(&mut w, &WithSelf(node.clone()), Default::default())
+        .ok().expect("couldn't write to memory");
+    String::from_utf8(w.unwrap()).ok().expect("serializer wrote invalid UTF-8")
+}
+
+// Backslash-escape characters which could start Markdown syntax.
+fn escape_into(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '&' => {
+                out.push_char('\\');
+                out.push_char(c);
+            }
+            c if is_ascii_whitespace(c) => out.push_char(' '),
+            c => out.push_char(c),
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::new();
+    escape_into(text, &mut out);
+    out
+}
+
+// Escape the start of a paragraph which would otherwise be read as a
+// heading, block quote, list item, etc.
+fn escape_block_start(text: String) -> String {
+    let digits = text.as_slice().chars().take_while(|c| c.is_digit()).count();
+    match text.as_slice().chars().nth(digits) {
+        Some('.') | Some(')') if digits > 0 => {
+            let mut out = text.as_slice().slice_to(digits).to_string();
+            out.push_char('\\');
+            out.push_str(text.as_slice().slice_from(digits));
+            out
+        }
+        _ => match text.as_slice().chars().next() {
+            Some('#') | Some('>') | Some('-') | Some('+') | Some('=') | Some('~') | Some('|')
+                => format!("\\{}", text),
+            _ => text,
+        },
+    }
+}
+
+// Collapse spaces in inline content, and turn the '\n' we use for `<br>`
+// into `line_break`.
+fn normalize_inline(text: &str, line_break: &str) -> String {
+    let lines: Vec<String> = text.split('\n').map(|line| {
+        line.split(' ').filter(|w| !w.is_empty()).collect::<Vec<&str>>().connect(" ")
+    }).collect();
+
+    let mut lines = lines.as_slice();
+    while lines.last().map_or(false, |l| l.is_empty()) {
+        lines = lines.slice_to(lines.len() - 1);
+    }
+    while lines.head().map_or(false, |l| l.is_empty()) {
+        lines = lines.slice_from(1);
+    }
+    lines.connect(line_break)
+}
+
+fn longest_backtick_run(text: &str) -> uint {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        if c == '`' {
+            run += 1;
+            longest = max(longest, run);
+        } else {
+            run = 0;
+        }
+    }
+    longest
+}
+
+// A link destination, in angle brackets if it contains anything which
+// would otherwise end it.
+fn link_dest(url: &str) -> String {
+    let url: String = url.chars().filter(|&c| c != '\n' && c != '\r').collect();
+    if url.is_empty() || url.as_slice().chars().any(|c| c == ' ' || c == '(' || c == ')' || c == '<' || c == '>') {
+        format!("<{}>", url.as_slice().replace("<", "%3C").as_slice().replace(">", "%3E"))
+    } else {
+        url
+    }
+}
+
+fn link_title(title: Option<String>) -> String {
+    match title {
+        None => String::new(),
+        Some(t) => format!(" \"{}\"", t.as_slice().replace("\\", "\\\\").as_slice().replace("\"", "\\\"")),
+    }
+}
+
+// Prefix each line of `text`, using `empty_prefix` on blank lines.
+fn prefix_lines(text: &str, first: &str, rest: &str, empty_prefix: &str) -> String {
+    let mut out = String::new();
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push_char('\n');
+        }
+        if line.is_empty() {
+            out.push_str(empty_prefix);
+        } else {
+            out.push_str(if i == 0 { first } else { rest });
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+struct Block {
+    text: String,
+
+    /// Is this a list?  A list can follow a paragraph in a list item
+    /// without a blank line, which keeps the outer list tight.
+    list: bool,
+}
+
+fn block(text: String) -> Block {
+    Block {
+        text: text,
+        list: false,
+    }
+}
+
+fn join_blocks(blocks: &[Block], tight: bool) -> String {
+    let mut out = String::new();
+    for (i, b) in blocks.iter().enumerate() {
+        if i > 0 {
+            out.push_str(if tight && b.list { "\n" } else { "\n\n" });
+        }
+        out.push_str(b.text.as_slice());
+    }
+    out
+}
+
+struct Converter {
+    opts: MarkdownOpts,
+
+    /// Link reference definitions, as destination and title.
+    references: Vec<(String, String)>,
+}
+
+impl Converter {
+    // Convert the children of `node`, which may mix inline and block
+    // content, into blocks.
+    fn blocks(&mut self, node: &Handle) -> Vec<Block> {
+        let mut blocks = vec!();
+        let mut para = String::new();
+        for child in node.borrow().children.iter() {
+            if is_block_node(child) {
+                self.finish_paragraph(&mut para, &mut blocks);
+                self.block(child, &mut blocks);
+            } else {
+                self.inline(child, &mut para);
+            }
+        }
+        self.finish_paragraph(&mut para, &mut blocks);
+        blocks
+    }
+
+    fn finish_paragraph(&mut self, para: &mut String, blocks: &mut Vec<Block>) {
+        // Each line after a hard break could start a block too.
+        let text = normalize_inline(para.as_slice(), "\n");
+        if !text.is_empty() {
+            let lines: Vec<String> = text.as_slice().split('\n')
+                .map(|line| escape_block_start(line.to_string())).collect();
+            blocks.push(block(lines.connect("\\\n")));
+        }
+        para.truncate(0);
+    }
+
+    fn inline_text(&mut self, node: &Handle, line_break: &str) -> String {
+        let mut s = String::new();
+        self.inline_children(node, &mut s);
+        normalize_inline(s.as_slice(), line_break)
+    }
+
+    fn block(&mut self, node: &Handle, blocks: &mut Vec<Block>) {
+        let name = elem_name(node).expect("not an element");
+        let level = match name {
+            atom!(h1) => 1u, atom!(h2) => 2, atom!(h3) => 3,
+            atom!(h4) => 4, atom!(h5) => 5, atom!(h6) => 6,
+            _ => 0,
+        };
+        if level > 0 {
+            let text = self.inline_text(node, " ");
+            if !text.is_empty() {
+                blocks.push(block(format!("{} {}", String::from_char(level, '#'), text)));
+            }
+            return;
+        }
+
+        match name {
+            atom!(blockquote) => {
+                let inner = self.blocks(node);
+                if !inner.is_empty() {
+                    let text = join_blocks(inner.as_slice(), false);
+                    blocks.push(block(prefix_lines(text.as_slice(), "> ", "> ", ">")));
+                }
+            }
+
+            atom!(ul) | atom!(menu) | atom!(dir) => {
+                let list = self.list(node, None);
+                blocks.push(list);
+            }
+
+            atom!(ol) => {
+                let start = node.get_attribute("start")
+                    .and_then(|s| from_str(s.as_slice().trim()))
+                    .unwrap_or(1u);
+                let list = self.list(node, Some(start));
+                blocks.push(list);
+            }
+
+            atom!(pre) | atom!(listing) | atom!(xmp) | atom!(plaintext)
+                => blocks.push(block(code_block(node))),
+
+            atom!(hr) => blocks.push(block("---".to_string())),
+
+            atom!(table) => {
+                let table = match self.table(node) {
+                    Some(t) => t,
+                    None => to_html(node),
+                };
+                blocks.push(block(table));
+            }
+
+            atom!(dl) | atom!(details) => blocks.push(block(to_html(node))),
+
+            _ => {
+                let inner = self.blocks(node);
+                blocks.push_all_move(inner);
+            }
+        }
+    }
+
+    fn list(&mut self, node: &Handle, start: Option<uint>) -> Block {
+        // Each item is a list of blocks.  Anything other than an <li>
+        // directly inside the list, such as a nested list, is added to
+        // the previous item.
+        let mut items: Vec<Vec<Block>> = vec!();
+        for child in node.borrow().children.iter() {
+            if is_whitespace_text(child) {
+                continue;
+            }
+            match elem_name(child) {
+                Some(atom!(li)) => {
+                    let item = self.blocks(child);
+                    items.push(item);
+                }
+                _ => {
+                    let mut extra = vec!();
+                    if is_block_node(child) {
+                        self.block(child, &mut extra);
+                    } else {
+                        let mut para = String::new();
+                        self.inline(child, &mut para);
+                        self.finish_paragraph(&mut para, &mut extra);
+                    }
+                    if items.is_empty() {
+                        items.push(vec!());
+                    }
+                    items.mut_last().unwrap().push_all_move(extra);
+                }
+            }
+        }
+
+        // A list is loose if any item has more than one paragraph or
+        // other non-list block.
+        let loose = items.iter().any(|item| item.iter().filter(|b| !b.list).count() > 1);
+
+        let mut out = String::new();
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                out.push_str(if loose { "\n\n" } else { "\n" });
+            }
+            let marker = match start {
+                None => format!("{} ", self.opts.bullet),
+                Some(n) => format!("{}. ", n + i),
+            };
+            let indent = String::from_char(marker.len(), ' ');
+            let text = join_blocks(item.as_slice(), true);
+            if text.is_empty() {
+                out.push_str(marker.as_slice().trim_right());
+            } else {
+                out.push_str(prefix_lines(text.as_slice(), marker.as_slice(), indent.as_slice(), "").as_slice());
+            }
+        }
+
+        Block {
+            text: out,
+            list: true,
+        }
+    }
+
+    // A pipe table, or `None` if the table is too complicated.
+    fn table(&mut self, node: &Handle) -> Option<String> {
+        let mut rows = vec!();
+        for child in node.borrow().children.iter() {
+            match elem_name(child) {
+                Some(atom!(thead)) | Some(atom!(tbody)) | Some(atom!(tfoot)) => {
+                    for row in child.borrow().children.iter() {
+                        match elem_name(row) {
+                            Some(atom!(tr)) => rows.push(row.clone()),
+                            Some(_) => return None,
+                            None => (),
+                        }
+                    }
+                }
+                Some(atom!(tr)) => rows.push(child.clone()),
+                Some(atom!(colgroup)) => (),
+                Some(_) => return None,
+                None => (),
+            }
+        }
+        if rows.is_empty() {
+            return None;
+        }
+
+        let mut cells: Vec<Vec<String>> = vec!();
+        for row in rows.iter() {
+            let mut row_cells = vec!();
+            for cell in row.borrow().children.iter() {
+                match elem_name(cell) {
+                    Some(atom!(td)) | Some(atom!(th)) => (),
+                    Some(_) => return None,
+                    None => continue,
+                }
+                let spans = ["colspan", "rowspan"].iter().any(|&a|
+                    cell.get_attribute(a).map_or(false, |v| v.as_slice().trim() != "1"));
+                if spans || cell.descendants().any(|d| is_block_node(&d)) {
+                    return None;
+                }
+                let text = self.inline_text(cell, "<br>");
+                row_cells.push(text.as_slice().replace("|", "\\|"));
+            }
+            cells.push(row_cells);
+        }
+
+        let ncols = max(1, cells.iter().map(|r| r.len()).max().unwrap_or(0));
+        let mut out = String::new();
+        for (i, row) in cells.iter().enumerate() {
+            if i == 1 {
+                out.push_str("\n|");
+                for _ in range(0, ncols) {
+                    out.push_str(" --- |");
+                }
+            }
+            if i > 0 {
+                out.push_char('\n');
+            }
+            out.push_char('|');
+            for j in range(0, ncols) {
+                let text = row.as_slice().get(j).map_or("", |t| t.as_slice());
+                if text.is_empty() {
+                    out.push_str(" |");
+                } else {
+                    out.push_str(format!(" {} |", text).as_slice());
+                }
+            }
+        }
+        if cells.len() == 1 {
+            out.push_str("\n|");
+            for _ in range(0, ncols) {
+                out.push_str(" --- |");
+            }
+        }
+        Some(out)
+    }
+
+    fn inline_children(&mut self, node: &Handle, out: &mut String) {
+        for child in node.borrow().children.iter() {
+            self.inline(child, out);
+        }
+    }
+
+    fn inline(&mut self, node: &Handle, out: &mut String) {
+        let name = match node.borrow().node {
+            Text(ref t) => return escape_into(t.as_slice(), out),
+            Comment(_) | Doctype(..) => return,
+            Document => return self.inline_children(node, out),
+            Element(ref name, _) => name.clone(),
+        };
+
+        if is_skipped(&name) {
+            return;
+        }
+
+        match name {
+            atom!(br) => out.push_char('\n'),
+            atom!(em) | atom!(i) => self.wrap(node, "*", out),
+            atom!(strong) | atom!(b) => self.wrap(node, "**", out),
+            atom!(code) | atom!(tt) | atom!(samp) => code_span(node, out),
+            atom!(a) => self.link(node, out),
+            atom!(img) => self.image(node, out),
+            _ if is_inline_html(&name) => out.push_str(to_html(node).as_slice()),
+            _ if is_block(&name) => {
+                // A block inside inline content, e.g. a <div> in a link.
+                out.push_char(' ');
+                self.inline_children(node, out);
+                out.push_char(' ');
+            }
+            _ => self.inline_children(node, out),
+        }
+    }
+
+    // Wrap inline content in emphasis delimiters, keeping any spaces
+    // at either end outside them.
+    fn wrap(&mut self, node: &Handle, delim: &str, out: &mut String) {
+        let mut s = String::new();
+        self.inline_children(node, &mut s);
+        let trimmed = s.as_slice().trim_chars(' ');
+        if trimmed.is_empty() {
+            out.push_str(s.as_slice());
+            return;
+        }
+        if s.as_slice().starts_with(" ") {
+            out.push_char(' ');
+        }
+        out.push_str(delim);
+        out.push_str(trimmed);
+        out.push_str(delim);
+        if s.as_slice().ends_with(" ") {
+            out.push_char(' ');
+        }
+    }
+
+    // A link or image target: inline, or a reference to a definition at
+    // the end of the document.
+    fn target(&mut self, dest: String, title: String) -> String {
+        match self.opts.link_style {
+            InlineLinks => format!("({}{})", dest, title),
+            ReferenceLinks => {
+                let pos = self.references.iter().position(|&(ref d, ref t)| *d == dest && *t == title);
+                let n = match pos {
+                    Some(i) => i + 1,
+                    None => {
+                        self.references.push((dest, title));
+                        self.references.len()
+                    }
+                };
+                format!("[{}]", n)
+            }
+        }
+    }
+
+    fn link(&mut self, node: &Handle, out: &mut String) {
+        let href = match node.get_attribute("href") {
+            None => return self.inline_children(node, out),
+            Some(h) => h,
+        };
+
+        let title = node.get_attribute("title");
+        let is_autolink = title.is_none()
+            && node.text_content() == href
+            && (href.as_slice().starts_with("http://") || href.as_slice().starts_with("https://")
+                || href.as_slice().starts_with("mailto:"))
+            && !href.as_slice().chars().any(|c| c == '<' || c == '>' || is_ascii_whitespace(c));
+        if is_autolink {
+            out.push_str(format!("<{}>", href).as_slice());
+            return;
+        }
+
+        let mut text = String::new();
+        self.inline_children(node, &mut text);
+        let text = normalize_inline(text.as_slice(), " ");
+        let target = self.target(link_dest(href.as_slice()), link_title(title));
+        out.push_str(format!("[{}]{}", text, target).as_slice());
+    }
+
+    fn image(&mut self, node: &Handle, out: &mut String) {
+        let src = unwrap_or_return!(node.get_attribute("src"), ());
+        let alt = escape(node.get_attribute("alt").unwrap_or(String::new()).as_slice());
+        let target = self.target(link_dest(src.as_slice()), link_title(node.get_attribute("title")));
+        out.push_str(format!("![{}]{}", alt, target).as_slice());
+    }
+}
+
+fn code_span(node: &Handle, out: &mut String) {
+    let text: String = node.text_content().as_slice().chars()
+        .map(|c| if c == '\n' { ' ' } else { c }).collect();
+    if text.is_empty() {
+        return;
+    }
+    let delim = String::from_char(longest_backtick_run(text.as_slice()) + 1, '`');
+    let pad = text.as_slice().starts_with("`") || text.as_slice().ends_with("`");
+    out.push_str(delim.as_slice());
+    if pad {
+        out.push_char(' ');
+    }
+    out.push_str(text.as_slice());
+    if pad {
+        out.push_char(' ');
+    }
+    out.push_str(delim.as_slice());
+}
+
+// A fenced code block, with the language from a `<code class="language-x">`.
+fn code_block(node: &Handle) -> String {
+    let children: Vec<Handle> = node.borrow().children.iter()
+        .filter(|c| !is_whitespace_text(*c)).map(|c| c.clone()).collect();
+    let lang = match children.as_slice() {
+        [ref code] if elem_name(code) == Some(atom!(code)) => {
+            code.get_attribute("class").and_then(|class| {
+                class.as_slice().words()
+                    .filter_map(|w| if w.starts_with("language-") {
+                        Some(w.slice_from("language-".len()).to_string())
+                    } else {
+                        None
+                    }).next()
+            })
+        }
+        _ => None,
+    };
+
+    let mut text = node.text_content();
+    if text.as_slice().ends_with("\n") {
+        let len = text.len();
+        text.truncate(len - 1);
+    }
+
+    let fence = String::from_char(max(3, longest_backtick_run(text.as_slice()) + 1), '`');
+    format!("{}{}\n{}\n{}", fence, lang.unwrap_or(String::new()), text, fence)
+}
+
+/// Convert a node and its descendants to CommonMark.
+pub fn to_markdown(node: &Handle, opts: MarkdownOpts) -> String {
+    let mut conv = Converter {
+        opts: opts,
+        references: vec!(),
+    };
+
+    let mut blocks = vec!();
+    if is_block_node(node) {
+        conv.block(node, &mut blocks);
+    } else if is_document(node) {
+        blocks = conv.blocks(node);
+    } else {
+        let mut para = String::new();
+        conv.inline(node, &mut para);
+        conv.finish_paragraph(&mut para, &mut blocks);
+    }
+
+    let mut out = join_blocks(blocks.as_slice(), false);
+    if !conv.references.is_empty() {
+        out.push_char('\n');
+        for (i, &(ref dest, ref title)) in conv.references.iter().enumerate() {
+            out.push_str(format!("\n[{}]: {}{}", i + 1, dest, title).as_slice());
+        }
+    }
+    if !out.is_empty() {
+        out.push_char('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MarkdownOpts, ReferenceLinks, to_markdown};
+
+    use driver::{parse, one_input};
+    use sink::rcdom::RcDom;
+
+    use std::default::Default;
+
+    fn md_with(html: &str, opts: MarkdownOpts) -> String {
+        let dom: RcDom = parse(one_input(html.to_string()), Default::default());
+        to_markdown(&dom.document, opts)
+    }
+
+    fn md(html: &str) -> String {
+        md_with(html, Default::default())
+    }
+
+    test_eq!(headings, md("<h1>Title</h1><h2>Sub <em>x</em></h2>"),
+        "# Title\n\n## Sub *x*\n".to_string())
+
+    test_eq!(emphasis, md("<p>a <em>b</em> <strong>c</strong> <b> d </b>e</p>"),
+        "a *b* **c** **d** e\n".to_string())
+
+    test_eq!(escaping, md("<p>1. not a list *really* [x]</p>"),
+        "1\\. not a list \\*really\\* \\[x\\]\n".to_string())
+
+    test_eq!(links, md("<a href=\"http://example.com/\" title=\"T\">ex</a> <a href=\"http://example.com/\">http://example.com/</a>"),
+        "[ex](http://example.com/ \"T\") <http://example.com/>\n".to_string())
+
+    test_eq!(link_with_space, md("<a href=\"/a b\">x</a>"),
+        "[x](</a b>)\n".to_string())
+
+    test_eq!(image, md("<img src=\"/i.png\" alt=\"a [b]\" title=\"t\">"),
+        "![a \\[b\\]](/i.png \"t\")\n".to_string())
+
+    #[test]
+    fn reference_links() {
+        let opts = MarkdownOpts {
+            link_style: ReferenceLinks,
+            .. Default::default()
+        };
+        assert_eq!(md_with("<p><a href=\"/a\">x</a> <a href=\"/b\">y</a> <a href=\"/a\">z</a> <img src=\"/i.png\" alt=\"pic\"></p>", opts),
+            "[x][1] [y][2] [z][1] ![pic][3]\n\n[1]: /a\n[2]: /b\n[3]: /i.png\n".to_string());
+    }
+
+    test_eq!(nested_list, md("<ul><li>a</li><li>b<ul><li>c</li></ul></li></ul>"),
+        "- a\n- b\n  - c\n".to_string())
+
+    test_eq!(ordered_start, md("<ol start=\"3\"><li>x</li><li>y</li></ol>"),
+        "3. x\n4. y\n".to_string())
+
+    test_eq!(loose_list, md("<ul><li><p>a</p><p>b</p></li><li>c</li></ul>"),
+        "- a\n\n  b\n\n- c\n".to_string())
+
+    test_eq!(code_block, md("<pre><code class=\"language-rust\">fn main() {\n    x\n}\n</code></pre>"),
+        "```rust\nfn main() {\n    x\n}\n```\n".to_string())
+
+    test_eq!(code_block_fence, md("<pre>a ``` b</pre>"),
+        "````\na ``` b\n````\n".to_string())
+
+    test_eq!(code_span, md("<p>use <code>a`b</code> and <code>`c</code></p>"),
+        "use ``a`b`` and `` `c ``\n".to_string())
+
+    test_eq!(blockquote, md("<blockquote><p>a</p><p>b</p></blockquote>"),
+        "> a\n>\n> b\n".to_string())
+
+    test_eq!(table, md("<table><tr><th>A</th><th>B</th></tr><tr><td>1</td><td>x|y</td></tr></table>"),
+        "| A | B |\n| --- | --- |\n| 1 | x\\|y |\n".to_string())
+
+    test_eq!(table_fallback, md("<table><tr><td colspan=2>a</td></tr></table>"),
+        "<table><tbody><tr><td colspan=\"2\">a</td></tr></tbody></table>\n".to_string())
+
+    test_eq!(inline_html, md("<p>H<sub>2</sub>O</p>"), "H<sub>2</sub>O\n".to_string())
+    test_eq!(hard_break, md("<p>a<br>b</p>"), "a\\\nb\n".to_string())
+    test_eq!(hard_break_block_start, md("<p>a<br># b<br>1. c</p>"), "a\\\n\\# b\\\n1\\. c\n".to_string())
+    test_eq!(hr, md("<p>a</p><hr><p>b</p>"), "a\n\n---\n\nb\n".to_string())
+    test_eq!(skipped, md("<title>t</title><p>a<script>x</script></p>"), "a\n".to_string())
+    test_eq!(dl, md("<dl><dt>t</dt><dd>d</dd></dl>"), "<dl><dt>t</dt><dd>d</dd></dl>\n".to_string())
+}
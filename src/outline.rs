@@ -0,0 +1,326 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The document outline, built from headings and sectioning elements.
+//!
+//! This follows the outline algorithm from the HTML specification.
+//! Sectioning roots nested in the body, like `<blockquote>`, have
+//! outlines of their own and don't contribute to the document's.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let dom: RcDom = parse(one_input(page), Default::default());
+//! let outline = outline(&dom.document);
+//! for issue in outline.issues.iter() {
+//!     match *issue {
+//!         SkippedLevel(_, from, to) => println!("h{} follows h{}", to, from),
+//!         Untitled(_) => println!("section without a heading"),
+//!     }
+//! }
+//! ```
+
+use atom::Atom;
+use atom::namespace::HTML;
+use sink::rcdom::{Handle, NodeHandle, Element};
+use tree_builder::heading_tag;
+
+/// The heading of a section.
+#[deriving(Clone)]
+pub struct Heading {
+    /// The `h1`–`h6` element.  For an `hgroup`, this is its
+    /// highest-ranked heading.
+    pub element: Handle,
+
+    /// The heading level, from 1 for `h1` to 6 for `h6`.
+    pub level: uint,
+
+    /// The heading's text, with whitespace collapsed.
+    pub text: String,
+}
+
+/// A section of the outline.
+#[deriving(Clone)]
+pub struct Section {
+    /// The sectioning element, or `None` for a section implied by a
+    /// heading.
+    pub element: Option<Handle>,
+
+    /// The section's heading, if it has one.
+    pub heading: Option<Heading>,
+
+    pub subsections: Vec<Section>,
+}
+
+impl Section {
+    fn new(element: Option<Handle>, heading: Option<Heading>) -> Section {
+        Section {
+            element: element,
+            heading: heading,
+            subsections: vec!(),
+        }
+    }
+}
+
+/// A problem with the heading structure.
+#[deriving(Clone)]
+pub enum Issue {
+    /// A heading more than one level below the heading before it, as in
+    /// an `h4` after an `h2`.  Holds the heading, the previous level and
+    /// this heading's level.
+    SkippedLevel(Handle, uint, uint),
+
+    /// A `<section>` or `<article>` without a heading.
+    Untitled(Handle),
+}
+
+/// A document's outline and the problems found building it.
+pub struct Outline {
+    pub sections: Vec<Section>,
+    pub issues: Vec<Issue>,
+}
+
+enum Kind {
+    SectioningContent,
+    SectioningRoot,
+    Other,
+}
+
+fn elem_name(node: &Handle) -> Option<Atom> {
+    match node.borrow().node {
+        Element(ref name, _) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+fn kind(name: &Atom) -> Kind {
+    match *name {
+        atom!(article) | atom!(aside) | atom!(nav) | atom!(section) => SectioningContent,
+        atom!(blockquote) | atom!(body) | atom!(details) | atom!(fieldset)
+        | atom!(dialog) | atom!(figure) | atom!(td) => SectioningRoot,
+        _ => Other,
+    }
+}
+
+fn heading_level(name: &Atom) -> Option<uint> {
+    if !heading_tag((HTML, name.clone())) {
+        return None;
+    }
+    name.as_slice().char_at(1).to_digit(10)
+}
+
+// The heading represented by an `h1`–`h6` element, or the highest-ranked
+// heading in an `hgroup`.
+fn heading(node: &Handle, name: &Atom) -> Option<Heading> {
+    let (element, level) = match heading_level(name) {
+        Some(level) => (node.clone(), level),
+        None if *name == atom!(hgroup) => {
+            let mut best: Option<(Handle, uint)> = None;
+            for desc in node.descendants() {
+                let level = match elem_name(&desc).and_then(|n| heading_level(&n)) {
+                    Some(level) => level,
+                    None => continue,
+                };
+                if best.as_ref().map_or(true, |&(_, l)| level < l) {
+                    best = Some((desc.clone(), level));
+                }
+            }
+            unwrap_or_return!(best, None)
+        }
+        None => return None,
+    };
+
+    let text = element.text_content();
+    let words: Vec<&str> = text.as_slice().words().collect();
+    Some(Heading {
+        element: element,
+        level: level,
+        text: words.connect(" "),
+    })
+}
+
+fn section_at<'a>(outline: &'a mut Vec<Section>, path: &[uint]) -> &'a mut Section {
+    let mut section = outline.get_mut(path[0]);
+    for &i in path.slice_from(1).iter() {
+        let parent = section;
+        section = parent.subsections.get_mut(i);
+    }
+    section
+}
+
+struct Builder {
+    issues: Vec<Issue>,
+    last_level: Option<uint>,
+}
+
+impl Builder {
+    // The outline of a sectioning element.
+    fn outline_of(&mut self, owner: &Handle) -> Vec<Section> {
+        let mut outline = vec!(Section::new(Some(owner.clone()), None));
+        let mut current = vec!(0u);
+        for child in owner.borrow().children.iter() {
+            self.walk(child, &mut outline, &mut current);
+        }
+
+        match elem_name(owner) {
+            Some(atom!(section)) | Some(atom!(article)) if outline.get(0).heading.is_none()
+                => self.issues.push(Untitled(owner.clone())),
+            _ => (),
+        }
+        outline
+    }
+
+    fn walk(&mut self, node: &Handle, outline: &mut Vec<Section>, current: &mut Vec<uint>) {
+        let name = unwrap_or_return!(elem_name(node), ());
+        match kind(&name) {
+            SectioningRoot => return,
+            SectioningContent => {
+                let sub = self.outline_of(node);
+                *current = vec!(outline.len() - 1);
+                section_at(outline, current.as_slice()).subsections.push_all_move(sub);
+                return;
+            }
+            Other => (),
+        }
+
+        match heading(node, &name) {
+            Some(h) => self.heading(h, outline, current),
+            None => for child in node.borrow().children.iter() {
+                self.walk(child, outline, current);
+            },
+        }
+    }
+
+    fn heading(&mut self, h: Heading, outline: &mut Vec<Section>, current: &mut Vec<uint>) {
+        match self.last_level {
+            Some(prev) if h.level > prev + 1
+                => self.issues.push(SkippedLevel(h.element.clone(), prev, h.level)),
+            _ => (),
+        }
+        self.last_level = Some(h.level);
+
+        if section_at(outline, current.as_slice()).heading.is_none() {
+            section_at(outline, current.as_slice()).heading = Some(h);
+            return;
+        }
+
+        // A heading of the same or higher rank than the last top-level
+        // section's starts a new top-level section.
+        let top_level = match outline.last().unwrap().heading {
+            Some(ref last) => h.level <= last.level,
+            None => true,
+        };
+        if top_level {
+            outline.push(Section::new(None, Some(h)));
+            *current = vec!(outline.len() - 1);
+            return;
+        }
+
+        // Otherwise it's a subsection of the nearest enclosing section
+        // with a higher-ranked heading.
+        let mut path = current.clone();
+        loop {
+            let higher = match section_at(outline, path.as_slice()).heading {
+                Some(ref candidate) => h.level > candidate.level,
+                None => true,
+            };
+            if higher || path.len() == 1 {
+                let section = section_at(outline, path.as_slice());
+                section.subsections.push(Section::new(None, Some(h)));
+                path.push(section.subsections.len() - 1);
+                break;
+            }
+            path.pop();
+        }
+        *current = path;
+    }
+}
+
+/// Build the outline of a document's body.  For a document without a
+/// `<body>`, such as a parsed fragment, the whole document is outlined.
+pub fn outline(document: &Handle) -> Outline {
+    let root = document.descendants()
+        .find(|n| elem_name(n) == Some(atom!(body)))
+        .unwrap_or(document.clone());
+
+    let mut builder = Builder {
+        issues: vec!(),
+        last_level: None,
+    };
+    let sections = builder.outline_of(&root);
+    Outline {
+        sections: sections,
+        issues: builder.issues,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{outline, Section, SkippedLevel, Untitled};
+
+    use driver::{parse, one_input};
+    use sink::rcdom::{RcDom, Element};
+
+    use std::default::Default;
+
+    fn render(sections: &[Section], depth: uint, out: &mut Vec<String>) {
+        for s in sections.iter() {
+            let title = match s.heading {
+                Some(ref h) => format!("h{} {}", h.level, h.text),
+                None => "(untitled)".to_string(),
+            };
+            out.push(format!("{}{}", "  ".repeat(depth), title));
+            render(s.subsections.as_slice(), depth + 1, out);
+        }
+    }
+
+    fn sections(body: &str) -> Vec<String> {
+        let dom: RcDom = parse(one_input(body.to_string()), Default::default());
+        let mut out = vec!();
+        render(outline(&dom.document).sections.as_slice(), 0, &mut out);
+        out
+    }
+
+    fn issues(body: &str) -> Vec<String> {
+        let dom: RcDom = parse(one_input(body.to_string()), Default::default());
+        outline(&dom.document).issues.iter().map(|i| match *i {
+            SkippedLevel(_, from, to) => format!("skipped h{} -> h{}", from, to),
+            Untitled(ref elem) => match elem.borrow().node {
+                Element(ref name, _) => format!("untitled <{}>", name.as_slice()),
+                _ => fail!(),
+            },
+        }).collect()
+    }
+
+    fn strs(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    test_eq!(implied, sections("<h1>A</h1><h2>B</h2><p>x</p><h2>C</h2><h3>D</h3><h1>E</h1>"),
+        strs(["h1 A", "  h2 B", "  h2 C", "    h3 D", "h1 E"]))
+
+    test_eq!(sectioning, sections(concat!(
+            "<h1>A</h1><section><h3>B</h3><article><h1>C</h1></article></section>",
+            "<nav><p>x</p></nav>")),
+        strs(["h1 A", "  h3 B", "    h1 C", "  (untitled)"]))
+
+    test_eq!(hgroup, sections("<hgroup><h2>A</h2><h1> Main\n title </h1></hgroup><h2>B</h2>"),
+        strs(["h1 Main title", "  h2 B"]))
+
+    test_eq!(roots_excluded, sections("<h1>A</h1><blockquote><h1>Q</h1></blockquote><h2>B</h2>"),
+        strs(["h1 A", "  h2 B"]))
+
+    test_eq!(empty, sections("<p>x</p>"), strs(["(untitled)"]))
+
+    test_eq!(skipped, issues("<h1>A</h1><h3>B</h3><h2>C</h2><section><h4>D</h4></section>"),
+        strs(["skipped h1 -> h3", "skipped h2 -> h4"]))
+
+    test_eq!(untitled, issues("<h1>A</h1><section><p>x</p></section><aside></aside><article><h2>B</h2></article>"),
+        strs(["untitled <section>"]))
+}
@@ -0,0 +1,643 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Streaming HTML rewriting, without building a tree.
+//!
+//! The rewriter sits directly on the tokenizer output and writes the
+//! rewritten document as each token arrives.  It tracks a lightweight
+//! stack of open elements, with a few rules for implied end tags
+//! (`<li>`, `<p>`, table cells, etc.) but none of the tree builder's
+//! error recovery.
+//!
+//! Handlers are registered for selectors, which may use type, id, class
+//! and attribute selectors, `:not()`, and the descendant and child
+//! combinators.  Anything which would need to look at siblings or later
+//! content can't be matched while streaming and is rejected.
+//!
+//! Tokens are re-serialized, so the output is equivalent to the input
+//! but not byte-for-byte identical: attribute values are always quoted,
+//! character references are normalized, and so on.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let mut rw = Rewriter::new(stdout());
+//! rw.on("a[href^=http]", box AddNofollow as Box<ElementHandler>).unwrap();
+//! for chunk in input {
+//!     try!(rw.feed(chunk));
+//! }
+//! try!(rw.end());
+//! ```
+
+use atom::Atom;
+use selector::{SelectorNode, Selector, parse_selectors, matches};
+use selector::{Descendant, Child, SimpleSelector, LocalName, Id, Class, Attr, Negation};
+use serialize::is_void_element;
+use tokenizer::{Tokenizer, TokenSink, Token, Tag, Attribute, AttrName, StartTag, EndTag};
+use tokenizer::{DoctypeToken, TagToken, CommentToken, CharacterTokens, NullCharacterToken};
+use tokenizer::{EOFToken, ParseError};
+use tokenizer::states::{State, RawData, Rcdata, Rawtext, ScriptData, Plaintext};
+
+use std::default::Default;
+use std::io::{Writer, IoResult, IoError};
+
+/// An element start tag passed to a handler, with methods to change the
+/// element and inject content around it.
+///
+/// Injected content is HTML, written as is.
+pub struct RewriteElement {
+    name: Atom,
+    attrs: Vec<Attribute>,
+    before: String,
+    prepend: String,
+    append: String,
+    after: String,
+    replacement: Option<String>,
+    removed: bool,
+    unwrapped: bool,
+}
+
+impl RewriteElement {
+    /// The element's tag name.
+    pub fn name(&self) -> Atom {
+        self.name.clone()
+    }
+
+    /// Get the value of an attribute.
+    pub fn get_attribute(&self, name: &str) -> Option<String> {
+        self.attrs.iter().find(|a| a.name.as_slice() == name).map(|a| a.value.clone())
+    }
+
+    /// Set an attribute, replacing any existing value.
+    pub fn set_attribute(&mut self, name: &str, value: String) {
+        match self.attrs.iter().position(|a| a.name.as_slice() == name) {
+            Some(i) => self.attrs.get_mut(i).value = value,
+            None => self.attrs.push(Attribute {
+                name: AttrName::new(Atom::from_str(name)),
+                value: value,
+            }),
+        }
+    }
+
+    /// Remove an attribute, if present.
+    pub fn remove_attribute(&mut self, name: &str) {
+        self.attrs.retain(|a| a.name.as_slice() != name);
+    }
+
+    /// Insert HTML just before the start tag.
+    pub fn before(&mut self, html: &str) {
+        self.before.push_str(html);
+    }
+
+    /// Insert HTML just after the start tag.  Does nothing for void
+    /// elements.
+    pub fn prepend(&mut self, html: &str) {
+        self.prepend.push_str(html);
+    }
+
+    /// Insert HTML just before the end tag, or where the element is
+    /// implicitly closed.  Does nothing for void elements.
+    pub fn append(&mut self, html: &str) {
+        self.append.push_str(html);
+    }
+
+    /// Insert HTML just after the end tag.
+    pub fn after(&mut self, html: &str) {
+        self.after.push_str(html);
+    }
+
+    /// Replace the element and its contents with HTML.
+    pub fn replace(&mut self, html: &str) {
+        self.replacement = Some(html.to_string());
+    }
+
+    /// Remove the element and its contents.
+    pub fn remove(&mut self) {
+        self.removed = true;
+    }
+
+    /// Remove the start and end tags, but keep the contents.
+    pub fn remove_and_keep_content(&mut self) {
+        self.unwrapped = true;
+    }
+}
+
+/// Types which can rewrite matching elements.
+pub trait ElementHandler {
+    /// Called at the start tag of each matching element, in document
+    /// order.  Handlers are not called for the descendants of an element
+    /// which has been removed or replaced.
+    fn element(&mut self, elem: &mut RewriteElement);
+}
+
+// An element on the stack, as it appeared in the input.
+struct OpenElem {
+    name: Atom,
+    attrs: Vec<Attribute>,
+
+    // Output at the end of the element.
+    append: String,
+    after: String,
+    end_tag: bool,
+
+    // Is this element's content being dropped?
+    suppresses: bool,
+}
+
+// A view of an open element for selector matching.  Only its ancestors
+// are known, so the selectors are restricted to those which don't need
+// siblings or children.
+#[deriving(Clone)]
+struct StackNode<'a> {
+    stack: &'a [OpenElem],
+    pos: uint,
+}
+
+impl<'a> SelectorNode for StackNode<'a> {
+    fn same_node(&self, other: &StackNode<'a>) -> bool {
+        self.pos == other.pos
+    }
+
+    fn parent_node(&self) -> Option<StackNode<'a>> {
+        match self.pos {
+            0 => None,
+            p => Some(StackNode {
+                stack: self.stack,
+                pos: p - 1,
+            }),
+        }
+    }
+
+    fn first_child_node(&self) -> Option<StackNode<'a>> {
+        None
+    }
+
+    fn prev_sibling_node(&self) -> Option<StackNode<'a>> {
+        None
+    }
+
+    fn next_sibling_node(&self) -> Option<StackNode<'a>> {
+        None
+    }
+
+    fn local_name(&self) -> Option<Atom> {
+        Some(self.stack[self.pos].name.clone())
+    }
+
+    fn attr_value(&self, name: &str) -> Option<String> {
+        self.stack[self.pos].attrs.iter()
+            .find(|a| a.name.as_slice() == name)
+            .map(|a| a.value.clone())
+    }
+
+    fn is_nonempty_text(&self) -> bool {
+        false
+    }
+}
+
+fn check_simple(simple: &SimpleSelector) -> bool {
+    match *simple {
+        LocalName(_) | Id(_) | Class(_) | Attr(..) => true,
+        Negation(ref inner) => inner.iter().all(check_simple),
+        _ => false,
+    }
+}
+
+// Can this selector be matched using only the open element stack?
+fn check_selector(sel: &Selector) -> bool {
+    sel.compounds.iter().all(|c| c.iter().all(check_simple))
+        && sel.combinators.iter().all(|c| *c == Descendant || *c == Child)
+}
+
+// Does a start tag for `new` imply an end tag for the open element
+// `open`?  A simplified version of the tree builder's rules.
+fn implies_end(new: &Atom, open: &Atom) -> bool {
+    match *open {
+        atom!(p) => match *new {
+            atom!(address) | atom!(article) | atom!(aside) | atom!(blockquote)
+            | atom!(div) | atom!(dl) | atom!(fieldset) | atom!(footer) | atom!(form)
+            | atom!(h1) | atom!(h2) | atom!(h3) | atom!(h4) | atom!(h5) | atom!(h6)
+            | atom!(header) | atom!(hr) | atom!(li) | atom!(main) | atom!(nav)
+            | atom!(ol) | atom!(p) | atom!(pre) | atom!(section) | atom!(table)
+            | atom!(ul) => true,
+            _ => false,
+        },
+        atom!(li) => *new == atom!(li),
+        atom!(dt) | atom!(dd) => *new == atom!(dt) || *new == atom!(dd),
+        atom!(option) => *new == atom!(option) || *new == atom!(optgroup),
+        atom!(td) | atom!(th) => *new == atom!(td) || *new == atom!(th) || *new == atom!(tr),
+        atom!(tr) => *new == atom!(tr),
+        _ => false,
+    }
+}
+
+// The tokenizer state for the content of an element.
+fn content_state(name: &Atom) -> Option<State> {
+    match *name {
+        atom!(title) | atom!(textarea) => Some(RawData(Rcdata)),
+        atom!(style) | atom!(xmp) | atom!(iframe) | atom!(noembed) | atom!(noframes)
+            => Some(RawData(Rawtext)),
+        atom!(script) => Some(RawData(ScriptData)),
+        atom!(plaintext) => Some(Plaintext),
+        _ => None,
+    }
+}
+
+fn escape(text: &str, attr_mode: bool) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '\xA0' => out.push_str("&nbsp;"),
+            '"' if attr_mode => out.push_str("&quot;"),
+            '<' if !attr_mode => out.push_str("&lt;"),
+            '>' if !attr_mode => out.push_str("&gt;"),
+            c => out.push_char(c),
+        }
+    }
+    out
+}
+
+fn start_tag(name: &Atom, attrs: &[Attribute], self_closing: bool) -> String {
+    let mut out = format!("<{}", name.as_slice());
+    for a in attrs.iter() {
+        out.push_str(format!(" {}=\"{}\"", a.name.as_slice(),
+            escape(a.value.as_slice(), true)).as_slice());
+    }
+    out.push_str(if self_closing { "/>" } else { ">" });
+    out
+}
+
+// The `TokenSink` behind a `Rewriter`.
+struct RewriteSink<Wr> {
+    out: Wr,
+    error: Option<IoError>,
+    handlers: Vec<(Vec<Selector>, Box<ElementHandler>)>,
+    stack: Vec<OpenElem>,
+
+    /// Number of open elements whose content is being dropped.
+    suppressing: uint,
+
+    next_state: Option<State>,
+}
+
+impl<Wr: Writer> RewriteSink<Wr> {
+    fn write(&mut self, s: &str) {
+        if self.suppressing > 0 || self.error.is_some() || s.is_empty() {
+            return;
+        }
+        match self.out.write_str(s) {
+            Err(e) => self.error = Some(e),
+            Ok(()) => (),
+        }
+    }
+
+    // Pop the current element, which was closed by an end tag if
+    // `explicit` is set.
+    fn pop(&mut self, explicit: bool) {
+        let elem = self.stack.pop().expect("no open element");
+        if elem.suppresses {
+            self.suppressing -= 1;
+        } else {
+            self.write(elem.append.as_slice());
+            if explicit && elem.end_tag {
+                self.write(format!("</{}>", elem.name.as_slice()).as_slice());
+            }
+        }
+        self.write(elem.after.as_slice());
+    }
+
+    // Is the text content of the current element written unescaped?
+    fn in_raw_text(&self) -> bool {
+        match self.stack.last() {
+            Some(e) => match content_state(&e.name) {
+                Some(RawData(Rawtext)) | Some(RawData(ScriptData)) | Some(Plaintext) => true,
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.in_raw_text() {
+            self.write(text);
+        } else {
+            let escaped = escape(text, false);
+            self.write(escaped.as_slice());
+        }
+    }
+
+    fn start_tag(&mut self, tag: Tag) {
+        while self.stack.last().map_or(false, |e| implies_end(&tag.name, &e.name)) {
+            self.pop(false);
+        }
+
+        let mut elem = RewriteElement {
+            name: tag.name.clone(),
+            attrs: tag.attrs.clone(),
+            before: String::new(),
+            prepend: String::new(),
+            append: String::new(),
+            after: String::new(),
+            replacement: None,
+            removed: false,
+            unwrapped: false,
+        };
+
+        self.stack.push(OpenElem {
+            name: tag.name.clone(),
+            attrs: tag.attrs,
+            append: String::new(),
+            after: String::new(),
+            end_tag: true,
+            suppresses: false,
+        });
+
+        if self.suppressing == 0 {
+            let node = StackNode {
+                stack: self.stack.as_slice(),
+                pos: self.stack.len() - 1,
+            };
+            for &(ref sels, ref mut handler) in self.handlers.mut_iter() {
+                if matches(sels.as_slice(), &node) {
+                    handler.element(&mut elem);
+                }
+                if elem.removed || elem.replacement.is_some() {
+                    break;
+                }
+            }
+        }
+
+        // Raw text elements switch the tokenizer state even when they're
+        // being removed, so their contents aren't read as markup.
+        let state = content_state(&tag.name);
+        // The self-closing flag means nothing on other HTML elements.
+        let void = is_void_element(&tag.name);
+        self.write(elem.before.as_slice());
+        let suppresses = match elem.replacement {
+            Some(ref html) => {
+                self.write(html.as_slice());
+                true
+            }
+            None => elem.removed,
+        };
+        if !suppresses {
+            if !elem.unwrapped {
+                let tag = start_tag(&elem.name, elem.attrs.as_slice(), tag.self_closing);
+                self.write(tag.as_slice());
+            }
+            if !void {
+                self.write(elem.prepend.as_slice());
+            }
+        }
+
+        if void {
+            self.stack.pop();
+            self.write(elem.after.as_slice());
+            return;
+        }
+
+        self.next_state = state;
+        let top = self.stack.mut_last().expect("no open element");
+        top.append = elem.append;
+        top.after = elem.after;
+        top.end_tag = !elem.unwrapped;
+        top.suppresses = suppresses;
+        if suppresses {
+            self.suppressing += 1;
+        }
+    }
+
+    fn end_tag(&mut self, name: Atom) {
+        match self.stack.iter().rposition(|e| e.name == name) {
+            None => {
+                // A stray end tag; pass it through.
+                self.write(format!("</{}>", name.as_slice()).as_slice());
+            }
+            Some(i) => {
+                while self.stack.len() > i + 1 {
+                    self.pop(false);
+                }
+                self.pop(true);
+            }
+        }
+    }
+}
+
+impl<Wr: Writer> TokenSink for RewriteSink<Wr> {
+    fn process_token(&mut self, token: Token) {
+        match token {
+            DoctypeToken(d) => {
+                let mut s = format!("<!DOCTYPE {}", d.name.unwrap_or(String::new()));
+                match (d.public_id, d.system_id) {
+                    (Some(p), Some(s2)) => s.push_str(format!(" PUBLIC \"{}\" \"{}\"", p, s2).as_slice()),
+                    (Some(p), None) => s.push_str(format!(" PUBLIC \"{}\"", p).as_slice()),
+                    (None, Some(s2)) => s.push_str(format!(" SYSTEM \"{}\"", s2).as_slice()),
+                    (None, None) => (),
+                }
+                s.push_char('>');
+                self.write(s.as_slice());
+            }
+
+            TagToken(tag) => match tag.kind {
+                StartTag => self.start_tag(tag),
+                EndTag => self.end_tag(tag.name),
+            },
+
+            CommentToken(text) => self.write(format!("<!--{}-->", text).as_slice()),
+            CharacterTokens(text) => self.text(text.as_slice()),
+            NullCharacterToken => self.write("\ufffd"),
+
+            EOFToken => {
+                while !self.stack.is_empty() {
+                    self.pop(false);
+                }
+            }
+
+            ParseError(_) => (),
+        }
+    }
+
+    fn query_state_change(&mut self) -> Option<State> {
+        self.next_state.take()
+    }
+}
+
+/// A streaming rewriter, which writes rewritten HTML to `Wr` as input
+/// is fed.
+pub struct Rewriter<Wr> {
+    tokenizer: Tokenizer<RewriteSink<Wr>>,
+}
+
+impl<Wr: Writer> Rewriter<Wr> {
+    /// Create a rewriter which writes to `out`.
+    pub fn new(out: Wr) -> Rewriter<Wr> {
+        let sink = RewriteSink {
+            out: out,
+            error: None,
+            handlers: vec!(),
+            stack: vec!(),
+            suppressing: 0,
+            next_state: None,
+        };
+        Rewriter {
+            tokenizer: Tokenizer::new(sink, Default::default()),
+        }
+    }
+
+    /// Register a handler for elements matching a selectors group.
+    /// Handlers run in the order they were registered.
+    pub fn on(&mut self, selectors: &str, handler: Box<ElementHandler>) -> Result<(), String> {
+        let sels = try!(parse_selectors(selectors));
+        if !sels.iter().all(check_selector) {
+            return Err(format!("selector can't be matched while streaming: {}", selectors));
+        }
+        self.tokenizer.sink().handlers.push((sels, handler));
+        Ok(())
+    }
+
+    /// Feed a chunk of input, writing whatever output is ready.
+    pub fn feed(&mut self, input: String) -> IoResult<()> {
+        self.tokenizer.feed(input);
+        self.check_error()
+    }
+
+    /// Signal the end of input, closing any open elements.
+    pub fn end(&mut self) -> IoResult<()> {
+        self.tokenizer.end();
+        self.check_error()
+    }
+
+    /// Consume the rewriter and return the output writer.
+    pub fn into_output(self) -> Wr {
+        self.tokenizer.into_sink().out
+    }
+
+    fn check_error(&mut self) -> IoResult<()> {
+        match self.tokenizer.sink().error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Rewriter, RewriteElement, ElementHandler};
+
+    use std::io::MemWriter;
+
+    struct SetAttr(&'static str, &'static str);
+
+    impl ElementHandler for SetAttr {
+        fn element(&mut self, elem: &mut RewriteElement) {
+            let SetAttr(name, value) = *self;
+            elem.set_attribute(name, value.to_string());
+        }
+    }
+
+    struct Remove;
+
+    impl ElementHandler for Remove {
+        fn element(&mut self, elem: &mut RewriteElement) {
+            elem.remove();
+        }
+    }
+
+    struct Unwrap;
+
+    impl ElementHandler for Unwrap {
+        fn element(&mut self, elem: &mut RewriteElement) {
+            elem.remove_and_keep_content();
+        }
+    }
+
+    struct Replace(&'static str);
+
+    impl ElementHandler for Replace {
+        fn element(&mut self, elem: &mut RewriteElement) {
+            let Replace(html) = *self;
+            elem.replace(html);
+        }
+    }
+
+    struct Inject;
+
+    impl ElementHandler for Inject {
+        fn element(&mut self, elem: &mut RewriteElement) {
+            elem.before("[");
+            elem.prepend("(");
+            elem.append(")");
+            elem.after("]");
+        }
+    }
+
+    // Feed one character at a time, to exercise chunk boundaries.
+    fn rewrite(input: &str, handlers: Vec<(&str, Box<ElementHandler>)>) -> String {
+        let mut rw = Rewriter::new(MemWriter::new());
+        for (sel, handler) in handlers.move_iter() {
+            rw.on(sel, handler).unwrap();
+        }
+        for c in input.chars() {
+            rw.feed(String::from_char(1, c)).unwrap();
+        }
+        rw.end().unwrap();
+        String::from_utf8(rw.into_output().unwrap()).unwrap()
+    }
+
+    test_eq!(passthrough, rewrite("<!DOCTYPE html><p class=a>x &amp; y</p><!--c-->", vec!()),
+        "<!DOCTYPE html><p class=\"a\">x &amp; y</p><!--c-->".to_string())
+
+    test_eq!(raw_text, rewrite("<script>if (a < b) x();</script>", vec!()),
+        "<script>if (a < b) x();</script>".to_string())
+
+    test_eq!(set_attribute,
+        rewrite("<a href=\"http://x\">1</a><a href=\"/y\">2</a>",
+            vec!(("a[href^=http]", box SetAttr("rel", "nofollow") as Box<ElementHandler>))),
+        "<a href=\"http://x\" rel=\"nofollow\">1</a><a href=\"/y\">2</a>".to_string())
+
+    test_eq!(remove,
+        rewrite("<div class=ad><p>x</p></div><p>y</p>",
+            vec!(("div.ad p", box Remove as Box<ElementHandler>))),
+        "<div class=\"ad\"></div><p>y</p>".to_string())
+
+    test_eq!(remove_raw_text,
+        rewrite("<div class=ad><script>x=\"</div>\"</script></div><p>y</p>",
+            vec!(("div.ad", box Remove as Box<ElementHandler>))),
+        "<p>y</p>".to_string())
+
+    test_eq!(self_closing_div,
+        rewrite("<div class=\"x\"/><p>y</p></div>",
+            vec!(("div.x p", box Remove as Box<ElementHandler>))),
+        "<div class=\"x\"/></div>".to_string())
+
+    test_eq!(replace,
+        rewrite("<b>a<i>b</i></b>c", vec!(("b", box Replace("<em>z</em>") as Box<ElementHandler>))),
+        "<em>z</em>c".to_string())
+
+    test_eq!(inject, rewrite("<p>x</p>", vec!(("p", box Inject as Box<ElementHandler>))),
+        "[<p>(x)</p>]".to_string())
+
+    test_eq!(unwrap, rewrite("<span><b>x</b></span>", vec!(("span", box Unwrap as Box<ElementHandler>))),
+        "<b>x</b>".to_string())
+
+    test_eq!(implied_end, rewrite("<ul><li>a<li>b</ul>", vec!(("ul > li", box Inject as Box<ElementHandler>))),
+        "<ul>[<li>(a)][<li>(b)]</ul>".to_string())
+
+    test_eq!(void, rewrite("<img src=a>", vec!(("img", box Inject as Box<ElementHandler>))),
+        "[<img src=\"a\">]".to_string())
+
+    #[test]
+    fn unsupported_selector() {
+        let mut rw = Rewriter::new(MemWriter::new());
+        assert!(rw.on("li:first-child", box Remove as Box<ElementHandler>).is_err());
+        assert!(rw.on("h1 + p", box Remove as Box<ElementHandler>).is_err());
+        assert!(rw.on("li:not(.x)", box Remove as Box<ElementHandler>).is_ok());
+    }
+}
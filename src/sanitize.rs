@@ -0,0 +1,364 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! An allowlist-based HTML sanitizer.
+//!
+//! Input is parsed with the real tree builder, so the sanitizer sees the
+//! same tree a browser would, and then serialized through a `Policy`
+//! which drops or escapes anything it doesn't explicitly allow.
+//!
+//! Only the contents of the document are kept: the `<html>`, `<head>`
+//! and `<body>` tags themselves are always removed.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let clean = sanitize(user_html.as_slice(), &Default::default());
+//! ```
+
+use atom::Atom;
+use atom::namespace::HTML;
+use tokenizer::{Attribute, AttrName, Tokenizer};
+use tree_builder::TreeBuilder;
+use sink::rcdom::{RcDom, Handle, Document, Doctype, Text, Comment, Element};
+use serialize::{Serializable, Serializer, serialize, is_void_element};
+use util::unsupported::DropUnsupported;
+
+use std::ascii::StrAsciiExt;
+use std::collections::hashmap::{HashMap, HashSet};
+use std::default::Default;
+use std::io::{Writer, IoResult, MemWriter};
+
+/// What to do with an element which isn't allowed.
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum Disallowed {
+    /// Remove the tags, but keep the contents (subject to the policy).
+    Strip,
+
+    /// Write the tags out as escaped text, so they're visible.
+    Escape,
+}
+
+/// Which elements, attributes and URLs to allow, with an impl for Default.
+///
+/// Allowing raw text elements such as `<script>` or `<style>` makes the
+/// output unsafe, because their contents aren't escaped.
+#[deriving(Clone)]
+pub struct Policy {
+    /// Elements to keep.
+    pub elements: HashSet<Atom>,
+
+    /// Attributes to keep on any allowed element.
+    pub global_attributes: HashSet<Atom>,
+
+    /// Attributes to keep on particular elements, as well as the
+    /// global ones.
+    pub element_attributes: HashMap<Atom, HashSet<Atom>>,
+
+    /// Attributes whose values are URLs.  These are dropped unless the
+    /// URL is relative or has one of `url_schemes`.
+    pub url_attributes: HashSet<Atom>,
+
+    /// Allowed URL schemes, in lowercase and without the colon.
+    pub url_schemes: HashSet<String>,
+
+    /// Allow URLs without a scheme?
+    pub allow_relative_urls: bool,
+
+    /// What to do with elements which aren't in `elements`.
+    pub disallowed: Disallowed,
+
+    /// Disallowed elements which are removed along with everything
+    /// inside them, whatever `disallowed` says.
+    pub drop_contents: HashSet<Atom>,
+
+    /// Keep comments?
+    pub allow_comments: bool,
+
+    /// Add `rel="noopener"` to `<a>` and `<area>` elements which have
+    /// an `href`.
+    pub add_rel_noopener: bool,
+}
+
+static default_element_attributes: &'static [(&'static str, &'static [&'static str])] = &[
+    ("a", &["href", "rel"]),
+    ("img", &["src", "alt", "width", "height"]),
+    ("td", &["colspan", "rowspan"]),
+    ("th", &["colspan", "rowspan", "scope"]),
+    ("col", &["span"]),
+    ("colgroup", &["span"]),
+    ("ol", &["start", "reversed"]),
+    ("blockquote", &["cite"]),
+    ("q", &["cite"]),
+    ("del", &["cite", "datetime"]),
+    ("ins", &["cite", "datetime"]),
+];
+
+fn atoms(names: &[&str]) -> HashSet<Atom> {
+    names.iter().map(|&n| Atom::from_str(n)).collect()
+}
+
+impl Default for Policy {
+    fn default() -> Policy {
+        let mut element_attributes = HashMap::new();
+        for &(elem, attrs) in default_element_attributes.iter() {
+            element_attributes.insert(Atom::from_str(elem), atoms(attrs));
+        }
+
+        Policy {
+            elements: atoms(&[
+                "a", "abbr", "b", "blockquote", "br", "caption", "cite", "code",
+                "col", "colgroup", "dd", "del", "div", "dl", "dt", "em",
+                "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6",
+                "hr", "i", "img", "ins", "kbd", "li", "mark", "ol", "p", "pre",
+                "q", "s", "samp", "small", "span", "strong", "sub", "sup",
+                "table", "tbody", "td", "tfoot", "th", "thead", "tr", "u", "ul",
+            ]),
+            global_attributes: atoms(&["title", "lang", "dir"]),
+            element_attributes: element_attributes,
+            url_attributes: atoms(&[
+                "action", "background", "cite", "formaction", "href",
+                "longdesc", "poster", "src",
+            ]),
+            url_schemes: ["http", "https", "mailto"].iter().map(|s| s.to_string()).collect(),
+            allow_relative_urls: true,
+            disallowed: Strip,
+            drop_contents: atoms(&[
+                "applet", "embed", "frameset", "iframe", "math", "noembed",
+                "noframes", "noscript", "object", "script", "select", "style",
+                "svg", "template", "textarea", "title",
+            ]),
+            allow_comments: false,
+            add_rel_noopener: true,
+        }
+    }
+}
+
+// Browsers strip these from both ends of a URL.
+fn is_c0_or_space(c: char) -> bool {
+    c <= ' '
+}
+
+// The scheme of a URL, in lowercase.  `Some("")` means the URL looks
+// like it has a scheme, but not a valid one.
+fn url_scheme(url: &str) -> Option<String> {
+    let end = unwrap_or_return!(url.find(|c: char| c == ':' || c == '/' || c == '?' || c == '#'), None);
+    if url.char_at(end) != ':' {
+        return None;
+    }
+
+    let scheme = url.slice_to(end);
+    let valid = scheme.chars().enumerate().all(|(i, c)| match c {
+        'a'..'z' | 'A'..'Z' => true,
+        '0'..'9' | '+' | '-' | '.' => i > 0,
+        _ => false,
+    });
+    if valid {
+        Some(scheme.to_ascii_lower())
+    } else {
+        Some(String::new())
+    }
+}
+
+impl Policy {
+    /// Is this URL allowed by `url_schemes` and `allow_relative_urls`?
+    pub fn url_allowed(&self, url: &str) -> bool {
+        // Browsers ignore tabs and newlines anywhere in a URL.
+        let url: String = url.chars().filter(|&c| c != '\t' && c != '\n' && c != '\r').collect();
+        match url_scheme(url.as_slice().trim_chars(is_c0_or_space)) {
+            None => self.allow_relative_urls,
+            Some(scheme) => self.url_schemes.contains(&scheme),
+        }
+    }
+
+    fn attr_allowed(&self, elem: &Atom, attr: &Attribute) -> bool {
+        let name = &attr.name.name;
+        let listed = self.global_attributes.contains(name)
+            || self.element_attributes.find(elem).map_or(false, |a| a.contains(name));
+        listed && (!self.url_attributes.contains(name) || self.url_allowed(attr.value.as_slice()))
+    }
+
+    // The attributes to write for an allowed element.
+    fn filter_attrs(&self, elem: &Atom, attrs: &[Attribute]) -> Vec<(AttrName, String)> {
+        let mut out: Vec<(AttrName, String)> = attrs.iter()
+            .filter(|a| self.attr_allowed(elem, *a))
+            .map(|a| (a.name.clone(), a.value.clone()))
+            .collect();
+
+        let is_link = match *elem {
+            atom!(a) | atom!(area) => out.iter().any(|&(ref n, _)| n.name == atom!(href)),
+            _ => false,
+        };
+        if self.add_rel_noopener && is_link {
+            match out.iter().position(|&(ref n, _)| n.name == atom!(rel)) {
+                Some(i) => {
+                    let (_, ref mut rel) = *out.get_mut(i);
+                    if !rel.as_slice().to_ascii_lower().as_slice().words().any(|w| w == "noopener") {
+                        rel.push_str(" noopener");
+                    }
+                }
+                None => out.push((AttrName::new(atom!(rel)), "noopener".to_string())),
+            }
+        }
+        out
+    }
+}
+
+/// A node which serializes as its sanitized contents.
+pub struct Sanitized<'a> {
+    pub node: Handle,
+    pub policy: &'a Policy,
+}
+
+impl<'a> Sanitized<'a> {
+    fn serialize_children<'wr, Wr: Writer>(&self, serializer: &mut Serializer<'wr, Wr>,
+            node: &Handle) -> IoResult<()> {
+        for child in node.borrow().children.iter() {
+            try!(self.serialize_node(serializer, child));
+        }
+        Ok(())
+    }
+
+    fn serialize_node<'wr, Wr: Writer>(&self, serializer: &mut Serializer<'wr, Wr>,
+            node: &Handle) -> IoResult<()> {
+        let policy = self.policy;
+        match node.borrow().node {
+            Document => self.serialize_children(serializer, node),
+            Doctype(..) => Ok(()),
+            Text(ref text) => serializer.write_text(text.as_slice()),
+            Comment(ref text) if policy.allow_comments => serializer.write_comment(text.as_slice()),
+            Comment(_) => Ok(()),
+
+            Element(atom!(html), _) | Element(atom!(head), _) | Element(atom!(body), _)
+                => self.serialize_children(serializer, node),
+
+            Element(ref name, ref attrs) if policy.elements.contains(name) => {
+                let attrs = policy.filter_attrs(name, attrs.as_slice());
+                try!(serializer.start_elem(HTML, name.clone(),
+                    attrs.iter().map(|&(ref n, ref v)| (n, v.as_slice()))));
+                try!(self.serialize_children(serializer, node));
+                serializer.end_elem(HTML, name.clone())
+            }
+
+            Element(ref name, _) if policy.drop_contents.contains(name) => Ok(()),
+
+            Element(ref name, ref attrs) => match policy.disallowed {
+                Strip => self.serialize_children(serializer, node),
+                Escape => {
+                    let mut tag = format!("<{}", name.as_slice());
+                    for attr in attrs.iter() {
+                        tag.push_str(format!(" {}=\"{}\"", attr.name.as_slice(), attr.value).as_slice());
+                    }
+                    tag.push_char('>');
+                    try!(serializer.write_text(tag.as_slice()));
+                    try!(self.serialize_children(serializer, node));
+                    if is_void_element(name) {
+                        Ok(())
+                    } else {
+                        serializer.write_text(format!("</{}>", name.as_slice()).as_slice())
+                    }
+                }
+            },
+        }
+    }
+}
+
+impl<'a> Serializable for Sanitized<'a> {
+    fn serialize<'wr, Wr: Writer>(&self, serializer: &mut Serializer<'wr, Wr>, incl_self: bool) -> IoResult<()> {
+        if incl_self {
+            self.serialize_node(serializer, &self.node)
+        } else {
+            self.serialize_children(serializer, &self.node)
+        }
+    }
+}
+
+/// Parse `input` as a document and return its contents, sanitized
+/// according to `policy`.
+pub fn sanitize(input: &str, policy: &Policy) -> String {
+    let mut dom: RcDom = Default::default();
+    {
+        let tb: TreeBuilder<Handle, &mut RcDom> = TreeBuilder::new(&mut dom, Default::default());
+        let mut tok = Tokenizer::new(DropUnsupported { inner: tb }, Default::default());
+        tok.feed(input.to_string());
+        tok.end();
+    }
+
+    let node = Sanitized {
+        node: dom.document.clone(),
+        policy: policy,
+    };
+
+    let mut w = MemWriter::new();
+    serialize(&mut w, &node, Default::default()).ok().expect("couldn't write to memory");
+    String::from_utf8(w.unwrap()).ok().expect("serializer wrote invalid UTF-8")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Policy, Escape, sanitize, url_scheme};
+
+    use std::default::Default;
+
+    fn clean(input: &str) -> String {
+        sanitize(input, &Default::default())
+    }
+
+    test_eq!(scheme_none, url_scheme("foo/bar:baz"), None)
+    test_eq!(scheme_fragment, url_scheme("#a:b"), None)
+    test_eq!(scheme_http, url_scheme("HTTP://example.com/"), Some("http".to_string()))
+    test_eq!(scheme_invalid, url_scheme("java script:x"), Some(String::new()))
+    test_eq!(scheme_empty, url_scheme(":x"), Some(String::new()))
+
+    #[test]
+    fn url_allowed() {
+        let policy: Policy = Default::default();
+        assert!(policy.url_allowed("https://example.com/"));
+        assert!(policy.url_allowed("/relative?a=b:c"));
+        assert!(!policy.url_allowed("javascript:alert(1)"));
+        assert!(!policy.url_allowed("  JaVaScRiPt:alert(1)"));
+        assert!(!policy.url_allowed("java\tscript:alert(1)"));
+        assert!(!policy.url_allowed("\x01javascript:alert(1)"));
+    }
+
+    test_eq!(keeps_allowed, clean("<p>a <b title=t>b</b></p>"),
+        "<p>a <b title=\"t\">b</b></p>".to_string())
+
+    test_eq!(strips_disallowed, clean("<p><blink>a</blink><script>b</script></p>"),
+        "<p>a</p>".to_string())
+
+    test_eq!(drops_attrs, clean("<img src=x.png onerror=alert(1)>"),
+        "<img src=\"x.png\">".to_string())
+
+    test_eq!(unsupported_tags, clean("<svg><p>a</p></svg><math>b</math><template>c</template><isindex>"),
+        "<p>a</p>bc".to_string())
+
+    test_eq!(noopener, clean("<a href=\"/x\" rel=nofollow>x</a><a href=/y>y</a><a>z</a>"),
+        "<a href=\"/x\" rel=\"nofollow noopener\">x</a><a href=\"/y\" rel=\"noopener\">y</a><a>z</a>".to_string())
+
+    #[test]
+    fn escape() {
+        let policy = Policy {
+            disallowed: Escape,
+            .. Default::default()
+        };
+        assert_eq!(sanitize("<p><blink a=\"<\">x</blink><br><wbr><script>y</script>", &policy),
+            "<p>&lt;blink a=\"&lt;\"&gt;x&lt;/blink&gt;<br>&lt;wbr&gt;</p>".to_string());
+    }
+
+    #[test]
+    fn comments() {
+        let policy = Policy {
+            allow_comments: true,
+            .. Default::default()
+        };
+        assert_eq!(clean("<p><!--x-->y"), "<p>y</p>".to_string());
+        assert_eq!(sanitize("<p><!--x-->y", &policy), "<p><!--x-->y</p>".to_string());
+    }
+}
@@ -0,0 +1,286 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use selector::{SelectorNode, Selector, Descendant, Child, NextSibling, SubsequentSibling};
+use selector::{SimpleSelector, LocalName, Id, Class, Attr, Root, Empty};
+use selector::{NthChild, NthLastChild, NthOfType, NthLastOfType, OnlyChild, OnlyOfType, Negation};
+use selector::{AttrOp, Exists, Equals, Includes, DashMatch, Prefix, Suffix, Substring};
+
+use util::str::is_ascii_whitespace;
+
+/// Does the node match any selector in the group?
+pub fn matches<N: SelectorNode>(selectors: &[Selector], node: &N) -> bool {
+    node.local_name().is_some()
+        && selectors.iter().any(|s| matches_from(s, 0, node))
+}
+
+/// Find all descendants of `root` which match any selector in the group,
+/// in document order.  `root` itself is not included.
+pub fn select<N: SelectorNode>(selectors: &[Selector], root: &N) -> Vec<N> {
+    let mut found = vec!();
+    let mut next = root.first_child_node();
+    loop {
+        let node = unwrap_or_return!(next, found);
+        if matches(selectors, &node) {
+            found.push(node.clone());
+        }
+
+        // Pre-order traversal, without leaving the subtree.
+        next = node.first_child_node();
+        let mut up = node;
+        while next.is_none() {
+            if up.same_node(root) {
+                return found;
+            }
+            next = up.next_sibling_node();
+            up = unwrap_or_return!(up.parent_node(), found);
+        }
+    }
+}
+
+fn parent_element<N: SelectorNode>(node: &N) -> Option<N> {
+    node.parent_node().and_then(|p| match p.local_name() {
+        Some(_) => Some(p),
+        None => None,
+    })
+}
+
+fn prev_sibling_element<N: SelectorNode>(node: &N) -> Option<N> {
+    let mut sib = node.prev_sibling_node();
+    loop {
+        let s = unwrap_or_return!(sib, None);
+        if s.local_name().is_some() {
+            return Some(s);
+        }
+        sib = s.prev_sibling_node();
+    }
+}
+
+fn next_sibling_element<N: SelectorNode>(node: &N) -> Option<N> {
+    let mut sib = node.next_sibling_node();
+    loop {
+        let s = unwrap_or_return!(sib, None);
+        if s.local_name().is_some() {
+            return Some(s);
+        }
+        sib = s.next_sibling_node();
+    }
+}
+
+// Match compound selector `i` against `node`, then the rest of the
+// selector against the appropriate relatives.
+fn matches_from<N: SelectorNode>(sel: &Selector, i: uint, node: &N) -> bool {
+    if !sel.compounds.get(i).iter().all(|s| matches_simple(s, node)) {
+        return false;
+    }
+
+    if i == sel.combinators.len() {
+        return true;
+    }
+
+    match *sel.combinators.get(i) {
+        Child => match parent_element(node) {
+            Some(p) => matches_from(sel, i+1, &p),
+            None => false,
+        },
+
+        Descendant => {
+            let mut anc = parent_element(node);
+            loop {
+                let a = unwrap_or_return!(anc, false);
+                if matches_from(sel, i+1, &a) {
+                    return true;
+                }
+                anc = parent_element(&a);
+            }
+        }
+
+        NextSibling => match prev_sibling_element(node) {
+            Some(s) => matches_from(sel, i+1, &s),
+            None => false,
+        },
+
+        SubsequentSibling => {
+            let mut sib = prev_sibling_element(node);
+            loop {
+                let s = unwrap_or_return!(sib, false);
+                if matches_from(sel, i+1, &s) {
+                    return true;
+                }
+                sib = prev_sibling_element(&s);
+            }
+        }
+    }
+}
+
+fn matches_attr(op: AttrOp, actual: &str, wanted: &str) -> bool {
+    match op {
+        Exists => true,
+        Equals => actual == wanted,
+        Includes => !wanted.is_empty()
+            && actual.split(is_ascii_whitespace).any(|w| w == wanted),
+        DashMatch => actual == wanted
+            || (actual.starts_with(wanted)
+                && actual.slice_from(wanted.len()).starts_with("-")),
+        Prefix => !wanted.is_empty() && actual.starts_with(wanted),
+        Suffix => !wanted.is_empty() && actual.ends_with(wanted),
+        Substring => !wanted.is_empty() && actual.contains(wanted),
+    }
+}
+
+// Is there some k >= 0 with a*k + b == n?
+fn matches_nth(a: int, b: int, n: int) -> bool {
+    match a {
+        0 => n == b,
+        a => {
+            let d = n - b;
+            (d % a == 0) && (d / a >= 0)
+        }
+    }
+}
+
+// Count the preceding (or following) element siblings of `node`,
+// optionally only those with the same local name.
+fn sibling_index<N: SelectorNode>(node: &N, from_end: bool, of_type: bool) -> int {
+    let name = node.local_name();
+    let step = |n: &N| if from_end { next_sibling_element(n) } else { prev_sibling_element(n) };
+
+    let mut i = 1;
+    let mut sib = step(node);
+    loop {
+        let s = unwrap_or_return!(sib, i);
+        if !of_type || s.local_name() == name {
+            i += 1;
+        }
+        sib = step(&s);
+    }
+}
+
+fn matches_simple<N: SelectorNode>(sel: &SimpleSelector, node: &N) -> bool {
+    match *sel {
+        LocalName(ref name) => node.local_name().as_ref() == Some(name),
+
+        Id(ref id) => node.attr_value("id").map_or(false, |v| v.as_slice() == id.as_slice()),
+
+        Class(ref class) => node.attr_value("class").map_or(false, |v|
+            v.as_slice().split(is_ascii_whitespace).any(|c| c == class.as_slice())),
+
+        Attr(ref name, op, ref value) => node.attr_value(name.as_slice()).map_or(false, |v|
+            matches_attr(op, v.as_slice(), value.as_slice())),
+
+        Root => node.parent_node().map_or(false, |p| p.local_name().is_none()),
+
+        Empty => {
+            let mut child = node.first_child_node();
+            loop {
+                let c = unwrap_or_return!(child, true);
+                if c.local_name().is_some() || c.is_nonempty_text() {
+                    return false;
+                }
+                child = c.next_sibling_node();
+            }
+        }
+
+        NthChild(a, b) => matches_nth(a, b, sibling_index(node, false, false)),
+        NthLastChild(a, b) => matches_nth(a, b, sibling_index(node, true, false)),
+        NthOfType(a, b) => matches_nth(a, b, sibling_index(node, false, true)),
+        NthLastOfType(a, b) => matches_nth(a, b, sibling_index(node, true, true)),
+
+        OnlyChild => prev_sibling_element(node).is_none()
+            && next_sibling_element(node).is_none(),
+
+        OnlyOfType => sibling_index(node, false, true) == 1
+            && sibling_index(node, true, true) == 1,
+
+        Negation(ref sels) => !sels.iter().all(|s| matches_simple(s, node)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::matches_nth;
+    use selector::{parse_selectors, select};
+
+    use sink::rcdom::{RcDom, Handle, NodeHandle};
+    use driver::{parse, one_input};
+
+    use std::default::Default;
+
+    test_eq!(nth_b, matches_nth(0, 3, 3), true)
+    test_eq!(nth_not_b, matches_nth(0, 3, 4), false)
+    test_eq!(nth_odd, matches_nth(2, 1, 5), true)
+    test_eq!(nth_even, matches_nth(2, 0, 5), false)
+    test_eq!(nth_first_three, matches_nth(-1, 3, 3), true)
+    test_eq!(nth_not_first_three, matches_nth(-1, 3, 4), false)
+
+    fn ids(html: &str, sel: &str) -> Vec<String> {
+        let dom: RcDom = parse(one_input(html.to_string()), Default::default());
+        let sels = parse_selectors(sel).unwrap();
+        select(sels.as_slice(), &dom.document).move_iter()
+            .map(|h: Handle| h.get_attribute("id").unwrap_or(String::new()))
+            .collect()
+    }
+
+    fn check(html: &str, sel: &str, expected: &[&str]) {
+        let got = ids(html, sel);
+        let got: Vec<&str> = got.iter().map(|s| s.as_slice()).collect();
+        assert_eq!(got.as_slice(), expected);
+    }
+
+    static list: &'static str =
+        "<ul id=u><li id=a class='x y'>1<li id=b>2<li id=c class=y><p id=d></ul>";
+
+    #[test]
+    fn type_and_class() {
+        check(list, "li", ["a", "b", "c"]);
+        check(list, ".y", ["a", "c"]);
+        check(list, "li.x.y", ["a"]);
+        check(list, "LI#b", ["b"]);
+    }
+
+    #[test]
+    fn combinators() {
+        check(list, "ul > li", ["a", "b", "c"]);
+        check(list, "ul p", ["d"]);
+        check(list, "ul > p", []);
+        check(list, "#a + li", ["b"]);
+        check(list, "#a ~ li", ["b", "c"]);
+        check(list, "body li + li p", ["d"]);
+    }
+
+    #[test]
+    fn attributes() {
+        let html = "<a id=a href='http://x/y.png' lang=en-US></a><a id=b href=/z></a>";
+        check(html, "[href^=http]", ["a"]);
+        check(html, "[href$='.png']", ["a"]);
+        check(html, "[href*=z]", ["b"]);
+        check(html, "[lang|=en]", ["a"]);
+        check(html, "[lang]", ["a"]);
+        check(html, "[class~='']", []);
+    }
+
+    #[test]
+    fn structural() {
+        check(list, "li:first-child", ["a"]);
+        check(list, "li:last-child", ["c"]);
+        check(list, "li:nth-child(odd)", ["a", "c"]);
+        check(list, "li:nth-last-child(1)", ["c"]);
+        check(list, "p:only-child", ["d"]);
+        check(list, "p:only-of-type", ["d"]);
+        check(list, "li:not(.y)", ["b"]);
+        check(list, "p:empty", ["d"]);
+        check(list, "li:empty", []);
+        check(list, ":root", [""]);
+    }
+
+    #[test]
+    fn document_order() {
+        check(list, "p, li, ul", ["u", "a", "b", "c", "d"]);
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! CSS selector matching over parsed trees.
+//!
+//! Supports the selectors from CSS Selectors Level 3, except for
+//! pseudo-elements and the pseudo-classes which depend on user
+//! interaction or document state (`:hover`, `:checked`, `:lang()`, etc).
+//!
+//! ## Example
+//!
+//! ```rust
+//! let dom: RcDom = parse(one_input(my_str), Default::default());
+//! let sels = parse_selectors("ul > li:nth-child(2n+1) a[href^=http]").unwrap();
+//! for link in select(sels.as_slice(), &dom.document).move_iter() {
+//!     ...
+//! }
+//! ```
+
+pub use self::parser::parse_selectors;
+pub use self::matching::{matches, select};
+
+use atom::Atom;
+
+mod parser;
+mod matching;
+
+/// The operations on a tree which are needed to match selectors.
+///
+/// `Self` is a reference to a node of any kind: element, text, etc.
+pub trait SelectorNode: Clone {
+    /// Do two references point at the same node?
+    fn same_node(&self, other: &Self) -> bool;
+
+    /// The parent of this node, if any.
+    fn parent_node(&self) -> Option<Self>;
+
+    /// The first child of this node, if any.
+    fn first_child_node(&self) -> Option<Self>;
+
+    /// The sibling immediately before this node, if any.
+    fn prev_sibling_node(&self) -> Option<Self>;
+
+    /// The sibling immediately after this node, if any.
+    fn next_sibling_node(&self) -> Option<Self>;
+
+    /// The local name of an element, or `None` for other kinds of node.
+    fn local_name(&self) -> Option<Atom>;
+
+    /// The value of an attribute on an element.
+    fn attr_value(&self, name: &str) -> Option<String>;
+
+    /// Is this a text node with non-empty contents?
+    /// Such nodes prevent their parent from matching `:empty`.
+    fn is_nonempty_text(&self) -> bool;
+}
+
+/// A comma-separated list of these makes up a selectors group.
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub struct Selector {
+    /// The compound selectors, rightmost (the subject) first.
+    pub compounds: Vec<Vec<SimpleSelector>>,
+
+    /// `combinators[i]` sits between `compounds[i]` and `compounds[i+1]`.
+    pub combinators: Vec<Combinator>,
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum Combinator {
+    /// `a b`
+    Descendant,
+    /// `a > b`
+    Child,
+    /// `a + b`
+    NextSibling,
+    /// `a ~ b`
+    SubsequentSibling,
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum SimpleSelector {
+    /// A type selector, lowercased.  The universal selector `*` matches
+    /// every element and so has no representation here.
+    LocalName(Atom),
+    Id(String),
+    Class(String),
+    /// Attribute name (lowercased), operator, and value.
+    Attr(String, AttrOp, String),
+    Root,
+    Empty,
+    /// `:nth-child(an+b)` and friends, holding `a` and `b`.
+    /// `:first-child` is `NthChild(0, 1)` and so on.
+    NthChild(int, int),
+    NthLastChild(int, int),
+    NthOfType(int, int),
+    NthLastOfType(int, int),
+    OnlyChild,
+    OnlyOfType,
+    Negation(Vec<SimpleSelector>),
+}
+
+#[deriving(PartialEq, Eq, Clone, Show)]
+pub enum AttrOp {
+    /// `[foo]`
+    Exists,
+    /// `[foo=bar]`
+    Equals,
+    /// `[foo~=bar]`
+    Includes,
+    /// `[foo|=bar]`
+    DashMatch,
+    /// `[foo^=bar]`
+    Prefix,
+    /// `[foo$=bar]`
+    Suffix,
+    /// `[foo*=bar]`
+    Substring,
+}
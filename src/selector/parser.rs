@@ -0,0 +1,462 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use selector::{Selector, Combinator, Descendant, Child, NextSibling, SubsequentSibling};
+use selector::{SimpleSelector, LocalName, Id, Class, Attr, Root, Empty};
+use selector::{NthChild, NthLastChild, NthOfType, NthLastOfType, OnlyChild, OnlyOfType, Negation};
+use selector::{AttrOp, Exists, Equals, Includes, DashMatch, Prefix, Suffix, Substring};
+
+use util::str::is_ascii_whitespace;
+
+use atom::Atom;
+
+use std::char;
+use std::num;
+use std::ascii::StrAsciiExt;
+
+/// Parse a selectors group, e.g. `"div > p.intro, a[href]"`.
+pub fn parse_selectors(input: &str) -> Result<Vec<Selector>, String> {
+    let mut parser = Parser {
+        input: input,
+        pos: 0,
+    };
+
+    let mut selectors = vec!();
+    loop {
+        parser.skip_whitespace();
+        selectors.push(try!(parser.parse_selector()));
+        match parser.next() {
+            None => return Ok(selectors),
+            Some(',') => (),
+            Some(c) => return Err(format!("Unexpected {} in selector", c)),
+        }
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    match c {
+        'a'..'z' | 'A'..'Z' | '_' | '\\' => true,
+        c => c as u32 >= 0x80,
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    match c {
+        '0'..'9' | '-' => true,
+        c => is_name_start(c),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: uint,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.peek_at(0)
+    }
+
+    // Look `n` characters ahead, without consuming anything.
+    fn peek_at(&self, n: uint) -> Option<char> {
+        self.input.slice_from(self.pos).chars().nth(n)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = unwrap_or_return!(self.peek(), None);
+        self.pos += c.len_utf8_bytes();
+        Some(c)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.next() {
+            Some(d) if d == c => Ok(()),
+            Some(d) => Err(format!("Expected {} but found {} in selector", c, d)),
+            None => Err(format!("Expected {} but found end of selector", c)),
+        }
+    }
+
+    // Returns true if any whitespace was skipped.
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(c) if is_ascii_whitespace(c) => { self.next(); }
+                _ => return self.pos != start,
+            }
+        }
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, String> {
+        let mut compounds = vec!(try!(self.parse_compound()));
+        let mut combinators = vec!();
+
+        loop {
+            let space = self.skip_whitespace();
+            let comb = match self.peek() {
+                None | Some(',') => break,
+                Some('>') => Child,
+                Some('+') => NextSibling,
+                Some('~') => SubsequentSibling,
+                _ if space => Descendant,
+                Some(c) => return Err(format!("Unexpected {} in selector", c)),
+            };
+            if comb != Descendant {
+                self.next();
+                self.skip_whitespace();
+            }
+            combinators.push(comb);
+            compounds.push(try!(self.parse_compound()));
+        }
+
+        // Matching works from right to left.
+        compounds.reverse();
+        combinators.reverse();
+        Ok(Selector {
+            compounds: compounds,
+            combinators: combinators,
+        })
+    }
+
+    fn parse_compound(&mut self) -> Result<Vec<SimpleSelector>, String> {
+        let mut simple = vec!();
+
+        let universal = match self.peek() {
+            Some('*') => {
+                self.next();
+                true
+            }
+            Some(c) if is_name_start(c) || c == '-' => {
+                let name = try!(self.parse_ident());
+                simple.push(LocalName(Atom::from_buf(name.as_slice().to_ascii_lower())));
+                false
+            }
+            _ => false,
+        };
+
+        loop {
+            let s = match self.peek() {
+                Some('#') => {
+                    self.next();
+                    Id(try!(self.parse_ident()))
+                }
+                Some('.') => {
+                    self.next();
+                    Class(try!(self.parse_ident()))
+                }
+                Some('[') => {
+                    self.next();
+                    try!(self.parse_attr())
+                }
+                Some(':') => {
+                    self.next();
+                    try!(self.parse_pseudo())
+                }
+                _ => break,
+            };
+            simple.push(s);
+        }
+
+        if simple.is_empty() && !universal {
+            return Err(match self.peek() {
+                Some(c) => format!("Expected a selector but found {}", c),
+                None => "Expected a selector but found end of input".to_string(),
+            });
+        }
+        Ok(simple)
+    }
+
+    fn parse_ident(&mut self) -> Result<String, String> {
+        let mut ident = String::new();
+        match (self.peek(), self.peek_at(1)) {
+            (Some('-'), Some(c)) if is_name_start(c) => {
+                self.next();
+                ident.push_char('-');
+            }
+            (Some(c), _) if is_name_start(c) => (),
+            (Some(c), _) => return Err(format!("Expected an identifier but found {}", c)),
+            (None, _) => return Err("Expected an identifier but found end of input".to_string()),
+        }
+
+        loop {
+            match self.peek() {
+                Some('\\') => {
+                    self.next();
+                    ident.push_char(try!(self.parse_escape()));
+                }
+                Some(c) if is_name_char(c) => {
+                    self.next();
+                    ident.push_char(c);
+                }
+                _ => return Ok(ident),
+            }
+        }
+    }
+
+    // After a backslash.
+    fn parse_escape(&mut self) -> Result<char, String> {
+        let mut hex = String::new();
+        loop {
+            match self.peek() {
+                Some(c) if hex.len() < 6 && c.is_digit_radix(16) => {
+                    self.next();
+                    hex.push_char(c);
+                }
+                _ => break,
+            }
+        }
+
+        if hex.is_empty() {
+            return match self.next() {
+                Some(c) => Ok(c),
+                None => Err("Escape at end of selector".to_string()),
+            };
+        }
+
+        // A single whitespace character terminates a hex escape.
+        match self.peek() {
+            Some(c) if is_ascii_whitespace(c) => { self.next(); }
+            _ => (),
+        }
+
+        let n: u32 = num::from_str_radix(hex.as_slice(), 16).expect("bad hex digits");
+        Ok(match n {
+            0 => '\ufffd',
+            n => char::from_u32(n).unwrap_or('\ufffd'),
+        })
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        let quote = self.next().expect("no quote");
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                None => return Err("Unterminated string in selector".to_string()),
+                Some('\\') => match self.peek() {
+                    Some('\n') => { self.next(); }
+                    _ => s.push_char(try!(self.parse_escape())),
+                },
+                Some(c) if c == quote => return Ok(s),
+                Some(c) => s.push_char(c),
+            }
+        }
+    }
+
+    // After the opening bracket.
+    fn parse_attr(&mut self) -> Result<SimpleSelector, String> {
+        self.skip_whitespace();
+        let name = try!(self.parse_ident()).as_slice().to_ascii_lower();
+        self.skip_whitespace();
+
+        let op = match self.next() {
+            Some(']') => return Ok(Attr(name, Exists, String::new())),
+            Some('=') => Equals,
+            Some(c) => {
+                try!(self.expect('='));
+                match c {
+                    '~' => Includes,
+                    '|' => DashMatch,
+                    '^' => Prefix,
+                    '$' => Suffix,
+                    '*' => Substring,
+                    c => return Err(format!("Unknown attribute operator {}=", c)),
+                }
+            }
+            None => return Err("Unterminated attribute selector".to_string()),
+        };
+
+        self.skip_whitespace();
+        let value = match self.peek() {
+            Some('"') | Some('\'') => try!(self.parse_string()),
+            _ => try!(self.parse_ident()),
+        };
+        self.skip_whitespace();
+        try!(self.expect(']'));
+        Ok(Attr(name, op, value))
+    }
+
+    // After the colon.
+    fn parse_pseudo(&mut self) -> Result<SimpleSelector, String> {
+        if self.peek() == Some(':') {
+            return Err("Pseudo-elements are not supported".to_string());
+        }
+
+        let name = try!(self.parse_ident()).as_slice().to_ascii_lower();
+        if self.peek() != Some('(') {
+            return Ok(match name.as_slice() {
+                "root" => Root,
+                "empty" => Empty,
+                "first-child" => NthChild(0, 1),
+                "last-child" => NthLastChild(0, 1),
+                "only-child" => OnlyChild,
+                "first-of-type" => NthOfType(0, 1),
+                "last-of-type" => NthLastOfType(0, 1),
+                "only-of-type" => OnlyOfType,
+                _ => return Err(format!("Unsupported pseudo-class :{}", name)),
+            });
+        }
+
+        self.next();
+        self.skip_whitespace();
+        let sel = match name.as_slice() {
+            "not" => Negation(try!(self.parse_compound())),
+            "nth-child" | "nth-last-child" | "nth-of-type" | "nth-last-of-type" => {
+                let (a, b) = try!(self.parse_nth());
+                match name.as_slice() {
+                    "nth-child" => NthChild(a, b),
+                    "nth-last-child" => NthLastChild(a, b),
+                    "nth-of-type" => NthOfType(a, b),
+                    _ => NthLastOfType(a, b),
+                }
+            }
+            _ => return Err(format!("Unsupported pseudo-class :{}()", name)),
+        };
+        self.skip_whitespace();
+        try!(self.expect(')'));
+        Ok(sel)
+    }
+
+    // Parse the `an+b` argument, up to but not including the closing paren.
+    fn parse_nth(&mut self) -> Result<(int, int), String> {
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                None | Some(')') => break,
+                _ => { self.next(); }
+            }
+        }
+
+        let arg: String = self.input.slice(start, self.pos).chars()
+            .filter(|&c| !is_ascii_whitespace(c))
+            .map(|c| c.to_lowercase())
+            .collect();
+
+        match parse_nth_arg(arg.as_slice()) {
+            Some(ab) => Ok(ab),
+            None => Err(format!("Bad argument ({}) for :nth-*()", arg)),
+        }
+    }
+}
+
+fn parse_int(s: &str) -> Option<int> {
+    match s {
+        "" | "+" | "-" => None,
+        _ if s.starts_with("+") => from_str(s.slice_from(1)),
+        _ => from_str(s),
+    }
+}
+
+// Parse a whitespace-free, lowercase `an+b` expression.
+fn parse_nth_arg(arg: &str) -> Option<(int, int)> {
+    match arg {
+        "odd" => return Some((2, 1)),
+        "even" => return Some((2, 0)),
+        _ => (),
+    }
+
+    match arg.find('n') {
+        None => parse_int(arg).map(|b| (0, b)),
+        Some(i) => {
+            let a = match arg.slice_to(i) {
+                "" | "+" => 1,
+                "-" => -1,
+                a => unwrap_or_return!(parse_int(a), None),
+            };
+            let b = match arg.slice_from(i+1) {
+                "" => 0,
+                b if b.starts_with("+") || b.starts_with("-") =>
+                    unwrap_or_return!(parse_int(b), None),
+                _ => return None,
+            };
+            Some((a, b))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_selectors, parse_nth_arg};
+    use selector::*;
+    use atom::Atom;
+
+    fn one(s: &str) -> Selector {
+        let mut sels = parse_selectors(s).unwrap();
+        assert_eq!(sels.len(), 1);
+        sels.pop().unwrap()
+    }
+
+    test_eq!(nth_odd, parse_nth_arg("odd"), Some((2, 1)))
+    test_eq!(nth_even, parse_nth_arg("even"), Some((2, 0)))
+    test_eq!(nth_b, parse_nth_arg("3"), Some((0, 3)))
+    test_eq!(nth_an, parse_nth_arg("-n"), Some((-1, 0)))
+    test_eq!(nth_an_b, parse_nth_arg("2n+1"), Some((2, 1)))
+    test_eq!(nth_an_minus_b, parse_nth_arg("-2n-3"), Some((-2, -3)))
+    test_eq!(nth_bad, parse_nth_arg("2x"), None)
+    test_eq!(nth_bad_b, parse_nth_arg("2n3"), None)
+
+    #[test]
+    fn compound() {
+        assert_eq!(one("DIV#main.a.b"), Selector {
+            compounds: vec!(vec!(
+                LocalName(Atom::from_str("div")),
+                Id("main".to_string()),
+                Class("a".to_string()),
+                Class("b".to_string()),
+            )),
+            combinators: vec!(),
+        });
+    }
+
+    #[test]
+    fn combinators() {
+        let sel = one("a > b + c ~ d e");
+        assert_eq!(sel.combinators,
+            vec!(Descendant, SubsequentSibling, NextSibling, Child));
+        assert_eq!(sel.compounds.len(), 5);
+        assert_eq!(*sel.compounds.get(0), vec!(LocalName(Atom::from_str("e"))));
+    }
+
+    #[test]
+    fn attrs() {
+        assert_eq!(*one("[href]").compounds.get(0),
+            vec!(Attr("href".to_string(), Exists, "".to_string())));
+        assert_eq!(*one("[lang|=en]").compounds.get(0),
+            vec!(Attr("lang".to_string(), DashMatch, "en".to_string())));
+        assert_eq!(*one("[title *= 'a b' ]").compounds.get(0),
+            vec!(Attr("title".to_string(), Substring, "a b".to_string())));
+    }
+
+    #[test]
+    fn pseudo_classes() {
+        assert_eq!(*one("li:first-child:nth-of-type( 2n + 1 ):not(.x)").compounds.get(0), vec!(
+            LocalName(Atom::from_str("li")),
+            NthChild(0, 1),
+            NthOfType(2, 1),
+            Negation(vec!(Class("x".to_string()))),
+        ));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(*one(".a\\:b").compounds.get(0), vec!(Class("a:b".to_string())));
+        assert_eq!(*one("#\\31 23").compounds.get(0), vec!(Id("123".to_string())));
+    }
+
+    #[test]
+    fn group() {
+        assert_eq!(parse_selectors("a, b ,c").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn errors() {
+        for s in ["", "a,", "a >", "p::before", ":hover", "[x~y]", "a[b", "#"].iter() {
+            assert!(parse_selectors(*s).is_err(), "{} should not parse", s);
+        }
+    }
+}
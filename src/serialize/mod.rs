@@ -8,8 +8,8 @@
 // except according to those terms.
 
 use tokenizer::AttrName;
-use util::namespace::{Namespace, HTML};
-use util::atom::Atom;
+use atom::namespace::{Namespace, HTML};
+use atom::Atom;
 
 use std::io::{Writer, IoResult};
 use std::default::Default;
@@ -47,6 +47,19 @@ struct ElemInfo {
 
 pub type AttrRef<'a> = (&'a AttrName, &'a str);
 
+/// Is this an HTML void element, which is written without an end tag
+/// and can't have children?
+pub fn is_void_element(name: &Atom) -> bool {
+    match *name {
+        atom!(area) | atom!(base) | atom!(basefont) | atom!(bgsound) | atom!(br)
+        | atom!(col) | atom!(embed) | atom!(frame) | atom!(hr) | atom!(img)
+        | atom!(input) | atom!(keygen) | atom!(link) | atom!(menuitem)
+        | atom!(meta) | atom!(param) | atom!(source) | atom!(track) | atom!(wbr)
+            => true,
+        _ => false,
+    }
+}
+
 pub struct Serializer<'wr, Wr> {
     writer: &'wr mut Wr,
     opts: SerializeOpts,
@@ -116,14 +129,7 @@ impl<'wr, Wr: Writer> Serializer<'wr, Wr> {
         }
         try!(self.writer.write_char('>'));
 
-        let ignore_children = ns == HTML && match name {
-            atom!(area) | atom!(base) | atom!(basefont) | atom!(bgsound) | atom!(br)
-            | atom!(col) | atom!(embed) | atom!(frame) | atom!(hr) | atom!(img)
-            | atom!(input) | atom!(keygen) | atom!(link) | atom!(menuitem)
-            | atom!(meta) | atom!(param) | atom!(source) | atom!(track) | atom!(wbr)
-                => true,
-            _ => false,
-        };
+        let ignore_children = ns == HTML && is_void_element(&name);
 
         self.parent().processed_first_child = true;
 
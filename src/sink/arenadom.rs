@@ -0,0 +1,420 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A DOM whose nodes live in an arena.
+//!
+//! Nodes are linked to their parent and siblings by plain references
+//! in `Cell`s, so there is no reference counting, inserting or removing a
+//! node is O(1), and the whole document is freed at once when the arena
+//! goes away.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let arena = TypedArena::new();
+//! let mut sink = ArenaSink::new(&arena);
+//! parse_to(&mut sink, one_input(my_str), Default::default());
+//! let document = sink.document();
+//! ```
+
+use atom::Atom;
+use atom::namespace::{Namespace, HTML};
+use tokenizer::{Attribute, AttrName};
+use tree_builder::{TreeSink, QuirksMode, NoQuirks};
+use serialize::{Serializable, Serializer};
+
+use arena::TypedArena;
+
+use std::cell::{Cell, RefCell};
+use std::collections::hashmap::HashSet;
+use std::io::IoResult;
+
+/// The different kinds of nodes in the DOM.
+#[deriving(Show)]
+pub enum NodeData {
+    /// The `Document` itself.
+    Document,
+
+    /// A `DOCTYPE` with name, public id, and system id.
+    Doctype(String, String, String),
+
+    /// A text node.
+    Text(RefCell<String>),
+
+    /// A comment.
+    Comment(String),
+
+    /// An element with attributes.
+    ///
+    /// FIXME: HTML namespace only for now.
+    Element(Atom, RefCell<Vec<Attribute>>),
+}
+
+type Link<'arena> = Cell<Option<Ref<'arena>>>;
+
+/// A DOM node.
+pub struct Node<'arena> {
+    pub data: NodeData,
+    parent: Link<'arena>,
+    next_sibling: Link<'arena>,
+    previous_sibling: Link<'arena>,
+    first_child: Link<'arena>,
+    last_child: Link<'arena>,
+
+    /// The "script already started" flag.
+    ///
+    /// Not meaningful for nodes other than HTML `<script>`.
+    pub script_already_started: Cell<bool>,
+}
+
+/// Reference to a DOM node.
+pub type Ref<'arena> = &'arena Node<'arena>;
+
+/// The arena which owns every node of a document.
+pub type Arena<'arena> = TypedArena<Node<'arena>>;
+
+/// Iterator over the children of a node.
+pub struct Children<'arena> {
+    next: Option<Ref<'arena>>,
+}
+
+impl<'arena> Iterator<Ref<'arena>> for Children<'arena> {
+    fn next(&mut self) -> Option<Ref<'arena>> {
+        let node = self.next;
+        self.next = node.and_then(|n| n.next_sibling.get());
+        node
+    }
+}
+
+impl<'arena> Node<'arena> {
+    fn new(data: NodeData) -> Node<'arena> {
+        Node {
+            data: data,
+            parent: Cell::new(None),
+            next_sibling: Cell::new(None),
+            previous_sibling: Cell::new(None),
+            first_child: Cell::new(None),
+            last_child: Cell::new(None),
+            script_already_started: Cell::new(false),
+        }
+    }
+
+    pub fn parent(&self) -> Option<Ref<'arena>> {
+        self.parent.get()
+    }
+
+    pub fn next_sibling(&self) -> Option<Ref<'arena>> {
+        self.next_sibling.get()
+    }
+
+    pub fn previous_sibling(&self) -> Option<Ref<'arena>> {
+        self.previous_sibling.get()
+    }
+
+    pub fn first_child(&self) -> Option<Ref<'arena>> {
+        self.first_child.get()
+    }
+
+    pub fn last_child(&self) -> Option<Ref<'arena>> {
+        self.last_child.get()
+    }
+
+    pub fn children(&self) -> Children<'arena> {
+        Children {
+            next: self.first_child.get(),
+        }
+    }
+
+    /// Do two references point at the same node?
+    pub fn same_node(&self, other: &Node<'arena>) -> bool {
+        (self as *const Node<'arena>) == (other as *const Node<'arena>)
+    }
+
+    /// Unlink this node from its parent and siblings.
+    pub fn detach(&self) {
+        let parent = self.parent.get();
+        let previous_sibling = self.previous_sibling.get();
+        let next_sibling = self.next_sibling.get();
+
+        self.parent.set(None);
+        self.previous_sibling.set(None);
+        self.next_sibling.set(None);
+
+        match next_sibling {
+            Some(next) => next.previous_sibling.set(previous_sibling),
+            None => match parent {
+                Some(p) => p.last_child.set(previous_sibling),
+                None => (),
+            },
+        }
+
+        match previous_sibling {
+            Some(prev) => prev.next_sibling.set(next_sibling),
+            None => match parent {
+                Some(p) => p.first_child.set(next_sibling),
+                None => (),
+            },
+        }
+    }
+
+    /// Append a node as the last child of this one.
+    ///
+    /// The new child is first detached from wherever it was.
+    pub fn append(&'arena self, new_child: Ref<'arena>) {
+        new_child.detach();
+        new_child.parent.set(Some(self));
+        match self.last_child.get() {
+            Some(last) => {
+                new_child.previous_sibling.set(Some(last));
+                last.next_sibling.set(Some(new_child));
+            }
+            None => self.first_child.set(Some(new_child)),
+        }
+        self.last_child.set(Some(new_child));
+    }
+
+    /// Insert a node as the previous sibling of this one.
+    ///
+    /// The new sibling is first detached from wherever it was.
+    pub fn insert_before(&'arena self, new_sibling: Ref<'arena>) {
+        new_sibling.detach();
+        new_sibling.parent.set(self.parent.get());
+        new_sibling.next_sibling.set(Some(self));
+        match self.previous_sibling.get() {
+            Some(prev) => {
+                new_sibling.previous_sibling.set(Some(prev));
+                prev.next_sibling.set(Some(new_sibling));
+            }
+            None => match self.parent.get() {
+                Some(p) => p.first_child.set(Some(new_sibling)),
+                None => (),
+            },
+        }
+        self.previous_sibling.set(Some(new_sibling));
+    }
+}
+
+/// A `TreeSink` which allocates nodes in an arena.
+pub struct ArenaSink<'arena> {
+    arena: &'arena Arena<'arena>,
+    document: Ref<'arena>,
+
+    /// Errors that occurred during parsing.
+    pub errors: Vec<String>,
+
+    /// The document's quirks mode.
+    pub quirks_mode: QuirksMode,
+}
+
+impl<'arena> ArenaSink<'arena> {
+    /// Create a sink which allocates the document and all of its
+    /// nodes in `arena`.
+    pub fn new(arena: &'arena Arena<'arena>) -> ArenaSink<'arena> {
+        ArenaSink {
+            arena: arena,
+            document: &*arena.alloc(Node::new(Document)),
+            errors: vec!(),
+            quirks_mode: NoQuirks,
+        }
+    }
+
+    /// Get the `Document` node.
+    pub fn document(&self) -> Ref<'arena> {
+        self.document
+    }
+
+    fn new_node(&self, data: NodeData) -> Ref<'arena> {
+        &*self.arena.alloc(Node::new(data))
+    }
+}
+
+impl<'arena> TreeSink<Ref<'arena>> for ArenaSink<'arena> {
+    fn parse_error(&mut self, msg: String) {
+        self.errors.push(msg);
+    }
+
+    fn get_document(&mut self) -> Ref<'arena> {
+        self.document
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.quirks_mode = mode;
+    }
+
+    fn same_node(&self, x: Ref<'arena>, y: Ref<'arena>) -> bool {
+        x.same_node(y)
+    }
+
+    fn elem_name(&self, target: Ref<'arena>) -> (Namespace, Atom) {
+        match target.data {
+            Element(ref name, _) => (HTML, name.clone()),
+            _ => fail!("not an element!"),
+        }
+    }
+
+    fn create_element(&mut self, ns: Namespace, name: Atom, attrs: Vec<Attribute>) -> Ref<'arena> {
+        assert!(ns == HTML);
+        self.new_node(Element(name, RefCell::new(attrs)))
+    }
+
+    fn append_text(&mut self, parent: Ref<'arena>, text: String) {
+        // Append to an existing Text node if we have one.
+        match parent.last_child() {
+            Some(&Node { data: Text(ref existing), .. }) => {
+                existing.borrow_mut().push_str(text.as_slice());
+                return;
+            }
+            _ => (),
+        }
+
+        // Otherwise, append a Text node.
+        parent.append(self.new_node(Text(RefCell::new(text))));
+    }
+
+    fn append_comment(&mut self, parent: Ref<'arena>, text: String) {
+        parent.append(self.new_node(Comment(text)));
+    }
+
+    fn append_element(&mut self, parent: Ref<'arena>, child: Ref<'arena>) {
+        parent.append(child);
+    }
+
+    fn append_before_sibling(&mut self, sibling: Ref<'arena>, new_node: Ref<'arena>) -> Result<(), Ref<'arena>> {
+        if sibling.parent().is_none() {
+            return Err(new_node);
+        }
+        sibling.insert_before(new_node);
+        Ok(())
+    }
+
+    fn append_doctype_to_document(&mut self, name: String, public_id: String, system_id: String) {
+        self.document.append(self.new_node(Doctype(name, public_id, system_id)));
+    }
+
+    fn add_attrs_if_missing(&mut self, target: Ref<'arena>, mut attrs: Vec<Attribute>) {
+        let mut existing = match target.data {
+            Element(_, ref attrs) => attrs.borrow_mut(),
+            _ => return,
+        };
+
+        let names: HashSet<AttrName> = existing.iter().map(|e| e.name.clone()).collect();
+        attrs.retain(|attr| !names.contains(&attr.name));
+        existing.push_all_move(attrs);
+    }
+
+    fn remove_from_parent(&mut self, target: Ref<'arena>) {
+        target.detach();
+    }
+
+    fn reparent_children(&mut self, node: Ref<'arena>, new_parent: Ref<'arena>) {
+        loop {
+            let child = unwrap_or_return!(node.first_child(), ());
+            child.detach();
+            new_parent.append(child);
+        }
+    }
+
+    fn mark_script_already_started(&mut self, node: Ref<'arena>) {
+        node.script_already_started.set(true);
+    }
+}
+
+impl<'arena> Serializable for Ref<'arena> {
+    fn serialize<'wr, Wr: Writer>(&self, serializer: &mut Serializer<'wr, Wr>, incl_self: bool) -> IoResult<()> {
+        match (incl_self, &self.data) {
+            (_, &Element(ref name, ref attrs)) => {
+                if incl_self {
+                    let attrs = attrs.borrow();
+                    try!(serializer.start_elem(HTML, name.clone(),
+                        attrs.iter().map(|at| (&at.name, at.value.as_slice()))));
+                }
+
+                for child in self.children() {
+                    try!(child.serialize(serializer, true));
+                }
+
+                if incl_self {
+                    try!(serializer.end_elem(HTML, name.clone()));
+                }
+                Ok(())
+            }
+
+            (false, &Document) => {
+                for child in self.children() {
+                    try!(child.serialize(serializer, true));
+                }
+                Ok(())
+            }
+
+            (false, _) => Ok(()),
+
+            (true, &Doctype(ref name, _, _)) => serializer.write_doctype(name.as_slice()),
+            (true, &Text(ref text)) => serializer.write_text(text.borrow().as_slice()),
+            (true, &Comment(ref text)) => serializer.write_comment(text.as_slice()),
+
+            (true, &Document) => fail!("Can't serialize Document node itself"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names<'arena>(parent: Ref<'arena>) -> Vec<String> {
+        parent.children().map(|n| match n.data {
+            Comment(ref s) => s.clone(),
+            _ => fail!("expected a comment"),
+        }).collect()
+    }
+
+    #[test]
+    fn append_and_detach() {
+        let arena = Arena::new();
+        let sink = ArenaSink::new(&arena);
+        let doc = sink.document();
+        let (a, b, c) = (sink.new_node(Comment("a".to_string())),
+                         sink.new_node(Comment("b".to_string())),
+                         sink.new_node(Comment("c".to_string())));
+        doc.append(a);
+        doc.append(b);
+        doc.append(c);
+        assert_eq!(names(doc), vec!("a".to_string(), "b".to_string(), "c".to_string()));
+
+        b.detach();
+        assert_eq!(names(doc), vec!("a".to_string(), "c".to_string()));
+        assert!(b.parent().is_none());
+        assert!(a.next_sibling().unwrap().same_node(c));
+        assert!(c.previous_sibling().unwrap().same_node(a));
+
+        a.detach();
+        c.detach();
+        assert!(doc.first_child().is_none());
+        assert!(doc.last_child().is_none());
+    }
+
+    #[test]
+    fn insert_before() {
+        let arena = Arena::new();
+        let sink = ArenaSink::new(&arena);
+        let doc = sink.document();
+        let (a, b, c) = (sink.new_node(Comment("a".to_string())),
+                         sink.new_node(Comment("b".to_string())),
+                         sink.new_node(Comment("c".to_string())));
+        doc.append(c);
+        c.insert_before(a);
+        c.insert_before(b);
+        assert_eq!(names(doc), vec!("a".to_string(), "b".to_string(), "c".to_string()));
+
+        // Moving a node unlinks it from its old position.
+        a.insert_before(c);
+        assert_eq!(names(doc), vec!("c".to_string(), "a".to_string(), "b".to_string()));
+        assert!(doc.last_child().unwrap().same_node(b));
+    }
+}
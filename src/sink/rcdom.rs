@@ -12,20 +12,23 @@
 //! This is sufficient as a static parse tree, but don't build a
 //! web browser using it. :)
 
-use util::atom::Atom;
-use util::namespace::{Namespace, HTML};
-use tokenizer::Attribute;
+use atom::Atom;
+use atom::namespace::{Namespace, HTML};
+use tokenizer::{Attribute, AttrName};
 use tree_builder::{TreeSink, QuirksMode, NoQuirks};
 use serialize::{Serializable, Serializer};
+use selector::SelectorNode;
 use driver::ParseResult;
 
 use std::rc::{Rc, Weak};
 use std::cell::RefCell;
+use std::collections::hashmap::HashSet;
 use std::default::Default;
 use std::io::IoResult;
+use std::mem::replace;
 
 /// The different kinds of nodes in the DOM.
-#[deriving(Show)]
+#[deriving(Clone, Show)]
 pub enum NodeEnum {
     /// The `Document` itself.
     Document,
@@ -46,15 +49,27 @@ pub enum NodeEnum {
 }
 
 /// A DOM node.
+///
+/// The `parent` and `children` fields are public for reading.  Modify
+/// the tree through the methods of `NodeHandle` instead, which keep the
+/// links consistent.
 pub struct Node {
     pub node: NodeEnum,
     pub parent: Option<WeakHandle>,
     pub children: Vec<Handle>,
 
+    /// Position of this node within `parent.children`.
+    index: uint,
+
     /// The "script already started" flag.
     ///
     /// Not meaningful for nodes other than HTML `<script>`.
     pub script_already_started: bool,
+
+    /// The `<form>` the parser associated this element with.
+    ///
+    /// Only set for form-associated elements without a `form` attribute.
+    pub form_owner: Option<WeakHandle>,
 }
 
 impl Node {
@@ -63,14 +78,11 @@ impl Node {
             node: node,
             parent: None,
             children: vec!(),
+            index: 0,
             script_already_started: false,
+            form_owner: None,
         }
     }
-
-    fn parent(&self) -> Handle {
-        self.parent.as_ref().expect("no parent!")
-            .upgrade().expect("dangling weak pointer!")
-    }
 }
 
 /// Reference to a DOM node.
@@ -80,19 +92,277 @@ pub type Handle = Rc<RefCell<Node>>;
 pub type WeakHandle = Weak<RefCell<Node>>;
 
 fn same_node(x: &Handle, y: &Handle) -> bool {
-    // FIXME: This shouldn't really need to touch the borrow flags, right?
-    (&*x.borrow() as *const Node) == (&*y.borrow() as *const Node)
+    (&**x as *const RefCell<Node>) == (&**y as *const RefCell<Node>)
 }
 
 fn new_node(node: NodeEnum) -> Handle {
     Rc::new(RefCell::new(Node::new(node)))
 }
 
+// Fix up the `index` of each child of `parent`, starting at `from`.
+fn renumber(parent: &Node, from: uint) {
+    for (i, child) in parent.children.iter().enumerate().skip(from) {
+        child.borrow_mut().index = i;
+    }
+}
+
+// Insert `child`, which must not have a parent, at position `i`
+// within `new_parent`.
+fn insert_at(new_parent: &Handle, i: uint, child: Handle) {
+    {
+        let mut c = child.borrow_mut();
+        assert!(c.parent.is_none());
+        c.parent = Some(new_parent.downgrade());
+    }
+    let mut parent = new_parent.borrow_mut();
+    parent.children.insert(i, child);
+    renumber(&*parent, i);
+}
+
+// Fail if inserting `child` into `new_parent` would make a node its
+// own ancestor.  Checked before any mutation, so a failed insertion
+// leaves the tree alone.
+fn check_insert(new_parent: &Handle, child: &Handle) {
+    if same_node(new_parent, child) || new_parent.ancestors().any(|a| same_node(&a, child)) {
+        fail!("inserting a node into itself or its descendant");
+    }
+}
+
 fn append(new_parent: &Handle, child: Handle) {
-    new_parent.borrow_mut().children.push(child.clone());
-    let parent = &mut child.borrow_mut().parent;
-    assert!(parent.is_none());
-    *parent = Some(new_parent.downgrade());
+    let i = new_parent.borrow().children.len();
+    insert_at(new_parent, i, child);
+}
+
+/// Navigation and mutation of the tree.
+pub trait NodeHandle {
+    /// The parent of this node, if any.
+    fn parent(&self) -> Option<Handle>;
+
+    /// The sibling immediately after this node, if any.
+    fn next_sibling(&self) -> Option<Handle>;
+
+    /// The sibling immediately before this node, if any.
+    fn previous_sibling(&self) -> Option<Handle>;
+
+    /// The first child of this node, if any.
+    fn first_child(&self) -> Option<Handle>;
+
+    /// The last child of this node, if any.
+    fn last_child(&self) -> Option<Handle>;
+
+    /// Iterate over the descendants of this node, in tree order.
+    /// Doesn't include the node itself.
+    fn descendants(&self) -> Descendants;
+
+    /// Iterate over the ancestors of this node, nearest first.
+    fn ancestors(&self) -> Ancestors;
+
+    /// Do two handles refer to the same node?
+    fn same_node(&self, other: &Handle) -> bool;
+
+    /// The DOM `textContent`: the data of a text or comment node, or the
+    /// concatenated text of all descendant text nodes.
+    fn text_content(&self) -> String;
+
+    /// Get the value of an attribute on an element.
+    fn get_attribute(&self, name: &str) -> Option<String>;
+
+    /// Set an attribute on an element, replacing any existing value.
+    /// Does nothing if this is not an element.
+    fn set_attribute(&self, name: &str, value: String);
+
+    /// Append a node as the last child of this one.  The new child is
+    /// first detached from its old parent, if any.  Fails if the new
+    /// child is this node or one of its ancestors.
+    fn append_child(&self, child: Handle);
+
+    /// Insert a node just before this one.  Fails if this node has
+    /// no parent, or if the new sibling is one of its ancestors.
+    /// Inserting a node before itself does nothing.
+    fn insert_before(&self, sibling: Handle);
+
+    /// Insert a node just after this one.  Fails if this node has
+    /// no parent, or if the new sibling is one of its ancestors.
+    /// Inserting a node after itself does nothing.
+    fn insert_after(&self, sibling: Handle);
+
+    /// Remove this node from its parent, if any.
+    fn detach(&self);
+
+    /// Copy this node and all of its descendants.  The copy has no parent.
+    fn deep_clone(&self) -> Handle;
+}
+
+impl NodeHandle for Handle {
+    fn parent(&self) -> Option<Handle> {
+        self.borrow().parent.as_ref().map(|p|
+            p.upgrade().expect("dangling weak pointer!"))
+    }
+
+    fn next_sibling(&self) -> Option<Handle> {
+        let parent = unwrap_or_return!(self.parent(), None);
+        let parent = parent.borrow();
+        parent.children.as_slice().get(self.borrow().index + 1).map(|h| h.clone())
+    }
+
+    fn previous_sibling(&self) -> Option<Handle> {
+        let parent = unwrap_or_return!(self.parent(), None);
+        let i = self.borrow().index;
+        if i == 0 {
+            return None;
+        }
+        Some(parent.borrow().children.get(i - 1).clone())
+    }
+
+    fn first_child(&self) -> Option<Handle> {
+        self.borrow().children.as_slice().head().map(|h| h.clone())
+    }
+
+    fn last_child(&self) -> Option<Handle> {
+        self.borrow().children.last().map(|h| h.clone())
+    }
+
+    fn descendants(&self) -> Descendants {
+        Descendants {
+            stack: self.borrow().children.iter().rev().map(|h| h.clone()).collect(),
+        }
+    }
+
+    fn ancestors(&self) -> Ancestors {
+        Ancestors {
+            next: self.parent(),
+        }
+    }
+
+    fn same_node(&self, other: &Handle) -> bool {
+        same_node(self, other)
+    }
+
+    fn text_content(&self) -> String {
+        match self.borrow().node {
+            Text(ref t) | Comment(ref t) => return t.clone(),
+            Doctype(..) => return String::new(),
+            Document | Element(..) => (),
+        }
+
+        let mut buf = String::new();
+        for node in self.descendants() {
+            match node.borrow().node {
+                Text(ref t) => buf.push_str(t.as_slice()),
+                _ => (),
+            }
+        }
+        buf
+    }
+
+    fn get_attribute(&self, name: &str) -> Option<String> {
+        match self.borrow().node {
+            Element(_, ref attrs) => attrs.iter()
+                .find(|a| a.name.as_slice() == name)
+                .map(|a| a.value.clone()),
+            _ => None,
+        }
+    }
+
+    fn set_attribute(&self, name: &str, value: String) {
+        let mut node = self.borrow_mut();
+        // FIXME: mozilla/rust#15609
+        let attrs = match node.deref_mut().node {
+            Element(_, ref mut attrs) => attrs,
+            _ => return,
+        };
+
+        match attrs.mut_iter().find(|a| a.name.as_slice() == name) {
+            Some(a) => {
+                a.value = value;
+                return;
+            }
+            None => (),
+        }
+
+        attrs.push(Attribute {
+            name: AttrName::new(Atom::from_str(name)),
+            value: value,
+        });
+    }
+
+    fn append_child(&self, child: Handle) {
+        check_insert(self, &child);
+        child.detach();
+        append(self, child);
+    }
+
+    fn insert_before(&self, sibling: Handle) {
+        let parent = self.parent().expect("no parent!");
+        check_insert(&parent, &sibling);
+        if same_node(self, &sibling) {
+            return;
+        }
+        sibling.detach();
+        let i = self.borrow().index;
+        insert_at(&parent, i, sibling);
+    }
+
+    fn insert_after(&self, sibling: Handle) {
+        let parent = self.parent().expect("no parent!");
+        check_insert(&parent, &sibling);
+        if same_node(self, &sibling) {
+            return;
+        }
+        sibling.detach();
+        let i = self.borrow().index;
+        insert_at(&parent, i + 1, sibling);
+    }
+
+    fn detach(&self) {
+        let parent = unwrap_or_return!(self.parent(), ());
+        let i = self.borrow().index;
+        {
+            let mut parent = parent.borrow_mut();
+            parent.children.remove(i).expect("not found!");
+            renumber(&*parent, i);
+        }
+        self.borrow_mut().parent = None;
+    }
+
+    fn deep_clone(&self) -> Handle {
+        let node = self.borrow();
+        let copy = new_node(node.node.clone());
+        copy.borrow_mut().script_already_started = node.script_already_started;
+        for child in node.children.iter() {
+            append(&copy, child.deep_clone());
+        }
+        copy
+    }
+}
+
+/// Iterator over the descendants of a node, in tree order.
+pub struct Descendants {
+    // Nodes yet to visit, next on the end.
+    stack: Vec<Handle>,
+}
+
+impl Iterator<Handle> for Descendants {
+    fn next(&mut self) -> Option<Handle> {
+        let node = unwrap_or_return!(self.stack.pop(), None);
+        for child in node.borrow().children.iter().rev() {
+            self.stack.push(child.clone());
+        }
+        Some(node)
+    }
+}
+
+/// Iterator over the ancestors of a node, nearest first.
+pub struct Ancestors {
+    next: Option<Handle>,
+}
+
+impl Iterator<Handle> for Ancestors {
+    fn next(&mut self) -> Option<Handle> {
+        let node = unwrap_or_return!(self.next.take(), None);
+        self.next = node.parent();
+        Some(node)
+    }
 }
 
 /// The DOM itself; the result of parsing.
@@ -161,6 +431,14 @@ impl TreeSink<Handle> for RcDom {
         append(&parent, child);
     }
 
+    fn append_before_sibling(&mut self, sibling: Handle, new_node: Handle) -> Result<(), Handle> {
+        if sibling.parent().is_none() {
+            return Err(new_node);
+        }
+        sibling.insert_before(new_node);
+        Ok(())
+    }
+
     fn append_doctype_to_document(&mut self, name: String, public_id: String, system_id: String) {
         append(&self.document, new_node(Doctype(name, public_id, system_id)));
     }
@@ -173,30 +451,30 @@ impl TreeSink<Handle> for RcDom {
             _ => return,
         };
 
-        // FIXME: quadratic time
-        attrs.retain(|attr|
-            !existing.iter().any(|e| e.name == attr.name));
+        let names: HashSet<AttrName> = existing.iter().map(|e| e.name.clone()).collect();
+        attrs.retain(|attr| !names.contains(&attr.name));
         existing.push_all_move(attrs);
     }
 
     fn remove_from_parent(&mut self, target: Handle) {
-        {
-            let child = target.borrow();
-            let parent = child.parent();
-            let mut parent = parent.borrow_mut();
-            let (i, _) = parent.children.iter().enumerate()
-                .find(|&(_, n)| same_node(n, &target))
-                .expect("not found!");
-            parent.children.remove(i).expect("not found!");
-        }
+        target.detach();
+    }
 
-        let mut child = target.borrow_mut();
-        (*child).parent = None;
+    fn reparent_children(&mut self, node: Handle, new_parent: Handle) {
+        let children = replace(&mut node.borrow_mut().children, vec!());
+        for child in children.move_iter() {
+            child.borrow_mut().parent = None;
+            append(&new_parent, child);
+        }
     }
 
     fn mark_script_already_started(&mut self, node: Handle) {
         node.borrow_mut().script_already_started = true;
     }
+
+    fn associate_with_form(&mut self, target: Handle, form: Handle) {
+        target.borrow_mut().form_owner = Some(form.downgrade());
+    }
 }
 
 impl Default for RcDom {
@@ -252,3 +530,161 @@ impl Serializable for Handle {
         }
     }
 }
+
+impl SelectorNode for Handle {
+    fn same_node(&self, other: &Handle) -> bool {
+        same_node(self, other)
+    }
+
+    fn parent_node(&self) -> Option<Handle> {
+        self.parent()
+    }
+
+    fn first_child_node(&self) -> Option<Handle> {
+        self.first_child()
+    }
+
+    fn prev_sibling_node(&self) -> Option<Handle> {
+        self.previous_sibling()
+    }
+
+    fn next_sibling_node(&self) -> Option<Handle> {
+        self.next_sibling()
+    }
+
+    fn local_name(&self) -> Option<Atom> {
+        match self.borrow().node {
+            Element(ref name, _) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    fn attr_value(&self, name: &str) -> Option<String> {
+        self.get_attribute(name)
+    }
+
+    fn is_nonempty_text(&self) -> bool {
+        match self.borrow().node {
+            Text(ref t) => !t.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Handle, NodeHandle, Comment, Element, new_node};
+    use atom::Atom;
+
+    fn comment(s: &str) -> Handle {
+        new_node(Comment(s.to_string()))
+    }
+
+    fn elem(name: &str) -> Handle {
+        new_node(Element(Atom::from_str(name), vec!()))
+    }
+
+    // The children of `parent`, after checking that their parent
+    // pointers, cached indices and sibling links agree.
+    fn names(parent: &Handle) -> Vec<String> {
+        let children = parent.borrow().children.clone();
+        for (i, child) in children.iter().enumerate() {
+            assert!(child.parent().expect("no parent").same_node(parent));
+            assert_eq!(child.borrow().index, i);
+            assert_eq!(child.previous_sibling().is_some(), i > 0);
+            assert_eq!(child.next_sibling().is_some(), i + 1 < children.len());
+            match child.next_sibling() {
+                Some(next) => assert!(next.previous_sibling().unwrap().same_node(child)),
+                None => (),
+            }
+        }
+        children.iter().map(|n| n.text_content()).collect()
+    }
+
+    fn strs(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn append_and_detach() {
+        let (p, a, b, c) = (elem("p"), comment("a"), comment("b"), comment("c"));
+        p.append_child(a.clone());
+        p.append_child(b.clone());
+        p.append_child(c.clone());
+        assert_eq!(names(&p), strs(["a", "b", "c"]));
+
+        b.detach();
+        assert_eq!(names(&p), strs(["a", "c"]));
+        assert!(b.parent().is_none());
+
+        // Appending an existing child moves it to the end.
+        p.append_child(a.clone());
+        assert_eq!(names(&p), strs(["c", "a"]));
+    }
+
+    #[test]
+    fn insert_siblings() {
+        let (p, a, b, c) = (elem("p"), comment("a"), comment("b"), comment("c"));
+        p.append_child(b.clone());
+        b.insert_before(a.clone());
+        b.insert_after(c.clone());
+        assert_eq!(names(&p), strs(["a", "b", "c"]));
+
+        // Moving within the same parent.
+        a.insert_after(c.clone());
+        assert_eq!(names(&p), strs(["a", "c", "b"]));
+        b.insert_before(a.clone());
+        assert_eq!(names(&p), strs(["c", "a", "b"]));
+    }
+
+    #[test]
+    fn insert_self() {
+        let (p, a, b) = (elem("p"), comment("a"), comment("b"));
+        p.append_child(a.clone());
+        p.append_child(b.clone());
+        a.insert_before(a.clone());
+        a.insert_after(a.clone());
+        assert_eq!(names(&p), strs(["a", "b"]));
+    }
+
+    #[test]
+    #[should_fail]
+    fn append_ancestor() {
+        let (p, q) = (elem("p"), elem("q"));
+        p.append_child(q.clone());
+        q.append_child(p);
+    }
+
+    #[test]
+    #[should_fail]
+    fn append_self() {
+        let p = elem("p");
+        p.append_child(p.clone());
+    }
+
+    #[test]
+    #[should_fail]
+    fn insert_parent_as_sibling() {
+        let (p, a) = (elem("p"), comment("a"));
+        p.append_child(a.clone());
+        a.insert_before(p);
+    }
+
+    #[test]
+    fn deep_clone() {
+        let (p, q, a, b) = (elem("p"), elem("q"), comment("a"), comment("b"));
+        p.append_child(a);
+        p.append_child(q.clone());
+        q.append_child(b);
+
+        let copy = q.deep_clone();
+        assert!(copy.parent().is_none());
+        assert!(!copy.same_node(&q));
+        assert_eq!(names(&copy), strs(["b"]));
+        assert!(!copy.first_child().unwrap().same_node(&q.first_child().unwrap()));
+
+        let copy = p.deep_clone();
+        assert_eq!(names(&copy), strs(["a", ""]));
+        assert_eq!(names(&copy.last_child().unwrap()), strs(["b"]));
+    }
+}
@@ -0,0 +1,790 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Extracting structured data: microdata, RDFa Lite and JSON-LD.
+//!
+//! Items are represented as in the microdata JSON conversion, as objects
+//! with optional `type` and `id` entries and a `properties` object
+//! mapping each property name to a list of values.  RDFa Lite resources
+//! use the same shape, with types and properties expanded to IRIs.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let dom: RcDom = parse(one_input(page), Default::default());
+//! let data = extract(&dom.document, "http://example.com/page");
+//! for item in data.microdata.iter() {
+//!     println!("{}", item.to_json());
+//! }
+//! ```
+
+use links::base_url;
+use sink::rcdom::{Node, Handle, NodeHandle, Element};
+use util::str::is_ascii_whitespace;
+use util::url::resolve;
+
+use std::ascii::StrAsciiExt;
+use std::cell::RefCell;
+use std::char;
+use std::collections::hashmap::HashMap;
+
+/// A JSON value.  Objects keep their entries in order.
+#[deriving(PartialEq, Clone, Show)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    List(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+fn write_json_string(s: &str, out: &mut String) {
+    out.push_char('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => out.push_str(format!("\\u{:04x}", c as u32).as_slice()),
+            c => out.push_char(c),
+        }
+    }
+    out.push_char('"');
+}
+
+impl Value {
+    /// Serialize as JSON, without whitespace.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+
+    fn write_json(&self, out: &mut String) {
+        match *self {
+            Null => out.push_str("null"),
+            Bool(b) => out.push_str(if b { "true" } else { "false" }),
+            // NaN and infinities.
+            Number(n) if n - n != 0.0 => out.push_str("null"),
+            Number(n) if n > -1e15 && n < 1e15 && (n as i64) as f64 == n
+                => out.push_str(format!("{}", n as i64).as_slice()),
+            Number(n) => out.push_str(format!("{}", n).as_slice()),
+            Str(ref s) => write_json_string(s.as_slice(), out),
+            List(ref vs) => {
+                out.push_char('[');
+                for (i, v) in vs.iter().enumerate() {
+                    if i > 0 {
+                        out.push_char(',');
+                    }
+                    v.write_json(out);
+                }
+                out.push_char(']');
+            }
+            Object(ref entries) => {
+                out.push_char('{');
+                for (i, &(ref k, ref v)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        out.push_char(',');
+                    }
+                    write_json_string(k.as_slice(), out);
+                    out.push_char(':');
+                    v.write_json(out);
+                }
+                out.push_char('}');
+            }
+        }
+    }
+}
+
+// Maximum nesting of JSON lists and objects.
+static MAX_JSON_DEPTH: uint = 256;
+
+struct JsonParser<'a> {
+    input: &'a str,
+    pos: uint,
+
+    /// Number of lists and objects currently open.
+    depth: uint,
+}
+
+impl<'a> JsonParser<'a> {
+    fn err<T>(&self, msg: &str) -> Result<T, String> {
+        Err(format!("{} at byte {}", msg, self.pos))
+    }
+
+    fn peek(&self) -> Option<char> {
+        if self.pos < self.input.len() {
+            Some(self.input.char_at(self.pos))
+        } else {
+            None
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek();
+        match c {
+            Some(c) => self.pos += c.len_utf8_bytes(),
+            None => (),
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, |c| c == ' ' || c == '\t' || c == '\n' || c == '\r') {
+            self.bump();
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.bump() == Some(c) {
+            Ok(())
+        } else {
+            self.err(format!("expected '{}'", c).as_slice())
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        if self.input.slice_from(self.pos).starts_with(word) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            self.err("invalid literal")
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') | Some('[') if self.depth >= MAX_JSON_DEPTH
+                => self.err("JSON nested too deeply"),
+            Some('{') => self.nested(|p| p.object()),
+            Some('[') => self.nested(|p| p.list()),
+            Some('"') => self.string().map(|s| Str(s)),
+            Some('t') => self.literal("true", Bool(true)),
+            Some('f') => self.literal("false", Bool(false)),
+            Some('n') => self.literal("null", Null),
+            Some(c) if c == '-' || c.is_digit() => self.number(),
+            Some(_) => self.err("unexpected character"),
+            None => self.err("unexpected end of input"),
+        }
+    }
+
+    fn nested(&mut self, f: |&mut JsonParser<'a>| -> Result<Value, String>) -> Result<Value, String> {
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        try!(self.expect('{'));
+        let mut entries = vec!();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = try!(self.string());
+            self.skip_whitespace();
+            try!(self.expect(':'));
+            let value = try!(self.value());
+            entries.push((key, value));
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => (),
+                Some('}') => return Ok(Object(entries)),
+                _ => return self.err("expected ',' or '}'"),
+            }
+        }
+    }
+
+    fn list(&mut self) -> Result<Value, String> {
+        try!(self.expect('['));
+        let mut values = vec!();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(List(values));
+        }
+        loop {
+            values.push(try!(self.value()));
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => (),
+                Some(']') => return Ok(List(values)),
+                _ => return self.err("expected ',' or ']'"),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut n = 0;
+        for _ in range(0u, 4) {
+            match self.bump().and_then(|c| c.to_digit(16)) {
+                Some(d) => n = n * 16 + d as u32,
+                None => return self.err("invalid \\u escape"),
+            }
+        }
+        Ok(n)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        try!(self.expect('"'));
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                None => return self.err("unterminated string"),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.bump() {
+                    Some('"') => s.push_char('"'),
+                    Some('\\') => s.push_char('\\'),
+                    Some('/') => s.push_char('/'),
+                    Some('b') => s.push_char('\x08'),
+                    Some('f') => s.push_char('\x0c'),
+                    Some('n') => s.push_char('\n'),
+                    Some('r') => s.push_char('\r'),
+                    Some('t') => s.push_char('\t'),
+                    Some('u') => {
+                        let mut n = try!(self.hex4());
+                        if n >= 0xD800 && n <= 0xDBFF
+                                && self.input.slice_from(self.pos).starts_with("\\u") {
+                            self.pos += 2;
+                            let low = try!(self.hex4());
+                            if low >= 0xDC00 && low <= 0xDFFF {
+                                n = 0x10000 + ((n - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                        }
+                        s.push_char(char::from_u32(n).unwrap_or('\ufffd'));
+                    }
+                    _ => return self.err("invalid escape"),
+                },
+                Some(c) if c < ' ' => return self.err("control character in string"),
+                Some(c) => s.push_char(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while self.peek().map_or(false, |c| c.is_digit() || c == '-' || c == '+' || c == '.'
+                || c == 'e' || c == 'E') {
+            self.bump();
+        }
+        match from_str::<f64>(self.input.slice(start, self.pos)) {
+            Some(n) => Ok(Number(n)),
+            None => self.err("invalid number"),
+        }
+    }
+}
+
+/// Parse a JSON text.
+pub fn parse_json(input: &str) -> Result<Value, String> {
+    let mut p = JsonParser {
+        input: input,
+        pos: 0,
+        depth: 0,
+    };
+    let value = try!(p.value());
+    p.skip_whitespace();
+    if p.pos < input.len() {
+        return p.err("trailing characters");
+    }
+    Ok(value)
+}
+
+/// The structured data found in a document.
+pub struct StructuredData {
+    /// Top-level microdata items.
+    pub microdata: Vec<Value>,
+
+    /// RDFa Lite resources.  Properties outside any `typeof` describe the
+    /// document itself, and are collected into a first item whose `id` is
+    /// the base URL.
+    pub rdfa: Vec<Value>,
+
+    /// The contents of each `<script type="application/ld+json">`, or
+    /// the error from parsing it.
+    pub json_ld: Vec<Result<Value, String>>,
+}
+
+fn is_element(node: &Handle) -> bool {
+    match node.borrow().node {
+        Element(..) => true,
+        _ => false,
+    }
+}
+
+fn has_attr(node: &Handle, name: &str) -> bool {
+    node.get_attribute(name).is_some()
+}
+
+fn child_elements(node: &Handle) -> Vec<Handle> {
+    node.borrow().children.iter().filter(|c| is_element(*c)).map(|c| c.clone()).collect()
+}
+
+// The unique space-separated tokens of an attribute, in order.
+fn tokens(node: &Handle, name: &str) -> Vec<String> {
+    let value = node.get_attribute(name).unwrap_or(String::new());
+    let mut out: Vec<String> = vec!();
+    for t in value.as_slice().split(is_ascii_whitespace).filter(|t| !t.is_empty()) {
+        if !out.iter().any(|o| o.as_slice() == t) {
+            out.push(t.to_string());
+        }
+    }
+    out
+}
+
+fn add_property(props: &mut Vec<(String, Vec<Value>)>, name: String, value: Value) {
+    match props.iter().position(|&(ref n, _)| *n == name) {
+        Some(i) => match *props.get_mut(i) {
+            (_, ref mut values) => values.push(value),
+        },
+        None => props.push((name, vec!(value))),
+    }
+}
+
+fn item_value(types: Vec<String>, id: Option<String>, props: Vec<(String, Vec<Value>)>) -> Value {
+    let mut entries = vec!();
+    if !types.is_empty() {
+        entries.push(("type".to_string(), List(types.move_iter().map(|t| Str(t)).collect())));
+    }
+    match id {
+        Some(id) => entries.push(("id".to_string(), Str(id))),
+        None => (),
+    }
+    entries.push(("properties".to_string(),
+        Object(props.move_iter().map(|(n, vs)| (n, List(vs))).collect())));
+    Object(entries)
+}
+
+struct Extractor {
+    base: String,
+
+    /// Every element, in tree order.
+    elements: Vec<Handle>,
+
+    /// The position of each element in `elements`, by node address.
+    positions: HashMap<uint, uint>,
+
+    /// The first element with each `id`.
+    ids: HashMap<String, Handle>,
+}
+
+fn node_key(node: &Handle) -> uint {
+    &**node as *const RefCell<Node> as uint
+}
+
+// RDFa Lite in-scope mappings.
+#[deriving(Clone)]
+struct RdfaContext {
+    vocab: Option<String>,
+    prefixes: Vec<(String, String)>,
+}
+
+impl RdfaContext {
+    fn new() -> RdfaContext {
+        // Part of the RDFa initial context.
+        let prefixes = [
+            ("dc", "http://purl.org/dc/terms/"),
+            ("foaf", "http://xmlns.com/foaf/0.1/"),
+            ("og", "http://ogp.me/ns#"),
+            ("schema", "http://schema.org/"),
+        ];
+        RdfaContext {
+            vocab: None,
+            prefixes: prefixes.iter().map(|&(p, iri)| (p.to_string(), iri.to_string())).collect(),
+        }
+    }
+
+    fn update(&mut self, node: &Handle) {
+        match node.get_attribute("vocab") {
+            Some(ref v) if v.is_empty() => self.vocab = None,
+            Some(v) => self.vocab = Some(v),
+            None => (),
+        }
+
+        // Not `tokens`, which would drop a repeated IRI and misalign the pairs.
+        let prefix = node.get_attribute("prefix").unwrap_or(String::new());
+        let prefix: Vec<&str> = prefix.as_slice().split(is_ascii_whitespace)
+            .filter(|t| !t.is_empty()).collect();
+        for pair in prefix.as_slice().chunks(2) {
+            match pair {
+                [p, iri] if p.ends_with(":") => {
+                    let p = p.slice_to(p.len() - 1).to_ascii_lower();
+                    self.prefixes.push((p, iri.to_string()));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    // Expand a term or CURIE to an IRI.
+    fn expand(&self, term: &str) -> String {
+        match term.find(':') {
+            Some(i) => {
+                let prefix = term.slice_to(i).to_ascii_lower();
+                match self.prefixes.iter().rev().find(|&&(ref p, _)| *p == prefix) {
+                    Some(&(_, ref iri)) => format!("{}{}", iri, term.slice_from(i + 1)),
+                    None => term.to_string(),
+                }
+            }
+            None => match self.vocab {
+                Some(ref v) => format!("{}{}", v, term),
+                None => term.to_string(),
+            },
+        }
+    }
+}
+
+impl Extractor {
+    fn resolve(&self, url: &str) -> String {
+        resolve(self.base.as_slice(), url).unwrap_or(String::new())
+    }
+
+    fn position(&self, node: &Handle) -> uint {
+        self.positions.find(&node_key(node)).map_or(0, |&i| i)
+    }
+
+    fn by_id(&self, id: &str) -> Option<Handle> {
+        self.ids.find_equiv(&id).map(|e| e.clone())
+    }
+
+    //§ associating-names-with-items
+    fn microdata_properties(&self, root: &Handle) -> Vec<Handle> {
+        let mut results = vec!();
+        let mut memory = vec!(root.clone());
+        let mut pending = child_elements(root);
+        for id in tokens(root, "itemref").iter() {
+            match self.by_id(id.as_slice()) {
+                Some(e) => pending.push(e),
+                None => (),
+            }
+        }
+
+        loop {
+            let current = match pending.pop() {
+                Some(c) => c,
+                None => break,
+            };
+            if memory.iter().any(|m| m.same_node(&current)) {
+                // Microdata error: a loop.
+                continue;
+            }
+            memory.push(current.clone());
+            if !has_attr(&current, "itemscope") {
+                pending.push_all_move(child_elements(&current));
+            }
+            if !tokens(&current, "itemprop").is_empty() {
+                results.push(current);
+            }
+        }
+
+        results.sort_by(|a, b| self.position(a).cmp(&self.position(b)));
+        results
+    }
+    //§ END
+
+    //§ values
+    fn microdata_value(&self, node: &Handle, in_progress: &mut Vec<Handle>) -> Value {
+        if has_attr(node, "itemscope") {
+            if in_progress.iter().any(|i| i.same_node(node)) {
+                return Str("ERROR".to_string());
+            }
+            return self.microdata_item(node, in_progress);
+        }
+
+        let name = match node.borrow().node {
+            Element(ref name, _) => name.clone(),
+            _ => unreachable!(),
+        };
+        let attr = |name: &str| node.get_attribute(name).unwrap_or(String::new());
+        // A missing URL attribute gives the empty string, not the base URL.
+        let url_attr = |name: &str| node.get_attribute(name)
+            .map_or(String::new(), |v| self.resolve(v.as_slice()));
+        Str(match name {
+            atom!(meta) => attr("content"),
+            atom!(audio) | atom!(embed) | atom!(iframe) | atom!(img) | atom!(source)
+            | atom!(track) | atom!(video) => url_attr("src"),
+            atom!(a) | atom!(area) | atom!(link) => url_attr("href"),
+            atom!(object) => url_attr("data"),
+            atom!(data) | atom!(meter) => attr("value"),
+            atom!(time) if has_attr(node, "datetime") => attr("datetime"),
+            _ => node.text_content(),
+        })
+    }
+    //§ END
+
+    fn microdata_item(&self, root: &Handle, in_progress: &mut Vec<Handle>) -> Value {
+        in_progress.push(root.clone());
+        let types = tokens(root, "itemtype");
+        let id = match root.get_attribute("itemid") {
+            Some(ref id) if !types.is_empty() => Some(self.resolve(id.as_slice())),
+            _ => None,
+        };
+
+        let mut props = vec!();
+        for elem in self.microdata_properties(root).iter() {
+            let value = self.microdata_value(elem, in_progress);
+            for name in tokens(elem, "itemprop").move_iter() {
+                add_property(&mut props, name, value.clone());
+            }
+        }
+
+        in_progress.pop();
+        item_value(types, id, props)
+    }
+
+    fn microdata(&self) -> Vec<Value> {
+        self.elements.iter()
+            .filter(|e| has_attr(*e, "itemscope") && !has_attr(*e, "itemprop"))
+            .map(|e| self.microdata_item(e, &mut vec!()))
+            .collect()
+    }
+
+    // The in-scope RDFa mappings for an element, including its own.
+    fn rdfa_context(&self, node: &Handle) -> RdfaContext {
+        let mut ancestors: Vec<Handle> = node.ancestors().collect();
+        ancestors.reverse();
+        let mut ctx = RdfaContext::new();
+        for a in ancestors.iter().filter(|a| is_element(*a)) {
+            ctx.update(a);
+        }
+        ctx.update(node);
+        ctx
+    }
+
+    fn rdfa_value(&self, node: &Handle) -> Value {
+        let name = match node.borrow().node {
+            Element(ref name, _) => name.clone(),
+            _ => unreachable!(),
+        };
+        let url_attr = match name {
+            atom!(a) | atom!(area) | atom!(link) => Some("href"),
+            atom!(audio) | atom!(embed) | atom!(iframe) | atom!(img) | atom!(source)
+            | atom!(track) | atom!(video) => Some("src"),
+            atom!(object) => Some("data"),
+            _ => None,
+        };
+
+        let url = node.get_attribute("resource")
+            .or_else(|| url_attr.and_then(|a| node.get_attribute(a)));
+        Str(match url {
+            Some(url) => self.resolve(url.as_slice()),
+            None => match node.get_attribute("content") {
+                Some(c) => c,
+                None => match name {
+                    atom!(time) if has_attr(node, "datetime")
+                        => node.get_attribute("datetime").unwrap(),
+                    _ => node.text_content(),
+                },
+            },
+        })
+    }
+
+    // Collect the properties of the resource at `node`, stopping at
+    // descendants which start another resource.
+    fn rdfa_collect(&self, node: &Handle, ctx: &RdfaContext, props: &mut Vec<(String, Vec<Value>)>) {
+        for child in child_elements(node).iter() {
+            let mut ctx = ctx.clone();
+            ctx.update(child);
+            let has_typeof = has_attr(child, "typeof");
+            if has_attr(child, "property") {
+                let value = if has_typeof {
+                    self.rdfa_item(child, &ctx)
+                } else {
+                    self.rdfa_value(child)
+                };
+                for name in tokens(child, "property").iter() {
+                    add_property(props, ctx.expand(name.as_slice()), value.clone());
+                }
+            }
+            if !has_typeof {
+                self.rdfa_collect(child, &ctx, props);
+            }
+        }
+    }
+
+    fn rdfa_item(&self, node: &Handle, ctx: &RdfaContext) -> Value {
+        let types = tokens(node, "typeof").iter().map(|t| ctx.expand(t.as_slice())).collect();
+        let id = node.get_attribute("resource").map(|r| self.resolve(r.as_slice()));
+        let mut props = vec!();
+        self.rdfa_collect(node, ctx, &mut props);
+        item_value(types, id, props)
+    }
+
+    fn rdfa(&self, document: &Handle) -> Vec<Value> {
+        let mut items = vec!();
+
+        let mut doc_props = vec!();
+        self.rdfa_collect(document, &RdfaContext::new(), &mut doc_props);
+        if !doc_props.is_empty() {
+            items.push(item_value(vec!(), Some(self.base.clone()), doc_props));
+        }
+
+        for e in self.elements.iter() {
+            if has_attr(e, "typeof") && !has_attr(e, "property") {
+                let ctx = self.rdfa_context(e);
+                items.push(self.rdfa_item(e, &ctx));
+            }
+        }
+        items
+    }
+
+    fn json_ld(&self) -> Vec<Result<Value, String>> {
+        self.elements.iter().filter(|e| {
+            match e.borrow().node {
+                Element(atom!(script), _) => e.get_attribute("type").map_or(false, |t|
+                    t.as_slice().trim_chars(is_ascii_whitespace).to_ascii_lower().as_slice()
+                        == "application/ld+json"),
+                _ => false,
+            }
+        }).map(|e| parse_json(e.text_content().as_slice())).collect()
+    }
+}
+
+/// Extract the structured data from a document at `document_url`.
+pub fn extract(document: &Handle, document_url: &str) -> StructuredData {
+    let elements: Vec<Handle> = document.descendants().filter(is_element).collect();
+    let mut positions = HashMap::new();
+    let mut ids = HashMap::new();
+    for (i, e) in elements.iter().enumerate() {
+        positions.insert(node_key(e), i);
+        match e.get_attribute("id") {
+            Some(id) => if !ids.contains_key(&id) {
+                ids.insert(id, e.clone());
+            },
+            None => (),
+        }
+    }
+
+    let ex = Extractor {
+        base: base_url(document, document_url),
+        elements: elements,
+        positions: positions,
+        ids: ids,
+    };
+    StructuredData {
+        microdata: ex.microdata(),
+        rdfa: ex.rdfa(document),
+        json_ld: ex.json_ld(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{extract, parse_json, StructuredData, Null, Bool, Number, Str, List, Object};
+
+    use driver::{parse, one_input};
+    use sink::rcdom::RcDom;
+
+    use std::default::Default;
+
+    fn data(html: &str) -> StructuredData {
+        let dom: RcDom = parse(one_input(html.to_string()), Default::default());
+        extract(&dom.document, "http://example.com/page")
+    }
+
+    fn microdata(html: &str) -> Vec<String> {
+        data(html).microdata.iter().map(|v| v.to_json()).collect()
+    }
+
+    fn rdfa(html: &str) -> Vec<String> {
+        data(html).rdfa.iter().map(|v| v.to_json()).collect()
+    }
+
+    test_eq!(microdata_item, microdata(concat!(
+            "<div itemscope itemtype=\"http://schema.org/Person\" itemid=\"urn:x\">",
+            "<span itemprop=\"name\">Ann</span>",
+            "<a itemprop=\"url\" href=\"/ann\">home</a>",
+            "<div itemprop=\"address\" itemscope><span itemprop=\"locality\">Paris</span></div>",
+            "<meta itemprop=\"age\" content=\"30\">",
+            "<time itemprop=\"born\" datetime=\"1990-01-01\">long ago</time>",
+            "</div>")),
+        vec!(concat!(
+            "{\"type\":[\"http://schema.org/Person\"],\"id\":\"urn:x\",\"properties\":{",
+            "\"name\":[\"Ann\"],\"url\":[\"http://example.com/ann\"],",
+            "\"address\":[{\"properties\":{\"locality\":[\"Paris\"]}}],",
+            "\"age\":[\"30\"],\"born\":[\"1990-01-01\"]}}").to_string()))
+
+    test_eq!(microdata_itemref, microdata(concat!(
+            "<div itemscope itemref=\"extra\"><span itemprop=\"a\">1</span></div>",
+            "<p id=\"extra\" itemprop=\"b c\">2</p>")),
+        vec!("{\"properties\":{\"a\":[\"1\"],\"b\":[\"2\"],\"c\":[\"2\"]}}".to_string()))
+
+    test_eq!(microdata_missing_href, microdata("<div itemscope><a itemprop=url>x</a></div>"),
+        vec!("{\"properties\":{\"url\":[\"\"]}}".to_string()))
+
+    test_eq!(microdata_loop, microdata("<div itemscope id=x itemref=x><span itemprop=a>1</span></div>"),
+        vec!("{\"properties\":{\"a\":[\"1\"]}}".to_string()))
+
+    test_eq!(rdfa_vocab, rdfa(concat!(
+            "<div vocab=\"http://schema.org/\" typeof=\"Person\" resource=\"#me\">",
+            "<span property=\"name\">Ann</span>",
+            "<a property=\"url\" href=\"/ann\">home</a>",
+            "<div property=\"knows\" typeof=\"Person\"><span property=\"name\">Bob</span></div>",
+            "</div>")),
+        vec!(concat!(
+            "{\"type\":[\"http://schema.org/Person\"],\"id\":\"http://example.com/page#me\",",
+            "\"properties\":{\"http://schema.org/name\":[\"Ann\"],",
+            "\"http://schema.org/url\":[\"http://example.com/ann\"],",
+            "\"http://schema.org/knows\":[{\"type\":[\"http://schema.org/Person\"],",
+            "\"properties\":{\"http://schema.org/name\":[\"Bob\"]}}]}}").to_string()))
+
+    test_eq!(rdfa_prefix, rdfa(concat!(
+            "<p prefix=\"ex: http://example.org/ns#\" typeof=\"ex:Thing\">",
+            "<span property=\"ex:name\">C</span></p>")),
+        vec!(concat!(
+            "{\"type\":[\"http://example.org/ns#Thing\"],",
+            "\"properties\":{\"http://example.org/ns#name\":[\"C\"]}}").to_string()))
+
+    test_eq!(rdfa_prefix_same_iri, rdfa(concat!(
+            "<p prefix=\"a: http://x/ b: http://x/\" typeof=\"b:T\"></p>")),
+        vec!("{\"type\":[\"http://x/T\"],\"properties\":{}}".to_string()))
+
+    test_eq!(rdfa_document, rdfa("<meta property=\"og:title\" content=\"T\">"),
+        vec!(concat!(
+            "{\"id\":\"http://example.com/page\",",
+            "\"properties\":{\"http://ogp.me/ns#title\":[\"T\"]}}").to_string()))
+
+    #[test]
+    fn json_ld() {
+        let d = data(concat!(
+            "<script type=\"application/ld+json\">",
+            "{\"@type\": \"Thing\", \"n\": [1, 2.5, true, null, \"a\\u00e9\\n\"]}",
+            "</script>",
+            "<script type=\"Application/LD+JSON \">{bad</script>",
+            "<script>{\"not\": \"json-ld\"}</script>"));
+        assert_eq!(d.json_ld.len(), 2);
+        match *d.json_ld.get(0) {
+            Ok(ref v) => assert_eq!(v.to_json(),
+                "{\"@type\":\"Thing\",\"n\":[1,2.5,true,null,\"aé\\n\"]}".to_string()),
+            Err(ref e) => fail!("{}", e),
+        }
+        assert!(d.json_ld.get(1).is_err());
+    }
+
+    test_eq!(json_values, parse_json(" [null, false, -1.5e2, \"\\ud83d\\ude00\", {}] "),
+        Ok(List(vec!(Null, Bool(false), Number(-150.0), Str("\U0001f600".to_string()), Object(vec!())))))
+    test_eq!(json_trailing, parse_json("1 2").is_err(), true)
+    test_eq!(json_unterminated, parse_json("[\"a").is_err(), true)
+
+    #[test]
+    fn json_too_deep() {
+        let deep = String::from_char(100000, '[');
+        assert!(parse_json(deep.as_slice()).is_err());
+
+        let ok = format!("{}{}", String::from_char(100, '['), String::from_char(100, ']'));
+        assert!(parse_json(ok.as_slice()).is_ok());
+    }
+}
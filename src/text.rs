@@ -0,0 +1,248 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Rendering a parsed document as plain text.
+//!
+//! This approximates the DOM `innerText` algorithm, using the default
+//! styles of HTML elements since we don't have CSS.  Block-level
+//! elements start new lines, paragraphs are separated by a blank line,
+//! table cells are separated by tabs, and whitespace is collapsed except
+//! inside `<pre>` and friends.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let dom: RcDom = parse(one_input(my_str), Default::default());
+//! index(inner_text(&dom.document));
+//! ```
+
+use atom::Atom;
+use sink::rcdom::{Handle, NodeHandle, Document, Doctype, Text, Comment, Element};
+use util::str::is_ascii_whitespace;
+
+use std::cmp::max;
+
+// Elements which aren't rendered at all.
+fn is_hidden(name: &Atom) -> bool {
+    match *name {
+        atom!(head) | atom!(title) | atom!(meta) | atom!(link) | atom!(base)
+        | atom!(script) | atom!(style) | atom!(template) | atom!(noscript)
+        | atom!(iframe) | atom!(noembed) | atom!(noframes) | atom!(param)
+        | atom!(rp) | atom!(area) | atom!(map)
+            => true,
+        _ => false,
+    }
+}
+
+// Elements which are `display: block` (or similar) by default, and so
+// start and end a line.
+fn is_block(name: &Atom) -> bool {
+    match *name {
+        atom!(html) | atom!(body) | atom!(address) | atom!(article) | atom!(aside)
+        | atom!(blockquote) | atom!(center) | atom!(dd) | atom!(details)
+        | atom!(dialog) | atom!(dir) | atom!(div) | atom!(dl) | atom!(dt)
+        | atom!(fieldset) | atom!(figcaption) | atom!(figure) | atom!(footer)
+        | atom!(form) | atom!(h1) | atom!(h2) | atom!(h3) | atom!(h4)
+        | atom!(h5) | atom!(h6) | atom!(header) | atom!(hgroup) | atom!(hr)
+        | atom!(legend) | atom!(li) | atom!(listing) | atom!(main) | atom!(menu)
+        | atom!(nav) | atom!(ol) | atom!(plaintext) | atom!(pre) | atom!(section)
+        | atom!(summary) | atom!(ul) | atom!(xmp)
+        | atom!(table) | atom!(caption) | atom!(thead) | atom!(tbody)
+        | atom!(tfoot) | atom!(tr)
+            => true,
+        _ => false,
+    }
+}
+
+// Elements with `white-space: pre` by default.
+fn preserves_whitespace(name: &Atom) -> bool {
+    match *name {
+        atom!(pre) | atom!(listing) | atom!(plaintext) | atom!(xmp) | atom!(textarea)
+            => true,
+        _ => false,
+    }
+}
+
+fn is_cell(node: &Handle) -> bool {
+    match node.borrow().node {
+        Element(atom!(td), _) | Element(atom!(th), _) => true,
+        _ => false,
+    }
+}
+
+struct Renderer {
+    out: String,
+
+    /// Line breaks required before the next text, as in the "required
+    /// line break count" of the `innerText` algorithm.  Adjacent
+    /// requirements are merged by taking the largest.
+    pending_breaks: uint,
+
+    /// Collapsed whitespace to write before the next text, unless that
+    /// text starts a line.
+    pending_space: bool,
+
+    /// Number of open elements which preserve whitespace.
+    pre_depth: uint,
+}
+
+impl Renderer {
+    fn require_breaks(&mut self, n: uint) {
+        self.pending_breaks = max(self.pending_breaks, n);
+        self.pending_space = false;
+    }
+
+    // Write some text, after any pending line breaks or space.
+    // Nothing is written before the first text.
+    fn write(&mut self, s: &str) {
+        if !self.out.is_empty() {
+            if self.pending_breaks > 0 {
+                for _ in range(0, self.pending_breaks) {
+                    self.out.push_char('\n');
+                }
+            } else if self.pending_space && !self.out.as_slice().ends_with("\n")
+                    && !self.out.as_slice().ends_with("\t") {
+                self.out.push_char(' ');
+            }
+        }
+        self.pending_breaks = 0;
+        self.pending_space = false;
+        self.out.push_str(s);
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.pre_depth > 0 {
+            if !text.is_empty() {
+                self.write(text);
+            }
+            return;
+        }
+
+        if text.chars().next().map_or(false, is_ascii_whitespace) {
+            self.pending_space = true;
+        }
+        let mut first = true;
+        for word in text.split(is_ascii_whitespace).filter(|w| !w.is_empty()) {
+            if !first {
+                self.pending_space = true;
+            }
+            self.write(word);
+            first = false;
+        }
+        if text.chars().last().map_or(false, is_ascii_whitespace) {
+            self.pending_space = true;
+        }
+    }
+
+    fn node(&mut self, node: &Handle) {
+        let name = match node.borrow().node {
+            Text(ref t) => return self.text(t.as_slice()),
+            Comment(_) | Doctype(..) => return,
+            Document => None,
+            Element(ref name, _) => Some(name.clone()),
+        };
+
+        let name = match name {
+            None => return self.children(node),
+            Some(name) => name,
+        };
+
+        if is_hidden(&name) || node.get_attribute("hidden").is_some() {
+            return;
+        }
+
+        match name {
+            atom!(br) => {
+                // A line break of its own, not merged with others.
+                // Whitespace before it is dropped, as at the end of any line.
+                self.pending_space = false;
+                self.write("\n");
+                return;
+            }
+            atom!(p) => self.require_breaks(2),
+            _ if is_block(&name) => self.require_breaks(1),
+            _ => (),
+        }
+
+        let pre = preserves_whitespace(&name);
+        if pre {
+            self.pre_depth += 1;
+        }
+        self.children(node);
+        if pre {
+            self.pre_depth -= 1;
+        }
+
+        match name {
+            atom!(p) => self.require_breaks(2),
+            atom!(td) | atom!(th) => {
+                let more_cells = node.next_sibling().map_or(false, |n| is_cell(&n));
+                if more_cells {
+                    self.write("\t");
+                }
+            }
+            _ if is_block(&name) => self.require_breaks(1),
+            _ => (),
+        }
+    }
+
+    fn children(&mut self, node: &Handle) {
+        for child in node.borrow().children.iter() {
+            self.node(child);
+        }
+    }
+}
+
+/// Render a node and its descendants as plain text, approximating the
+/// DOM `innerText` algorithm.
+pub fn inner_text(node: &Handle) -> String {
+    let mut r = Renderer {
+        out: String::new(),
+        pending_breaks: 0,
+        pending_space: false,
+        pre_depth: 0,
+    };
+    r.node(node);
+    r.out
+}
+
+#[cfg(test)]
+mod test {
+    use super::inner_text;
+
+    use driver::{parse, one_input};
+    use sink::rcdom::RcDom;
+
+    use std::default::Default;
+
+    fn text(html: &str) -> String {
+        let dom: RcDom = parse(one_input(html.to_string()), Default::default());
+        inner_text(&dom.document)
+    }
+
+    test_eq!(collapse, text("  a  \n b\t<b> c </b> d "), "a b c d".to_string())
+    test_eq!(inline_join, text("a<b>b</b>c <i>d</i>"), "abc d".to_string())
+    test_eq!(blocks, text("a<div>b</div>c"), "a\nb\nc".to_string())
+    test_eq!(nested_blocks, text("<div><div>a</div></div><div>b</div>"), "a\nb".to_string())
+    test_eq!(paragraphs, text("<p>a</p><p>b</p>c"), "a\n\nb\n\nc".to_string())
+    test_eq!(p_in_div, text("<div>a<p>b</p></div>c"), "a\n\nb\n\nc".to_string())
+    test_eq!(br, text("a<br>b<br><br>c"), "a\nb\n\nc".to_string())
+    test_eq!(br_space, text("a <br> b"), "a\nb".to_string())
+    test_eq!(pre, text("a <pre>  b\n   c </pre> d"), "a\n  b\n   c \nd".to_string())
+    test_eq!(skipped, text("<title>t</title><style>s</style>a<script>x</script><noscript>y</noscript>b"),
+        "ab".to_string())
+    test_eq!(block_then_br, text("<div>a</div><br>b"), "a\n\nb".to_string())
+    test_eq!(hidden, text("a<span hidden>b</span><div hidden>c</div>d"), "ad".to_string())
+    test_eq!(list, text("<ul><li>a</li><li>b <b>c</b></li></ul>"), "a\nb c".to_string())
+    test_eq!(table, text("<table><tr><td>a</td><td>b</td></tr><tr><th>c<td>d</table>"),
+        "a\tb\nc\td".to_string())
+    test_eq!(empty_cell, text("<table><tr><td></td><td>b</td></tr></table>"), "\tb".to_string())
+    test_eq!(headings, text("<h1>Title</h1>Text"), "Title\nText".to_string())
+    test_eq!(empty, text("<div> </div>"), "".to_string())
+}
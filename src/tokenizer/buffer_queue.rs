@@ -62,6 +62,12 @@ impl BufferQueue {
         }
     }
 
+    /// Remove all buffers, keeping the queue itself.
+    pub fn clear(&mut self) {
+        self.buffers.clear();
+        self.available = 0;
+    }
+
     /// Add a buffer to the beginning of the queue.
     pub fn push_front(&mut self, buf: String) {
         if buf.len() == 0 {
@@ -250,6 +256,22 @@ mod test {
         assert_eq!(bq.next(), None);
     }
 
+    #[test]
+    fn can_clear() {
+        let mut bq = BufferQueue::new();
+        bq.push_back("abc".to_string(), 0);
+        bq.push_back("def".to_string(), 0);
+        assert_eq!(bq.next(), Some('a'));
+
+        bq.clear();
+        assert_eq!(bq.has(1), false);
+        assert_eq!(bq.next(), None);
+
+        bq.push_back("x".to_string(), 0);
+        assert_eq!(bq.next(), Some('x'));
+        assert_eq!(bq.next(), None);
+    }
+
     #[test]
     fn count_bytes_test() {
         for &c in ['&', '\0'].iter() {
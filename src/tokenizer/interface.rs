@@ -8,7 +8,7 @@
 // except according to those terms.
 
 use std::string::String;
-use util::atom::Atom;
+use atom::Atom;
 use tokenizer::states;
 
 /// A `DOCTYPE` token.
@@ -33,7 +33,7 @@ impl Doctype {
 }
 
 /// Attribute name; will eventually support namespaces.
-#[deriving(PartialEq, Eq, PartialOrd, Ord, Clone, Show)]
+#[deriving(PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Show)]
 pub struct AttrName {
     pub name: Atom,
 }
@@ -114,3 +114,15 @@ pub trait TokenSink {
         None
     }
 }
+
+/// A borrowed sink is also a sink, so a `Tokenizer` can either own
+/// its sink or borrow it.
+impl<'a, T: TokenSink> TokenSink for &'a mut T {
+    fn process_token(&mut self, token: Token) {
+        (**self).process_token(token)
+    }
+
+    fn query_state_change(&mut self) -> Option<states::State> {
+        (**self).query_state_change()
+    }
+}
@@ -25,7 +25,7 @@ use self::char_ref::{CharRef, CharRefTokenizer};
 use self::buffer_queue::{BufferQueue, SetResult, FromSet, NotFromSet};
 
 use util::str::{lower_ascii, lower_ascii_letter, empty_str};
-use util::atom::Atom;
+use atom::Atom;
 use util::bitset::Bitset64;
 
 use std::ascii::StrAsciiExt;
@@ -35,7 +35,7 @@ use std::default::Default;
 
 use time::precise_time_ns;
 
-use std::collections::hashmap::HashMap;
+use std::collections::hashmap::{HashMap, HashSet};
 
 pub mod states;
 mod interface;
@@ -57,6 +57,37 @@ fn append_strings(lhs: &mut String, rhs: String) {
     }
 }
 
+// Push a character, unless that would make the string longer than
+// `limit` bytes.  Returns false if the character was dropped.
+fn push_char_limited(buf: &mut String, c: char, limit: Option<uint>) -> bool {
+    match limit {
+        Some(max) if buf.len() + c.len_utf8_bytes() > max => false,
+        _ => {
+            buf.push_char(c);
+            true
+        }
+    }
+}
+
+// Append as much of `rhs` as fits in `limit` bytes, without splitting
+// a character.  Returns false if anything was dropped.
+fn append_limited(lhs: &mut String, rhs: &str, limit: Option<uint>) -> bool {
+    match limit {
+        Some(max) if lhs.len() + rhs.len() > max => {
+            let mut n = if lhs.len() >= max { 0 } else { max - lhs.len() };
+            while !rhs.is_char_boundary(n) {
+                n -= 1;
+            }
+            lhs.push_str(rhs.slice_to(n));
+            false
+        }
+        _ => {
+            lhs.push_str(rhs);
+            true
+        }
+    }
+}
+
 /// Tokenizer options, with an impl for `Default`.
 #[deriving(Clone)]
 pub struct TokenizerOpts {
@@ -79,6 +110,22 @@ pub struct TokenizerOpts {
     /// Last start tag.  Only the test runner should use a
     /// non-`None` value!
     pub last_start_tag_name: Option<String>,
+
+    /// Maximum number of attributes on a tag.  Further attributes
+    /// are dropped.  Default: no limit
+    pub max_attrs_per_tag: Option<uint>,
+
+    /// Maximum length in bytes of an attribute name or value.  Longer
+    /// ones are truncated.  Default: no limit
+    pub max_attr_len: Option<uint>,
+
+    /// Maximum length in bytes of a comment.  Longer comments are
+    /// truncated.  Default: no limit
+    pub max_comment_len: Option<uint>,
+
+    /// Maximum length in bytes of character data between two tags or
+    /// comments.  The rest is dropped.  Default: no limit
+    pub max_text_len: Option<uint>,
 }
 
 impl Default for TokenizerOpts {
@@ -89,17 +136,21 @@ impl Default for TokenizerOpts {
             profile: false,
             initial_state: None,
             last_start_tag_name: None,
+            max_attrs_per_tag: None,
+            max_attr_len: None,
+            max_comment_len: None,
+            max_text_len: None,
         }
     }
 }
 
 /// The HTML tokenizer.
-pub struct Tokenizer<'sink, Sink> {
+pub struct Tokenizer<Sink> {
     /// Options controlling the behavior of the tokenizer.
     opts: TokenizerOpts,
 
     /// Destination for tokens we emit.
-    sink: &'sink mut Sink,
+    sink: Sink,
 
     /// The abstract machine state as described in the spec.
     state: states::State,
@@ -145,6 +196,9 @@ pub struct Tokenizer<'sink, Sink> {
     /// Current tag attributes.
     current_tag_attrs: Vec<Attribute>,
 
+    /// Names of the current tag's attributes, for finding duplicates.
+    current_tag_attr_names: HashSet<Atom>,
+
     /// Current attribute name.
     current_attr_name: String,
 
@@ -165,12 +219,24 @@ pub struct Tokenizer<'sink, Sink> {
 
     /// Record of how many ns we spent in each state, if profiling is enabled.
     state_profile: HashMap<states::State, u64>,
+
+    /// Bytes of character data emitted since the last tag or comment.
+    text_len: uint,
+
+    /// Have we reported truncation of the current attribute, tag,
+    /// comment, or run of text?  Each limit is reported at most once
+    /// per item.
+    attr_truncated: bool,
+    attrs_dropped: bool,
+    comment_truncated: bool,
+    text_truncated: bool,
 }
 
-impl<'sink, Sink: TokenSink> Tokenizer<'sink, Sink> {
+impl<Sink: TokenSink> Tokenizer<Sink> {
     /// Create a new tokenizer which feeds tokens to a particular `TokenSink`.
-    pub fn new(sink: &'sink mut Sink, mut opts: TokenizerOpts) -> Tokenizer<'sink, Sink> {
-        let start_tag_name = opts.last_start_tag_name.take().map(|s| Atom::from_buf(s));
+    pub fn new(sink: Sink, opts: TokenizerOpts) -> Tokenizer<Sink> {
+        let start_tag_name = opts.last_start_tag_name.as_ref()
+            .map(|s| Atom::from_str(s.as_slice()));
         let state = *opts.initial_state.as_ref().unwrap_or(&states::Data);
         let discard_bom = opts.discard_bom;
         Tokenizer {
@@ -189,6 +255,7 @@ impl<'sink, Sink: TokenSink> Tokenizer<'sink, Sink> {
             current_tag_name: empty_str(),
             current_tag_self_closing: false,
             current_tag_attrs: vec!(),
+            current_tag_attr_names: HashSet::new(),
             current_attr_name: empty_str(),
             current_attr_value: empty_str(),
             current_comment: empty_str(),
@@ -196,9 +263,58 @@ impl<'sink, Sink: TokenSink> Tokenizer<'sink, Sink> {
             last_start_tag_name: start_tag_name,
             temp_buf: empty_str(),
             state_profile: HashMap::new(),
+            text_len: 0,
+            attr_truncated: false,
+            attrs_dropped: false,
+            comment_truncated: false,
+            text_truncated: false,
         }
     }
 
+    /// Get a mutable reference to the sink.
+    pub fn sink<'a>(&'a mut self) -> &'a mut Sink {
+        &mut self.sink
+    }
+
+    /// Consume the tokenizer and return the sink.
+    pub fn into_sink(self) -> Sink {
+        self.sink
+    }
+
+    /// Return to the initial state, ready to tokenize a new document.
+    ///
+    /// Buffers and tables are cleared rather than reallocated, so a
+    /// tokenizer can be reused for many small documents.
+    pub fn reset(&mut self) {
+        self.state = *self.opts.initial_state.as_ref().unwrap_or(&states::Data);
+        self.input_buffers.clear();
+        self.wait_for = None;
+        self.at_eof = false;
+        self.char_ref_tokenizer = None;
+        self.current_char = '\0';
+        self.reconsume = false;
+        self.ignore_lf = false;
+        self.discard_bom = self.opts.discard_bom;
+        self.current_tag_kind = StartTag;
+        self.current_tag_name.truncate(0);
+        self.current_tag_self_closing = false;
+        self.current_tag_attrs.truncate(0);
+        self.current_tag_attr_names.clear();
+        self.current_attr_name.truncate(0);
+        self.current_attr_value.truncate(0);
+        self.current_comment.truncate(0);
+        self.current_doctype = Doctype::new();
+        self.last_start_tag_name = self.opts.last_start_tag_name.as_ref()
+            .map(|s| Atom::from_str(s.as_slice()));
+        self.temp_buf.truncate(0);
+        self.state_profile.clear();
+        self.text_len = 0;
+        self.attr_truncated = false;
+        self.attrs_dropped = false;
+        self.comment_truncated = false;
+        self.text_truncated = false;
+    }
+
     /// Feed an input string into the tokenizer.
     pub fn feed(&mut self, input: String) {
         if input.len() == 0 {
@@ -339,7 +455,35 @@ impl<'sink, Sink: TokenSink> Tokenizer<'sink, Sink> {
         self.emit_error(msg);
     }
 
+    // Account for `n` more bytes of text, and return how many of them
+    // fit within `max_text_len`.
+    fn text_room(&mut self, n: uint) -> uint {
+        let max = match self.opts.max_text_len {
+            None => return n,
+            Some(max) => max,
+        };
+
+        let room = if self.text_len >= max { 0 } else { max - self.text_len };
+        if n > room && !self.text_truncated {
+            self.text_truncated = true;
+            self.emit_error("Text too long, truncating".to_string());
+        }
+        let n = if n > room { room } else { n };
+        self.text_len += n;
+        n
+    }
+
+    fn reset_text_len(&mut self) {
+        self.text_len = 0;
+        self.text_truncated = false;
+    }
+
     fn emit_char(&mut self, c: char) {
+        let len = c.len_utf8_bytes();
+        if self.text_room(len) < len {
+            return;
+        }
+
         self.sink.process_token(match c {
             '\0' => NullCharacterToken,
             _ => CharacterTokens(String::from_char(1, c)),
@@ -347,12 +491,24 @@ impl<'sink, Sink: TokenSink> Tokenizer<'sink, Sink> {
     }
 
     // The string must not contain '\0'!
-    fn emit_chars(&mut self, b: String) {
+    fn emit_chars(&mut self, mut b: String) {
+        let mut n = self.text_room(b.len());
+        if n < b.len() {
+            while !b.as_slice().is_char_boundary(n) {
+                n -= 1;
+            }
+            b.truncate(n);
+        }
+
+        if b.is_empty() {
+            return;
+        }
         self.sink.process_token(CharacterTokens(b));
     }
 
     fn emit_current_tag(&mut self) {
         self.finish_attribute();
+        self.reset_text_len();
 
         let name = Atom::take_from_buf(&mut self.current_tag_name);
 
@@ -397,14 +553,42 @@ impl<'sink, Sink: TokenSink> Tokenizer<'sink, Sink> {
     }
 
     fn emit_current_comment(&mut self) {
+        self.reset_text_len();
+        self.comment_truncated = false;
         self.sink.process_token(CommentToken(
             replace(&mut self.current_comment, empty_str())));
     }
 
+    fn clear_comment(&mut self) {
+        self.current_comment.truncate(0);
+        self.comment_truncated = false;
+    }
+
+    fn comment_too_long(&mut self) {
+        if !self.comment_truncated {
+            self.comment_truncated = true;
+            self.emit_error("Comment too long, truncating".to_string());
+        }
+    }
+
+    fn push_comment(&mut self, c: char) {
+        if !push_char_limited(&mut self.current_comment, c, self.opts.max_comment_len) {
+            self.comment_too_long();
+        }
+    }
+
+    fn append_comment(&mut self, s: &str) {
+        if !append_limited(&mut self.current_comment, s, self.opts.max_comment_len) {
+            self.comment_too_long();
+        }
+    }
+
     fn discard_tag(&mut self) {
         self.current_tag_name = String::new();
         self.current_tag_self_closing = false;
         self.current_tag_attrs = vec!();
+        self.current_tag_attr_names.clear();
+        self.attrs_dropped = false;
     }
 
     fn create_tag(&mut self, kind: TagKind, c: char) {
@@ -425,7 +609,35 @@ impl<'sink, Sink: TokenSink> Tokenizer<'sink, Sink> {
     fn create_attribute(&mut self, c: char) {
         self.finish_attribute();
 
-        self.current_attr_name.push_char(c);
+        self.attr_truncated = false;
+        self.push_attr_name(c);
+    }
+
+    fn attr_too_long(&mut self) {
+        if !self.attr_truncated {
+            self.attr_truncated = true;
+            self.emit_error("Attribute too long, truncating".to_string());
+        }
+    }
+
+    fn push_attr_name(&mut self, c: char) {
+        if !push_char_limited(&mut self.current_attr_name, c, self.opts.max_attr_len) {
+            self.attr_too_long();
+        }
+    }
+
+    fn push_attr_value(&mut self, c: char) {
+        if !push_char_limited(&mut self.current_attr_value, c, self.opts.max_attr_len) {
+            self.attr_too_long();
+        }
+    }
+
+    fn append_attr_value(&mut self, s: String) {
+        if self.opts.max_attr_len.is_none() {
+            append_strings(&mut self.current_attr_value, s);
+        } else if !append_limited(&mut self.current_attr_value, s.as_slice(), self.opts.max_attr_len) {
+            self.attr_too_long();
+        }
     }
 
     fn finish_attribute(&mut self) {
@@ -433,21 +645,30 @@ impl<'sink, Sink: TokenSink> Tokenizer<'sink, Sink> {
             return;
         }
 
-        // Check for a duplicate attribute.
-        // FIXME: the spec says we should error as soon as the name is finished.
-        // FIXME: linear time search, do we care?
-        let dup = {
-            let name = self.current_attr_name.as_slice();
-            self.current_tag_attrs.iter().any(|a| a.name.as_slice() == name)
+        let too_many = match self.opts.max_attrs_per_tag {
+            Some(max) => self.current_tag_attrs.len() >= max,
+            None => false,
         };
 
-        if dup {
-            self.emit_error("Duplicate attribute".to_string());
+        if too_many {
+            if !self.attrs_dropped {
+                self.attrs_dropped = true;
+                self.emit_error("Too many attributes, dropping the rest".to_string());
+            }
             self.current_attr_name.truncate(0);
             self.current_attr_value.truncate(0);
+            return;
+        }
+
+        // Check for a duplicate attribute.
+        // FIXME: the spec says we should error as soon as the name is finished.
+        let name = Atom::take_from_buf(&mut self.current_attr_name);
+        if !self.current_tag_attr_names.insert(name.clone()) {
+            self.emit_error("Duplicate attribute".to_string());
+            self.current_attr_value.truncate(0);
         } else {
             self.current_tag_attrs.push(Attribute {
-                name: AttrName::new(Atom::take_from_buf(&mut self.current_attr_name)),
+                name: AttrName::new(name),
                 value: replace(&mut self.current_attr_value, empty_str()),
             });
         }
@@ -516,13 +737,13 @@ macro_rules! shorthand (
     ( emit_temp                       ) => ( self.emit_temp_buf();                                 );
     ( clear_temp                      ) => ( self.clear_temp_buf();                                );
     ( create_attr $c:expr             ) => ( self.create_attribute($c);                            );
-    ( push_name $c:expr               ) => ( self.current_attr_name.push_char($c);                 );
-    ( push_value $c:expr              ) => ( self.current_attr_value.push_char($c);                );
-    ( append_value $c:expr            ) => ( append_strings(&mut self.current_attr_value, $c);     );
-    ( push_comment $c:expr            ) => ( self.current_comment.push_char($c);                   );
-    ( append_comment $c:expr          ) => ( self.current_comment.push_str($c);                    );
+    ( push_name $c:expr               ) => ( self.push_attr_name($c);                              );
+    ( push_value $c:expr              ) => ( self.push_attr_value($c);                             );
+    ( append_value $c:expr            ) => ( self.append_attr_value($c);                           );
+    ( push_comment $c:expr            ) => ( self.push_comment($c);                                );
+    ( append_comment $c:expr          ) => ( self.append_comment($c);                              );
     ( emit_comment                    ) => ( self.emit_current_comment();                          );
-    ( clear_comment                   ) => ( self.current_comment.truncate(0);                     );
+    ( clear_comment                   ) => ( self.clear_comment();                                 );
     ( create_doctype                  ) => ( self.current_doctype = Doctype::new();                );
     ( push_doctype_name $c:expr       ) => ( option_push_char(&mut self.current_doctype.name, $c); );
     ( push_doctype_id $k:expr $c:expr ) => ( option_push_char(self.doctype_id($k), $c);            );
@@ -611,7 +832,7 @@ macro_rules! lookahead_and_consume ( ($n:expr, $pred:expr) => (
     }
 ))
 
-impl<'sink, Sink: TokenSink> Tokenizer<'sink, Sink> {
+impl<Sink: TokenSink> Tokenizer<Sink> {
     // Run the state machine for a while.
     // Return true if we should be immediately re-invoked
     // (this just simplifies control flow vs. break / continue).
@@ -1309,6 +1530,7 @@ impl<'sink, Sink: TokenSink> Tokenizer<'sink, Sink> {
 #[allow(non_snake_case_functions)]
 mod test {
     use super::{option_push_char, append_strings}; // private items
+    use super::{push_char_limited, append_limited};
 
     #[test]
     fn push_to_None_gives_singleton() {
@@ -1350,4 +1572,24 @@ mod test {
         let ptr_new = lhs.into_bytes().get(0) as *const u8;
         assert_eq!(ptr_old, ptr_new);
     }
+
+    #[test]
+    fn push_limited() {
+        let mut s = "ab".to_string();
+        assert!(push_char_limited(&mut s, 'c', Some(3)));
+        assert!(!push_char_limited(&mut s, 'd', Some(3)));
+        assert!(push_char_limited(&mut s, 'd', None));
+        assert_eq!(s, "abcd".to_string());
+    }
+
+    #[test]
+    fn append_limited_keeps_whole_chars() {
+        let mut s = "a".to_string();
+        assert!(!append_limited(&mut s, "b\u2603c", Some(4)));
+        assert_eq!(s, "ab".to_string());
+        assert!(!append_limited(&mut s, "x", Some(2)));
+        assert_eq!(s, "ab".to_string());
+        assert!(append_limited(&mut s, "x", Some(3)));
+        assert_eq!(s, "abx".to_string());
+    }
 }
@@ -14,14 +14,14 @@
 
 use tree_builder::types::*;
 use tree_builder::tag_sets::*;
-use tree_builder::interface::{TreeSink, QuirksMode};
+use tree_builder::interface::{TreeSink, QuirksMode, QuirksReason};
 use tree_builder::rules::TreeBuilderStep;
 
-use tokenizer::{Attribute, Tag};
+use tokenizer::{Attribute, Tag, EndTag};
 use tokenizer::states::{RawData, RawKind};
 
-use util::atom::Atom;
-use util::namespace::{Namespace, HTML};
+use atom::Atom;
+use atom::namespace::{Namespace, HTML};
 use util::str::to_escaped_string;
 
 use std::mem::replace;
@@ -42,6 +42,11 @@ impl<'a, Handle> Iterator<(uint, &'a Handle, &'a Tag)> for ActiveFormattingIter<
     }
 }
 
+enum Bookmark<Handle> {
+    Replace(Handle),
+    InsertAfter(Handle),
+}
+
 pub enum PushFlag {
     Push,
     NoPush,
@@ -82,19 +87,25 @@ pub trait TreeBuilderActions<Handle> {
     fn pop(&mut self) -> Handle;
     fn push(&mut self, elem: &Handle);
     fn adoption_agency(&mut self, subject: Atom);
+    fn process_end_tag_in_body(&mut self, tag: Tag);
+    fn in_stack(&self, elem: &Handle) -> bool;
+    fn position_in_active_formatting(&self, elem: &Handle) -> Option<uint>;
+    fn create_element_for(&mut self, tag: &Tag) -> Handle;
     fn target(&self) -> Handle;
+    fn element_target(&mut self) -> Handle;
+    fn insert_appropriately(&mut self, target: Handle, elem: Handle);
     fn current_node_in(&self, set: TagSet) -> bool;
     fn current_node(&self) -> Handle;
     fn parse_raw_data(&mut self, tag: Tag, k: RawKind);
     fn to_raw_text_mode(&mut self, k: RawKind);
     fn stop_parsing(&mut self) -> ProcessResult;
-    fn set_quirks_mode(&mut self, mode: QuirksMode);
+    fn set_quirks_mode(&mut self, mode: QuirksMode, reason: QuirksReason);
     fn active_formatting_end_to_marker<'a>(&'a self) -> ActiveFormattingIter<'a, Handle>;
 }
 
 #[doc(hidden)]
-impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
-    TreeBuilderActions<Handle> for super::TreeBuilder<'sink, Handle, Sink> {
+impl<Handle: Clone, Sink: TreeSink<Handle>>
+    TreeBuilderActions<Handle> for super::TreeBuilder<Handle, Sink> {
 
     /// Iterate over the active formatting elements (with index in the list) from the end
     /// to the last marker, or the beginning if there are no markers.
@@ -104,8 +115,9 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
         }
     }
 
-    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+    fn set_quirks_mode(&mut self, mode: QuirksMode, reason: QuirksReason) {
         self.quirks_mode = mode;
+        self.quirks_reason = Some(reason);
         self.sink.set_quirks_mode(mode);
     }
 
@@ -147,12 +159,289 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
         self.current_node()
     }
 
+    // Where to insert a new element, taking `max_depth` into account.
+    fn element_target(&mut self) -> Handle {
+        match self.opts.max_depth {
+            Some(max) if self.open_elems.len() >= max => {
+                self.sink.parse_error("Elements nested too deeply".to_string());
+                // Close the elements at the limit, so that the stack of
+                // open elements stays bounded too.  They won't be
+                // reconstructed, and the insertion mode follows what's
+                // still open.
+                while self.open_elems.len() >= max {
+                    let elem = self.pop();
+                    match self.position_in_active_formatting(&elem) {
+                        Some(i) => { self.active_formatting.remove(i); }
+                        None => (),
+                    }
+                }
+                self.mode = self.reset_insertion_mode();
+                self.target()
+            }
+            _ => self.target(),
+        }
+    }
+
+    // Insert an element at the "appropriate place for inserting a node",
+    // with `target` as the override target.
+    // FIXME: `target()` should use this too, once text and comments can be
+    // foster parented.
+    fn insert_appropriately(&mut self, target: Handle, elem: Handle) {
+        if !(self.foster_parenting && self.elem_in(target.clone(), table_outer)) {
+            self.sink.append_element(target, elem);
+            return;
+        }
+
+        let table = self.open_elems.iter()
+            .rposition(|e| self.html_elem_named(e.clone(), atom!(table)));
+        let table_index = match table {
+            Some(i) => i,
+            None => {
+                // FIXME: fragment case; there's no <table> otherwise.
+                let html = self.html_elem();
+                self.sink.append_element(html, elem);
+                return;
+            }
+        };
+
+        let table = self.open_elems.get(table_index).clone();
+        match self.sink.append_before_sibling(table, elem) {
+            Ok(()) => (),
+            Err(elem) => {
+                let previous = self.open_elems.get(table_index - 1).clone();
+                self.sink.append_element(previous, elem);
+            }
+        }
+    }
+
+    fn in_stack(&self, elem: &Handle) -> bool {
+        self.open_elems.iter().any(|x| self.sink.same_node(elem.clone(), x.clone()))
+    }
+
+    fn position_in_active_formatting(&self, elem: &Handle) -> Option<uint> {
+        self.active_formatting.iter().position(|e| match *e {
+            Element(ref h, _) => self.sink.same_node(elem.clone(), h.clone()),
+            Marker => false,
+        })
+    }
+
+    // Create an element for a token, without inserting it.
+    fn create_element_for(&mut self, tag: &Tag) -> Handle {
+        self.node_count += 1;
+        self.sink.create_element(HTML, tag.name.clone(), tag.attrs.clone())
+    }
+
+    //§ adoption-agency-algorithm
     fn adoption_agency(&mut self, subject: Atom) {
-        // FIXME: this is not right
-        if self.current_node_named(subject) {
-            self.pop();
+        // 2.
+        if self.current_node_named(subject.clone()) {
+            let current = self.current_node();
+            if self.position_in_active_formatting(&current).is_none() {
+                self.pop();
+                return;
+            }
+        }
+
+        // 3-5.
+        for _ in range(0u, 8) {
+            // 6.
+            let fmt_elem = self.active_formatting_end_to_marker()
+                .filter(|&(_, _, tag)| tag.name == subject)
+                .next()
+                .map(|(i, h, t)| (i, h.clone(), t.clone()));
+
+            let (fmt_elem_index, fmt_elem, fmt_elem_tag) = unwrap_or_return!(fmt_elem,
+                self.process_end_tag_in_body(Tag {
+                    kind: EndTag,
+                    name: subject,
+                    self_closing: false,
+                    attrs: vec!(),
+                })
+            );
+
+            let fmt_elem_stack_index = self.open_elems.iter()
+                .rposition(|n| self.sink.same_node(n.clone(), fmt_elem.clone()));
+
+            let fmt_elem_stack_index = unwrap_or_return!(fmt_elem_stack_index, {
+                // 7.
+                self.sink.parse_error("Formatting element not open".to_string());
+                self.active_formatting.remove(fmt_elem_index);
+            });
+
+            // 8.
+            if !self.in_scope(default_scope, |n| self.sink.same_node(n.clone(), fmt_elem.clone())) {
+                self.sink.parse_error("Formatting element not in scope".to_string());
+                return;
+            }
+
+            // 9.
+            if !self.sink.same_node(self.current_node(), fmt_elem.clone()) {
+                self.sink.parse_error("Formatting element not current node".to_string());
+            }
+
+            // 10.
+            let furthest_block = self.open_elems.iter().enumerate()
+                .skip(fmt_elem_stack_index + 1)
+                .filter(|&(_, open_element)| self.elem_in(open_element.clone(), special_tag))
+                .next()
+                .map(|(i, h)| (i, h.clone()));
+
+            let (furthest_block_index, furthest_block) = unwrap_or_return!(furthest_block, {
+                // 11.
+                self.open_elems.truncate(fmt_elem_stack_index);
+                self.active_formatting.remove(fmt_elem_index);
+            });
+
+            // 12.
+            let common_ancestor = self.open_elems.get(fmt_elem_stack_index - 1).clone();
+
+            // 13.
+            let mut bookmark = Replace(fmt_elem.clone());
+
+            // 14.
+            let mut node_index = furthest_block_index;
+            let mut last_node = furthest_block.clone();
+
+            // 14.1.
+            let mut inner_counter = 0u;
+            loop {
+                // 14.2.
+                inner_counter += 1;
+
+                // 14.3.
+                node_index -= 1;
+                let node = self.open_elems.get(node_index).clone();
+
+                // 14.4.
+                if self.sink.same_node(node.clone(), fmt_elem.clone()) {
+                    break;
+                }
+
+                // 14.5.
+                let node_formatting_index = self.position_in_active_formatting(&node);
+                let node_formatting_index = match node_formatting_index {
+                    Some(i) if inner_counter > 3 => {
+                        self.active_formatting.remove(i);
+                        None
+                    }
+                    x => x,
+                };
+
+                let node_formatting_index = match node_formatting_index {
+                    Some(i) => i,
+
+                    // 14.6.
+                    None => {
+                        self.open_elems.remove(node_index);
+                        continue;
+                    }
+                };
+
+                // 14.7.
+                let tag = match *self.active_formatting.get(node_formatting_index) {
+                    Element(ref h, ref t) => {
+                        assert!(self.sink.same_node(h.clone(), node.clone()));
+                        t.clone()
+                    }
+                    Marker => fail!("Found marker during adoption agency"),
+                };
+                let new_element = self.create_element_for(&tag);
+                *self.open_elems.get_mut(node_index) = new_element.clone();
+                *self.active_formatting.get_mut(node_formatting_index) = Element(new_element.clone(), tag);
+                let node = new_element;
+
+                // 14.8.
+                if self.sink.same_node(last_node.clone(), furthest_block.clone()) {
+                    bookmark = InsertAfter(node.clone());
+                }
+
+                // 14.9.
+                self.sink.remove_from_parent(last_node.clone());
+                self.sink.append_element(node.clone(), last_node.clone());
+
+                // 14.10.
+                last_node = node.clone();
+
+                // 14.11.
+            }
+
+            // 15.
+            self.sink.remove_from_parent(last_node.clone());
+            self.insert_appropriately(common_ancestor, last_node);
+
+            // 16.
+            let new_element = self.create_element_for(&fmt_elem_tag);
+            let new_entry = Element(new_element.clone(), fmt_elem_tag);
+
+            // 17.
+            self.sink.reparent_children(furthest_block.clone(), new_element.clone());
+
+            // 18.
+            self.sink.append_element(furthest_block.clone(), new_element.clone());
+
+            // 19.
+            match bookmark {
+                Replace(to_replace) => {
+                    let index = self.position_in_active_formatting(&to_replace)
+                        .expect("bookmark not found in active formatting elements");
+                    *self.active_formatting.get_mut(index) = new_entry;
+                }
+                InsertAfter(previous) => {
+                    let index = self.position_in_active_formatting(&previous)
+                        .expect("bookmark not found in active formatting elements") + 1;
+                    self.active_formatting.insert(index, new_entry);
+                    let old_index = self.position_in_active_formatting(&fmt_elem)
+                        .expect("formatting element not found in active formatting elements");
+                    self.active_formatting.remove(old_index);
+                }
+            }
+
+            // 20.
+            self.remove_from_stack(&fmt_elem);
+            let new_furthest_block_index = self.open_elems.iter()
+                .position(|n| self.sink.same_node(n.clone(), furthest_block.clone()))
+                .expect("furthest block missing from open element stack");
+            self.open_elems.insert(new_furthest_block_index + 1, new_element);
+
+            // 21.
         }
     }
+    //§ END
+
+    // The "any other end tag" rule of the "in body" insertion mode,
+    // which the adoption agency algorithm also falls back to.
+    fn process_end_tag_in_body(&mut self, tag: Tag) {
+        // Look back for a matching open element.
+        let mut match_idx = None;
+        for (i, elem) in self.open_elems.iter().enumerate().rev() {
+            if self.html_elem_named(elem.clone(), tag.name.clone()) {
+                match_idx = Some(i);
+                break;
+            }
+
+            if self.elem_in(elem.clone(), special_tag) {
+                self.sink.parse_error(format!("Unexpected token {} in insertion mode {:?}",
+                    to_escaped_string(&tag), self.mode));
+                return;
+            }
+        }
+
+        let match_idx = unwrap_or_return!(match_idx, {
+            // I believe this is impossible, because the root
+            // <html> element is in special_tag.
+            self.sink.parse_error(format!("Unexpected token {} in insertion mode {:?}",
+                    to_escaped_string(&tag), self.mode));
+        });
+
+        self.generate_implied_end_except(tag.name.clone());
+
+        if match_idx != self.open_elems.len() - 1 {
+            // mis-nested tags
+            self.sink.parse_error(format!("Unexpected token {} in insertion mode {:?}",
+                    to_escaped_string(&tag), self.mode));
+        }
+        self.open_elems.truncate(match_idx);
+    }
 
     fn push(&mut self, elem: &Handle) {
         self.open_elems.push(elem.clone());
@@ -169,9 +458,43 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
     }
 
     /// Reconstruct the active formatting elements.
+    //§ reconstruct-the-active-formatting-elements
     fn reconstruct_formatting(&mut self) {
-        // FIXME
+        let last = match self.active_formatting.last() {
+            None | Some(&Marker) => return,
+            Some(&Element(ref h, _)) => h.clone(),
+        };
+        if self.in_stack(&last) {
+            return;
+        }
+
+        // Rewind to just after the last entry which is a marker or
+        // an open element.
+        let len = self.active_formatting.len();
+        let mut entry_index = len - 1;
+        while entry_index > 0 {
+            let stop = match *self.active_formatting.get(entry_index - 1) {
+                Marker => true,
+                Element(ref h, _) => self.in_stack(h),
+            };
+            if stop {
+                break;
+            }
+            entry_index -= 1;
+        }
+
+        // Advance, creating and replacing each entry.
+        for i in range(entry_index, len) {
+            let tag = match *self.active_formatting.get(i) {
+                Element(_, ref t) => t.clone(),
+                Marker => fail!("Found marker during formatting element reconstruction"),
+            };
+
+            let new_element = self.insert_element(Push, tag.name.clone(), tag.attrs.clone());
+            *self.active_formatting.get_mut(i) = Element(new_element, tag);
+        }
     }
+    //§ END
 
     /// Get the first element on the stack, which will be the <html> element.
     fn html_elem(&self) -> Handle {
@@ -328,7 +651,6 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
     }
 
     fn process_chars_in_table(&mut self, token: Token) -> ProcessResult {
-        declare_tag_set!(table_outer = table tbody tfoot thead tr)
         if self.current_node_in(table_outer) {
             assert!(self.pending_table_text.is_empty());
             self.orig_mode = Some(self.mode);
@@ -349,8 +671,18 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
             let last = i == 0u;
             // FIXME: fragment case context element
             match name {
-                // FIXME: <select> sub-steps
-                atom!(select) => return InSelect,
+                atom!(select) => {
+                    if !last {
+                        for ancestor in self.open_elems.slice_to(i).iter().rev() {
+                            if self.html_elem_named(ancestor.clone(), atom!(template)) {
+                                return InSelect;
+                            } else if self.html_elem_named(ancestor.clone(), atom!(table)) {
+                                return InSelectInTable;
+                            }
+                        }
+                    }
+                    return InSelect;
+                }
 
                 atom!(td) | atom!(th) => if !last { return InCell; },
                 atom!(tr) => return InRow,
@@ -383,16 +715,34 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
 
     //§ creating-and-inserting-nodes
     fn create_root(&mut self, attrs: Vec<Attribute>) {
-        let elem = self.sink.create_element(HTML, atom!(html), attrs);
+        let elem = self.sink.create_element(HTML, atom!(html), attrs.clone());
+        self.node_count += 1;
         self.push(&elem);
-        self.sink.append_element(self.doc_handle.clone(), elem);
-        // FIXME: application cache selection algorithm
+        self.sink.append_element(self.doc_handle.clone(), elem.clone());
+        self.sink.root_element_inserted(elem, attrs.as_slice());
     }
 
     fn insert_element(&mut self, push: PushFlag, name: Atom, attrs: Vec<Attribute>)
             -> Handle {
-        let target = self.target();
+        let target = self.element_target();
+
+        // FIXME: check that the target is in the same tree as the form
+        // element pointer, once scripts can move it.
+        let form = match self.form_elem {
+            Some(ref form) if form_associatable_tag((HTML, name.clone()))
+                && !self.open_elems.iter().any(|e| self.html_elem_named(e.clone(), atom!(template)))
+                && !(listed_tag((HTML, name.clone()))
+                    && attrs.iter().any(|a| a.name.name == atom!(form)))
+                => Some(form.clone()),
+            _ => None,
+        };
+
         let elem = self.sink.create_element(HTML, name, attrs);
+        self.node_count += 1;
+        match form {
+            Some(form) => self.sink.associate_with_form(elem.clone(), form),
+            None => (),
+        }
         match push {
             Push => self.push(&elem),
             NoPush => (),
@@ -416,7 +766,7 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
     //§ END
 
     fn create_formatting_element_for(&mut self, tag: Tag) -> Handle {
-        // FIXME: This really wants unit tests.
+        // Noah's Ark clause: at most three matching entries after the last marker.
         let mut first_match = None;
         let mut matches = 0u;
         for (i, _, old_tag) in self.active_formatting_end_to_marker() {
@@ -444,3 +794,259 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use tree_builder::interface::{TreeSink, QuirksMode};
+    use tokenizer::Attribute;
+    use driver::{parse_to, one_input};
+
+    use atom::Atom;
+    use atom::namespace::{Namespace, HTML};
+
+    use std::default::Default;
+    use std::mem::replace;
+
+    enum Node {
+        Elem(Atom, Vec<uint>),
+        Text(String),
+    }
+
+    // Records the tree as a table of nodes, with handles indexing into it.
+    // Node 0 is the document.
+    struct RecordingSink {
+        nodes: Vec<Node>,
+        parents: Vec<Option<uint>>,
+        errors: uint,
+        root: Option<(uint, Vec<Attribute>)>,
+    }
+
+    impl RecordingSink {
+        fn new() -> RecordingSink {
+            RecordingSink {
+                nodes: vec!(Elem(Atom::from_str("#document"), vec!())),
+                parents: vec!(None),
+                errors: 0,
+                root: None,
+            }
+        }
+
+        fn add(&mut self, node: Node) -> uint {
+            self.nodes.push(node);
+            self.parents.push(None);
+            self.nodes.len() - 1
+        }
+
+        fn children<'a>(&'a mut self, node: uint) -> &'a mut Vec<uint> {
+            match *self.nodes.get_mut(node) {
+                Elem(_, ref mut c) => c,
+                Text(_) => fail!("text node has no children"),
+            }
+        }
+
+        fn append(&mut self, parent: uint, child: uint) {
+            assert!(self.parents.get(child).is_none());
+            self.children(parent).push(child);
+            *self.parents.get_mut(child) = Some(parent);
+        }
+
+        fn serialize(&self, node: uint, out: &mut String) {
+            match *self.nodes.get(node) {
+                Text(ref t) => out.push_str(t.as_slice()),
+                Elem(ref name, ref children) => {
+                    out.push_str(format!("<{}>", name.as_slice()).as_slice());
+                    for &c in children.iter() {
+                        self.serialize(c, out);
+                    }
+                    out.push_str(format!("</{}>", name.as_slice()).as_slice());
+                }
+            }
+        }
+    }
+
+    impl TreeSink<uint> for RecordingSink {
+        fn parse_error(&mut self, _msg: String) {
+            self.errors += 1;
+        }
+
+        fn get_document(&mut self) -> uint {
+            0
+        }
+
+        fn same_node(&self, x: uint, y: uint) -> bool {
+            x == y
+        }
+
+        fn elem_name(&self, target: uint) -> (Namespace, Atom) {
+            match *self.nodes.get(target) {
+                Elem(ref name, _) => (HTML, name.clone()),
+                Text(_) => fail!("not an element"),
+            }
+        }
+
+        fn create_element(&mut self, _ns: Namespace, name: Atom, _attrs: Vec<Attribute>) -> uint {
+            self.add(Elem(name, vec!()))
+        }
+
+        fn append_text(&mut self, parent: uint, text: String) {
+            let last = self.children(parent).last().map(|&c| c);
+            match last {
+                Some(c) => match *self.nodes.get_mut(c) {
+                    Text(ref mut t) => {
+                        t.push_str(text.as_slice());
+                        return;
+                    }
+                    _ => (),
+                },
+                None => (),
+            }
+            let child = self.add(Text(text));
+            self.append(parent, child);
+        }
+
+        fn append_element(&mut self, parent: uint, child: uint) {
+            self.append(parent, child);
+        }
+
+        fn append_before_sibling(&mut self, sibling: uint, new_node: uint) -> Result<(), uint> {
+            let parent = unwrap_or_return!(*self.parents.get(sibling), Err(new_node));
+            assert!(self.parents.get(new_node).is_none());
+            let i = self.children(parent).iter().position(|&c| c == sibling).expect("not a child");
+            self.children(parent).insert(i, new_node);
+            *self.parents.get_mut(new_node) = Some(parent);
+            Ok(())
+        }
+
+        fn remove_from_parent(&mut self, target: uint) {
+            let parent = unwrap_or_return!(*self.parents.get(target), ());
+            self.children(parent).retain(|&c| c != target);
+            *self.parents.get_mut(target) = None;
+        }
+
+        fn reparent_children(&mut self, node: uint, new_parent: uint) {
+            let children = replace(self.children(node), vec!());
+            for c in children.move_iter() {
+                *self.parents.get_mut(c) = None;
+                self.append(new_parent, c);
+            }
+        }
+
+        fn set_quirks_mode(&mut self, _mode: QuirksMode) { }
+        fn append_comment(&mut self, _parent: uint, _text: String) { }
+        fn append_doctype_to_document(&mut self, _name: String, _public_id: String, _system_id: String) { }
+        fn add_attrs_if_missing(&mut self, _target: uint, _attrs: Vec<Attribute>) { }
+        fn mark_script_already_started(&mut self, _node: uint) { }
+
+        fn root_element_inserted(&mut self, root: uint, attrs: &[Attribute]) {
+            assert!(self.root.is_none());
+            assert_eq!(*self.parents.get(root), Some(0));
+            self.root = Some((root, attrs.to_vec()));
+        }
+    }
+
+    fn parse(input: &str) -> RecordingSink {
+        let mut sink = RecordingSink::new();
+        parse_to(&mut sink, one_input(input.to_string()), Default::default());
+        sink
+    }
+
+    // Parse and return the contents of <body>, and the number of errors.
+    fn body(input: &str) -> (String, uint) {
+        let sink = parse(input);
+        let mut out = String::new();
+        sink.serialize(0, &mut out);
+        let start = out.as_slice().find_str("<body>").expect("no body") + "<body>".len();
+        let end = out.as_slice().rfind_str("</body>").expect("no body");
+        (out.as_slice().slice(start, end).to_string(), sink.errors)
+    }
+
+    fn body_html(input: &str) -> String {
+        let (html, _) = body(input);
+        html
+    }
+
+    test_eq!(no_misnesting, body_html("<b>1<i>2</i>3</b>"),
+        "<b>1<i>2</i>3</b>".to_string())
+
+    test_eq!(furthest_block, body_html("<b>1<p>2</b>3"),
+        "<b>1</b><p><b>2</b>3</p>".to_string())
+
+    test_eq!(no_furthest_block, body_html("<b>1<i>2</b>3"),
+        "<b>1<i>2</i></b><i>3</i>".to_string())
+
+    test_eq!(nested_a, body_html("<a>1<a>2"),
+        "<a>1</a><a>2</a>".to_string())
+
+    test_eq!(a_across_block, body_html("<a>1<div>2<a>3"),
+        "<a>1</a><div><a>2</a><a>3</a></div>".to_string())
+
+    test_eq!(formatting_between, body_html("<b><em><p>x</b>y"),
+        "<b><em></em></b><em><p><b>x</b>y</p></em>".to_string())
+
+    // The inner loop gives up on formatting elements after the third,
+    // so the <b> is dropped from the list and not cloned.
+    test_eq!(inner_loop_limit, body_html("<a><b><i><u><s><div>x</a>"),
+        "<a><b><i><u><s></s></u></i></b></a><i><u><s><div><a>x</a></div></s></u></i>".to_string())
+
+    test_eq!(reconstruct, body_html("<p><b>1<p>2"),
+        "<p><b>1</b></p><p><b>2</b></p>".to_string())
+
+    // Only three identical <b> elements survive to be reconstructed.
+    test_eq!(noahs_ark, body_html("<p><b><b><b><b>1<p>2"),
+        "<p><b><b><b><b>1</b></b></b></b></p><p><b><b><b>2</b></b></b></p>".to_string())
+
+    // Attributes make the elements different, so all four survive.
+    test_eq!(noahs_ark_attrs, body_html("<p><b><b><b><b x>1<p>2"),
+        "<p><b><b><b><b>1</b></b></b></b></p><p><b><b><b><b>2</b></b></b></b></p>".to_string())
+
+    // The clause doesn't look past a marker.
+    test_eq!(noahs_ark_marker, body_html("<p><b><b><b><object><b></object><p>1"),
+        "<p><b><b><b><object><b></b></object></b></b></b></p><p><b><b><b>1</b></b></b></p>".to_string())
+
+    // A cell start tag closes the <select>, and resetting the insertion
+    // mode finds the open cell.
+    test_eq!(reset_to_cell, body_html("<table><tr><td><select><td>x"),
+        "<table><tbody><tr><td><select></select></td><td>x</td></tr></tbody></table>".to_string())
+
+    test_eq!(reset_after_select, body_html("<table><tr><td><select></select>x<td>y"),
+        "<table><tbody><tr><td><select></select>x</td><td>y</td></tr></tbody></table>".to_string())
+
+    // Closing the cell from inside the <select> goes back to the row.
+    test_eq!(reset_to_row, body_html("<table><tr><td><select></td><td>x"),
+        "<table><tbody><tr><td><select></select></td><td>x</td></tr></tbody></table>".to_string())
+
+    // The furthest block is foster parented out of the table.
+    test_eq!(adoption_foster_parent, body_html("<table><a>x<p>y</a>"),
+        "<p><a>y</a></p><table><a>x</a></table>".to_string())
+
+    #[test]
+    fn end_tag_without_formatting_element() {
+        let (html, errors) = body("<!DOCTYPE html><p>1</b>2");
+        assert_eq!(html, "<p>12</p>".to_string());
+        assert_eq!(errors, 1);
+    }
+
+    #[test]
+    fn formatting_element_not_open() {
+        let (html, errors) = body("<!DOCTYPE html><div><b></div></b>1");
+        assert_eq!(html, "<div><b></b></div>1".to_string());
+        assert_eq!(errors, 2);
+    }
+
+    #[test]
+    fn root_element_inserted() {
+        let sink = parse("<!DOCTYPE html><html manifest=app.appcache lang=en><p>x");
+        let (root, attrs) = sink.root.clone().expect("no root");
+        assert_eq!(sink.elem_name(root), (HTML, atom!(html)));
+        let names: Vec<&str> = attrs.iter().map(|a| a.name.as_slice()).collect();
+        assert_eq!(names, vec!("manifest", "lang"));
+        assert_eq!(attrs.get(0).value, "app.appcache".to_string());
+    }
+
+    #[test]
+    fn implied_root_element_inserted() {
+        let sink = parse("<p>x");
+        let (_, attrs) = sink.root.expect("no root");
+        assert!(attrs.is_empty());
+    }
+}
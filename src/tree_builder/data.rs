@@ -9,11 +9,15 @@
 
 use tokenizer::Doctype;
 use tree_builder::interface::{QuirksMode, Quirks, LimitedQuirks, NoQuirks};
+use tree_builder::interface::{QuirksReason, ForceQuirksFlag, DoctypeNameNotHtml, IframeSrcdoc};
+use tree_builder::interface::{PublicIdMatch, SystemIdMatch, PublicIdPrefix, Html4PublicId, StandardsDoctype};
 
 use std::ascii::StrAsciiExt;
 
 // These should all be lowercase, for ASCII-case-insensitive matching.
+// The prefix lists must be sorted, and no entry may be a prefix of another.
 static quirky_public_prefixes: &'static [&'static str] = &[
+    "+//silmaril//dtd html pro v0r11 19970101//",
     "-//advasoft ltd//dtd html 3.0 aswedit + extensions//",
     "-//as//dtd html 3.0 aswedit + extensions//",
     "-//ietf//dtd html 2.0 level 1//",
@@ -90,13 +94,46 @@ static html4_public_prefixes: &'static [&'static str] = &[
     "-//w3c//dtd html 4.01 transitional//",
 ];
 
-pub fn doctype_error_and_quirks(doctype: &Doctype, iframe_srcdoc: bool) -> (bool, QuirksMode) {
-    fn opt_as_slice<'t>(x: &'t Option<String>) -> Option<&'t str> {
-        x.as_ref().map(|y| y.as_slice())
+// Longer than any string in the tables above, so identifiers only
+// need to be lowercased up to this length.
+static max_match_len: uint = 80;
+
+// Does `needle` start with any of the strings in `haystack`?
+// `haystack` must be sorted and prefix-free.  Then the only candidate
+// is the greatest entry which is <= `needle`.
+fn contains_pfx(haystack: &[&str], needle: &str) -> bool {
+    let (mut lo, mut hi) = (0u, haystack.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if haystack[mid] <= needle {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
     }
+    lo > 0 && needle.starts_with(haystack[lo - 1])
+}
 
-    fn opt_to_ascii_lower(x: Option<&str>) -> Option<String> {
-        x.map(|y| y.to_ascii_lower())
+// ASCII-lowercase at most `max_match_len` bytes, plus enough to finish
+// a character.  Anything longer won't equal a table entry, and the
+// prefixes we'd check are all in the first part.
+fn bounded_lower(x: Option<&str>) -> Option<String> {
+    x.map(|y| {
+        let mut n = y.len();
+        if n > max_match_len {
+            n = max_match_len;
+            while !y.is_char_boundary(n) {
+                n += 1;
+            }
+        }
+        y.slice_to(n).to_ascii_lower()
+    })
+}
+
+pub fn doctype_error_and_quirks(doctype: &Doctype, iframe_srcdoc: bool)
+        -> (bool, QuirksMode, QuirksReason) {
+    fn opt_as_slice<'t>(x: &'t Option<String>) -> Option<&'t str> {
+        x.as_ref().map(|y| y.as_slice())
     }
 
     let name = opt_as_slice(&doctype.name);
@@ -117,35 +154,147 @@ pub fn doctype_error_and_quirks(doctype: &Doctype, iframe_srcdoc: bool) -> (bool
         _ => true,
     };
 
-    // FIXME: We could do something asymptotically faster here.
-    // But there aren't many strings, and this happens at most once per parse.
-    fn contains_pfx(haystack: &[&str], needle: &str) -> bool {
-        haystack.iter().any(|&x| needle.starts_with(x))
-    }
-
     // Quirks-mode matches are case-insensitive.
-    let public = opt_to_ascii_lower(public);
-    let system = opt_to_ascii_lower(system);
+    let public = bounded_lower(public);
+    let system = bounded_lower(system);
 
-    let quirk = match (opt_as_slice(&public), opt_as_slice(&system)) {
-        _ if doctype.force_quirks => Quirks,
-        _ if name != Some("html") => Quirks,
+    let (quirk, reason) = match (opt_as_slice(&public), opt_as_slice(&system)) {
+        // An iframe srcdoc document is never in quirks mode.
+        _ if iframe_srcdoc => (NoQuirks, IframeSrcdoc),
 
-        _ if iframe_srcdoc => NoQuirks,
+        _ if doctype.force_quirks => (Quirks, ForceQuirksFlag),
+        _ if name != Some("html") => (Quirks, DoctypeNameNotHtml),
 
-        (Some(ref p), _) if quirky_public_matches.contains(p) => Quirks,
-        (_, Some(ref s)) if quirky_system_matches.contains(s) => Quirks,
+        (Some(ref p), _) if quirky_public_matches.contains(p) => (Quirks, PublicIdMatch),
+        (_, Some(ref s)) if quirky_system_matches.contains(s) => (Quirks, SystemIdMatch),
 
-        (Some(p), _) if contains_pfx(quirky_public_prefixes, p) => Quirks,
-        (Some(p), _) if contains_pfx(limited_quirky_public_prefixes, p) => LimitedQuirks,
+        (Some(p), _) if contains_pfx(quirky_public_prefixes, p) => (Quirks, PublicIdPrefix),
+        (Some(p), _) if contains_pfx(limited_quirky_public_prefixes, p) => (LimitedQuirks, PublicIdPrefix),
 
         (Some(p), s) if contains_pfx(html4_public_prefixes, p) => match s {
-            None => Quirks,
-            Some(_) => LimitedQuirks,
+            None => (Quirks, Html4PublicId),
+            Some(_) => (LimitedQuirks, Html4PublicId),
         },
 
-        _ => NoQuirks,
+        _ => (NoQuirks, StandardsDoctype),
     };
 
-    (err, quirk)
+    (err, quirk, reason)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{quirky_public_prefixes, quirky_public_matches, quirky_system_matches};
+    use super::{limited_quirky_public_prefixes, html4_public_prefixes};
+    use super::{max_match_len, contains_pfx, doctype_error_and_quirks};
+
+    use tokenizer::Doctype;
+    use tree_builder::interface::{QuirksMode, Quirks, LimitedQuirks, NoQuirks};
+    use tree_builder::interface::{QuirksReason, DoctypeNameNotHtml, IframeSrcdoc, PublicIdMatch};
+    use tree_builder::interface::{SystemIdMatch, PublicIdPrefix, Html4PublicId, StandardsDoctype};
+
+    static prefix_tables: &'static [&'static [&'static str]] = &[
+        quirky_public_prefixes,
+        limited_quirky_public_prefixes,
+        html4_public_prefixes,
+    ];
+
+    #[test]
+    fn prefix_tables_sorted_and_prefix_free() {
+        for table in prefix_tables.iter() {
+            for w in table.windows(2) {
+                assert!(w[0] < w[1]);
+                assert!(!w[1].starts_with(w[0]));
+            }
+        }
+    }
+
+    static all_tables: &'static [&'static [&'static str]] = &[
+        quirky_public_prefixes,
+        limited_quirky_public_prefixes,
+        html4_public_prefixes,
+        quirky_public_matches,
+        quirky_system_matches,
+    ];
+
+    #[test]
+    fn tables_within_max_len() {
+        for table in all_tables.iter() {
+            for s in table.iter() {
+                assert!(s.len() < max_match_len);
+            }
+        }
+    }
+
+    test_eq!(pfx_first, contains_pfx(html4_public_prefixes, "-//w3c//dtd html 4.01 frameset//en"), true)
+    test_eq!(pfx_last, contains_pfx(html4_public_prefixes, "-//w3c//dtd html 4.01 transitional//"), true)
+    test_eq!(pfx_before, contains_pfx(html4_public_prefixes, "-//w3c//dtd html 4.01 f"), false)
+    test_eq!(pfx_between, contains_pfx(html4_public_prefixes, "-//w3c//dtd html 4.01 g"), false)
+    test_eq!(pfx_empty, contains_pfx([], "x"), false)
+
+    fn quirks(public: &str, system: Option<&str>) -> QuirksMode {
+        let dt = Doctype {
+            name: Some("html".to_string()),
+            public_id: Some(public.to_string()),
+            system_id: system.map(|s| s.to_string()),
+            force_quirks: false,
+        };
+        let (_, q, _) = doctype_error_and_quirks(&dt, false);
+        q
+    }
+
+    #[test]
+    fn long_identifiers() {
+        let long = String::from_char(10000, 'X');
+        assert_eq!(quirks(["-//W3C//DTD HTML 4.0 Transitional//", long.as_slice()].concat().as_slice(), None), Quirks);
+        assert_eq!(quirks(["-//W3C//DTD XHTML 1.0 Frameset//", long.as_slice()].concat().as_slice(), None), LimitedQuirks);
+        assert_eq!(quirks(["HTML", long.as_slice()].concat().as_slice(), None), NoQuirks);
+        assert_eq!(quirks("HTML", None), Quirks);
+    }
+
+    fn reason(name: &str, public: Option<&str>, system: Option<&str>, srcdoc: bool)
+            -> (QuirksMode, QuirksReason) {
+        let dt = Doctype {
+            name: Some(name.to_string()),
+            public_id: public.map(|s| s.to_string()),
+            system_id: system.map(|s| s.to_string()),
+            force_quirks: false,
+        };
+        let (_, q, r) = doctype_error_and_quirks(&dt, srcdoc);
+        (q, r)
+    }
+
+    test_eq!(reason_name, reason("svg", None, None, false), (Quirks, DoctypeNameNotHtml))
+    test_eq!(reason_srcdoc, reason("html", Some("html"), None, true), (NoQuirks, IframeSrcdoc))
+    test_eq!(reason_srcdoc_name, reason("foo", None, None, true), (NoQuirks, IframeSrcdoc))
+
+    #[test]
+    fn srcdoc_force_quirks() {
+        let dt = Doctype {
+            name: None,
+            public_id: None,
+            system_id: None,
+            force_quirks: true,
+        };
+        let (_, q, r) = doctype_error_and_quirks(&dt, true);
+        assert_eq!((q, r), (NoQuirks, IframeSrcdoc));
+    }
+    test_eq!(reason_public_match, reason("html", Some("HTML"), None, false), (Quirks, PublicIdMatch))
+    test_eq!(reason_system_match,
+        reason("html", None, Some("http://www.ibm.com/data/dtd/v11/ibmxhtml1-transitional.dtd"), false),
+        (Quirks, SystemIdMatch))
+    test_eq!(reason_silmaril,
+        reason("html", Some("+//Silmaril//dtd html Pro v0r11 19970101//EN"), None, false),
+        (Quirks, PublicIdPrefix))
+    test_eq!(reason_xhtml_frameset,
+        reason("html", Some("-//W3C//DTD XHTML 1.0 Frameset//EN"), None, false),
+        (LimitedQuirks, PublicIdPrefix))
+    test_eq!(reason_html4_no_system,
+        reason("html", Some("-//W3C//DTD HTML 4.01 Transitional//EN"), None, false),
+        (Quirks, Html4PublicId))
+    test_eq!(reason_html4_system,
+        reason("html", Some("-//W3C//DTD HTML 4.01 Transitional//EN"),
+            Some("http://www.w3.org/TR/html4/loose.dtd"), false),
+        (LimitedQuirks, Html4PublicId))
+    test_eq!(reason_standards, reason("html", None, None, false), (NoQuirks, StandardsDoctype))
 }
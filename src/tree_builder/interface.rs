@@ -10,8 +10,8 @@
 //! The interface for consumers of the tree builder (and thus the
 //! parser overall).
 
-use util::atom::Atom;
-use util::namespace::Namespace;
+use atom::Atom;
+use atom::namespace::Namespace;
 use tokenizer::Attribute;
 
 /// A document's quirks mode.
@@ -22,6 +22,39 @@ pub enum QuirksMode {
     NoQuirks,
 }
 
+/// Which rule decided a document's quirks mode.
+#[deriving(PartialEq, Eq, Clone, Hash, Show)]
+pub enum QuirksReason {
+    /// There was no DOCTYPE.
+    MissingDoctype,
+
+    /// The DOCTYPE was malformed, so the tokenizer set its force-quirks flag.
+    ForceQuirksFlag,
+
+    /// The DOCTYPE name was not `html`.
+    DoctypeNameNotHtml,
+
+    /// This is an `iframe srcdoc` document, which is never in quirks mode.
+    IframeSrcdoc,
+
+    /// The public identifier is one of a few exact matches.
+    PublicIdMatch,
+
+    /// The system identifier is one of a few exact matches.
+    SystemIdMatch,
+
+    /// The public identifier starts with a legacy DTD prefix.
+    PublicIdPrefix,
+
+    /// The public identifier is an HTML 4.01 frameset or transitional
+    /// DTD.  This is quirks mode if the system identifier is missing,
+    /// and limited-quirks mode otherwise.
+    Html4PublicId,
+
+    /// No rule matched.
+    StandardsDoctype,
+}
+
 /// Types which can process tree modifications from the tree builder.
 ///
 /// `Handle` is a reference to a DOM node.  The tree builder requires
@@ -61,6 +94,13 @@ pub trait TreeSink<Handle> {
     /// The child element will not already have a parent.
     fn append_element(&mut self, parent: Handle, child: Handle);
 
+    /// Insert an element as the previous sibling of `sibling`, for
+    /// foster parenting.  If `sibling` has no parent, return the element
+    /// as `Err` and the tree builder will put it somewhere else.
+    ///
+    /// The new element will not already have a parent.
+    fn append_before_sibling(&mut self, sibling: Handle, new_node: Handle) -> Result<(), Handle>;
+
     /// Append a `DOCTYPE` element to the `Document` node.
     fn append_doctype_to_document(&mut self, name: String, public_id: String, system_id: String);
 
@@ -71,6 +111,96 @@ pub trait TreeSink<Handle> {
     /// Detach the given node from its parent.
     fn remove_from_parent(&mut self, target: Handle);
 
+    /// Remove all the children from node and append them to new_parent.
+    fn reparent_children(&mut self, node: Handle, new_parent: Handle);
+
     /// Mark a HTML `<script>` element as "already started".
     fn mark_script_already_started(&mut self, node: Handle);
+
+    /// Called when the root `<html>` element has been inserted, with its
+    /// attributes.  This is where a browser would run the application
+    /// cache selection algorithm, using the `manifest` attribute.
+    ///
+    /// The default implementation does nothing.
+    fn root_element_inserted(&mut self, _root: Handle, _attrs: &[Attribute]) { }
+
+    /// Associate a newly created form-associated element with the
+    /// `<form>` in the parser's form element pointer.  This is only
+    /// called for elements without a `form` attribute, which the sink
+    /// should resolve itself once the tree is built.
+    ///
+    /// The default implementation does nothing.
+    fn associate_with_form(&mut self, _target: Handle, _form: Handle) { }
+}
+
+/// A borrowed sink is also a sink, so a `TreeBuilder` can either own
+/// its sink or borrow it.
+impl<'a, Handle, T: TreeSink<Handle>> TreeSink<Handle> for &'a mut T {
+    fn parse_error(&mut self, msg: String) {
+        (**self).parse_error(msg)
+    }
+
+    fn get_document(&mut self) -> Handle {
+        (**self).get_document()
+    }
+
+    fn same_node(&self, x: Handle, y: Handle) -> bool {
+        (**self).same_node(x, y)
+    }
+
+    fn elem_name(&self, target: Handle) -> (Namespace, Atom) {
+        (**self).elem_name(target)
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        (**self).set_quirks_mode(mode)
+    }
+
+    fn create_element(&mut self, ns: Namespace, name: Atom, attrs: Vec<Attribute>) -> Handle {
+        (**self).create_element(ns, name, attrs)
+    }
+
+    fn append_text(&mut self, parent: Handle, text: String) {
+        (**self).append_text(parent, text)
+    }
+
+    fn append_comment(&mut self, parent: Handle, text: String) {
+        (**self).append_comment(parent, text)
+    }
+
+    fn append_element(&mut self, parent: Handle, child: Handle) {
+        (**self).append_element(parent, child)
+    }
+
+    fn append_before_sibling(&mut self, sibling: Handle, new_node: Handle) -> Result<(), Handle> {
+        (**self).append_before_sibling(sibling, new_node)
+    }
+
+    fn append_doctype_to_document(&mut self, name: String, public_id: String, system_id: String) {
+        (**self).append_doctype_to_document(name, public_id, system_id)
+    }
+
+    fn add_attrs_if_missing(&mut self, target: Handle, attrs: Vec<Attribute>) {
+        (**self).add_attrs_if_missing(target, attrs)
+    }
+
+    fn remove_from_parent(&mut self, target: Handle) {
+        (**self).remove_from_parent(target)
+    }
+
+    fn reparent_children(&mut self, node: Handle, new_parent: Handle) {
+        (**self).reparent_children(node, new_parent)
+    }
+
+    fn mark_script_already_started(&mut self, node: Handle) {
+        (**self).mark_script_already_started(node)
+    }
+
+    fn root_element_inserted(&mut self, root: Handle, attrs: &[Attribute]) {
+        (**self).root_element_inserted(root, attrs)
+    }
+
+    fn associate_with_form(&mut self, target: Handle, form: Handle) {
+        (**self).associate_with_form(target, form)
+    }
 }
@@ -10,7 +10,11 @@
 //! The HTML5 tree builder.
 
 pub use self::interface::{QuirksMode, Quirks, LimitedQuirks, NoQuirks};
+pub use self::interface::{QuirksReason, MissingDoctype, ForceQuirksFlag, DoctypeNameNotHtml};
+pub use self::interface::{IframeSrcdoc, PublicIdMatch, SystemIdMatch, PublicIdPrefix};
+pub use self::interface::{Html4PublicId, StandardsDoctype};
 pub use self::interface::TreeSink;
+pub use self::tag_sets::{heading_tag, listed_tag};
 
 use self::types::*;
 use self::actions::TreeBuilderActions;
@@ -20,7 +24,7 @@ use tokenizer;
 use tokenizer::{Doctype, Tag};
 use tokenizer::TokenSink;
 
-use util::namespace::HTML;
+use atom::namespace::HTML;
 use util::str::{is_ascii_whitespace, Runs};
 
 use std::default::Default;
@@ -47,6 +51,18 @@ pub struct TreeBuilderOpts {
 
     /// Should we drop the DOCTYPE (if any) from the tree?
     pub drop_doctype: bool,
+
+    /// Maximum depth of elements in the tree, counting `<html>` as 1.
+    /// An element which would be nested deeper closes the deepest open
+    /// element and is inserted as its sibling instead.  Must be at least
+    /// 2; `TreeBuilder::new` fails otherwise.
+    pub max_depth: Option<uint>,
+
+    /// Maximum number of elements, comments and text insertions.  Once
+    /// this is reached, the rest of the document is ignored.  The limit
+    /// is checked between tokens, so it may be exceeded by the few
+    /// nodes a single token implies.
+    pub max_nodes: Option<uint>,
 }
 
 impl Default for TreeBuilderOpts {
@@ -56,17 +72,19 @@ impl Default for TreeBuilderOpts {
             iframe_srcdoc: false,
             fragment: false,
             drop_doctype: false,
+            max_depth: None,
+            max_nodes: None,
         }
     }
 }
 
 /// The HTML tree builder.
-pub struct TreeBuilder<'sink, Handle, Sink> {
+pub struct TreeBuilder<Handle, Sink> {
     /// Options controlling the behavior of the tree builder.
     opts: TreeBuilderOpts,
 
     /// Consumer of tree modifications.
-    sink: &'sink mut Sink,
+    sink: Sink,
 
     /// Insertion mode.
     mode: InsertionMode,
@@ -77,10 +95,13 @@ pub struct TreeBuilder<'sink, Handle, Sink> {
     /// Pending table character tokens.
     pending_table_text: Vec<(SplitStatus, String)>,
 
-    /// Quirks mode as set by the parser.
-    /// FIXME: can scripts etc. change this?
+    /// Quirks mode as set by the parser.  Scripts can't change this,
+    /// so it's final once the initial insertion mode is done.
     quirks_mode: QuirksMode,
 
+    /// The rule which set `quirks_mode`, if we've got that far.
+    quirks_reason: Option<QuirksReason>,
+
     /// The document node, which is created by the sink.
     doc_handle: Handle,
 
@@ -109,13 +130,23 @@ pub struct TreeBuilder<'sink, Handle, Sink> {
 
     /// Is foster parenting enabled?
     foster_parenting: bool,
+
+    /// Number of nodes inserted so far, for `max_nodes`.
+    node_count: uint,
+
+    /// Have we reached `max_nodes`?
+    node_limit_reached: bool,
 }
 
-impl<'sink, Handle: Clone, Sink: TreeSink<Handle>> TreeBuilder<'sink, Handle, Sink> {
+impl<Handle: Clone, Sink: TreeSink<Handle>> TreeBuilder<Handle, Sink> {
     /// Create a new tree builder which sends tree modifications to a particular `TreeSink`.
     ///
     /// The tree builder is also a `TokenSink`.
-    pub fn new(sink: &'sink mut Sink, opts: TreeBuilderOpts) -> TreeBuilder<'sink, Handle, Sink> {
+    pub fn new(mut sink: Sink, opts: TreeBuilderOpts) -> TreeBuilder<Handle, Sink> {
+        match opts.max_depth {
+            Some(max) => assert!(max >= 2, "max_depth must be at least 2"),
+            None => (),
+        }
         let doc_handle = sink.get_document();
         TreeBuilder {
             opts: opts,
@@ -124,6 +155,7 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>> TreeBuilder<'sink, Handle, Si
             orig_mode: None,
             pending_table_text: vec!(),
             quirks_mode: NoQuirks,
+            quirks_reason: None,
             doc_handle: doc_handle,
             open_elems: vec!(),
             active_formatting: vec!(),
@@ -133,9 +165,59 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>> TreeBuilder<'sink, Handle, Si
             frameset_ok: true,
             ignore_lf: false,
             foster_parenting: false,
+            node_count: 0,
+            node_limit_reached: false,
         }
     }
 
+    /// Get a mutable reference to the sink.
+    pub fn sink<'a>(&'a mut self) -> &'a mut Sink {
+        &mut self.sink
+    }
+
+    /// Consume the tree builder and return the sink.
+    pub fn into_sink(self) -> Sink {
+        self.sink
+    }
+
+    /// The document's quirks mode.
+    ///
+    /// This is `NoQuirks` until the parser has seen a DOCTYPE or some
+    /// other content, and it doesn't change after that.
+    pub fn quirks_mode(&self) -> QuirksMode {
+        self.quirks_mode
+    }
+
+    /// The rule which decided the quirks mode, or `None` if it hasn't
+    /// been decided yet.
+    pub fn quirks_reason(&self) -> Option<QuirksReason> {
+        self.quirks_reason
+    }
+
+    /// Return to the initial state, ready to build a new document.
+    ///
+    /// This calls `get_document` on the sink again, so the sink should
+    /// be ready for a new document too.  The stacks are cleared rather
+    /// than reallocated.
+    pub fn reset(&mut self) {
+        self.doc_handle = self.sink.get_document();
+        self.mode = Initial;
+        self.orig_mode = None;
+        self.pending_table_text.truncate(0);
+        self.quirks_mode = NoQuirks;
+        self.quirks_reason = None;
+        self.open_elems.truncate(0);
+        self.active_formatting.truncate(0);
+        self.head_elem = None;
+        self.form_elem = None;
+        self.next_tokenizer_state = None;
+        self.frameset_ok = true;
+        self.ignore_lf = false;
+        self.foster_parenting = false;
+        self.node_count = 0;
+        self.node_limit_reached = false;
+    }
+
     // Debug helper
     #[allow(dead_code)]
     fn dump_state(&self, label: String) {
@@ -151,6 +233,21 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>> TreeBuilder<'sink, Handle, Si
         println!("");
     }
 
+    // Have we inserted `max_nodes` nodes?  Reports an error the first
+    // time this is true.
+    fn check_node_limit(&mut self) -> bool {
+        if !self.node_limit_reached {
+            match self.opts.max_nodes {
+                Some(max) if self.node_count >= max => {
+                    self.node_limit_reached = true;
+                    self.sink.parse_error("Too many nodes, ignoring the rest of the document".to_string());
+                }
+                _ => (),
+            }
+        }
+        self.node_limit_reached
+    }
+
     fn process_to_completion(&mut self, mut token: Token) {
         // Additional tokens yet to be processed. First to be processed is on
         // the *end*, because that's where Vec supports O(1) push/pop.
@@ -200,7 +297,7 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>> TreeBuilder<'sink, Handle, Si
     }
 }
 
-impl<'sink, Handle: Clone, Sink: TreeSink<Handle>> TokenSink for TreeBuilder<'sink, Handle, Sink> {
+impl<Handle: Clone, Sink: TreeSink<Handle>> TokenSink for TreeBuilder<Handle, Sink> {
     fn process_token(&mut self, token: tokenizer::Token) {
         let ignore_lf = replace(&mut self.ignore_lf, false);
 
@@ -212,7 +309,7 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>> TokenSink for TreeBuilder<'si
             }
 
             tokenizer::DoctypeToken(dt) => if self.mode == Initial {
-                let (err, quirk) = data::doctype_error_and_quirks(&dt, self.opts.iframe_srcdoc);
+                let (err, quirk, reason) = data::doctype_error_and_quirks(&dt, self.opts.iframe_srcdoc);
                 if err {
                     self.sink.parse_error(format!("Bad DOCTYPE: {}", dt));
                 }
@@ -224,7 +321,7 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>> TokenSink for TreeBuilder<'si
                         system_id.unwrap_or(String::new())
                     );
                 }
-                self.set_quirks_mode(quirk);
+                self.set_quirks_mode(quirk, reason);
 
                 self.mode = BeforeHtml;
                 return;
@@ -249,6 +346,13 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>> TokenSink for TreeBuilder<'si
             }
         };
 
+        if self.check_node_limit() {
+            match token {
+                EOFToken => (),
+                _ => return,
+            }
+        }
+
         self.process_to_completion(token);
     }
 
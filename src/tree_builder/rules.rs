@@ -12,13 +12,13 @@
 use tree_builder::types::*;
 use tree_builder::tag_sets::*;
 use tree_builder::actions::TreeBuilderActions;
-use tree_builder::interface::{TreeSink, Quirks};
+use tree_builder::interface::{TreeSink, Quirks, NoQuirks, MissingDoctype, IframeSrcdoc};
 
 use tokenizer::{Tag, StartTag, EndTag};
 use tokenizer::states::{Rcdata, Rawtext, ScriptData, Plaintext};
 
-use util::atom::Atom;
-use util::namespace::{Namespace, HTML};
+use atom::Atom;
+use atom::namespace::{Namespace, HTML};
 use util::str::{is_ascii_whitespace, to_escaped_string};
 
 use std::mem::replace;
@@ -32,6 +32,7 @@ macro_rules! append_with ( ( $fun:ident, $target:expr, $($args:expr),* ) => ({
     // two steps to avoid double borrow
     let target = $target;
     self.sink.$fun(target, $($args),*);
+    self.node_count += 1;
     Done
 }))
 
@@ -44,8 +45,8 @@ pub trait TreeBuilderStep<Handle> {
 }
 
 #[doc(hidden)]
-impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
-    TreeBuilderStep<Handle> for super::TreeBuilder<'sink, Handle, Sink> {
+impl<Handle: Clone, Sink: TreeSink<Handle>>
+    TreeBuilderStep<Handle> for super::TreeBuilder<Handle, Sink> {
 
     fn step(&mut self, mode: InsertionMode, token: Token) -> ProcessResult {
         // $thing may be either a Token or a Tag
@@ -64,9 +65,11 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
                 CharacterTokens(Whitespace, _) => Done,
                 CommentToken(text) => append_comment!(self.doc_handle.clone(), text),
                 token => {
-                    if !self.opts.iframe_srcdoc {
+                    if self.opts.iframe_srcdoc {
+                        self.set_quirks_mode(NoQuirks, IframeSrcdoc);
+                    } else {
                         unexpected!(token);
-                        self.set_quirks_mode(Quirks);
+                        self.set_quirks_mode(Quirks, MissingDoctype);
                     }
                     Reprocess(BeforeHtml, token)
                 }
@@ -148,8 +151,9 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
                 }
 
                 tag @ <script> => {
-                    let target = self.target();
+                    let target = self.element_target();
                     let elem = self.sink.create_element(HTML, atom!(script), tag.attrs);
+                    self.node_count += 1;
                     if self.opts.fragment {
                         self.sink.mark_script_already_started(elem.clone());
                     }
@@ -500,23 +504,32 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
                 }
 
                 tag @ <a> => {
-                    let mut to_remove = vec!();
-                    for (i, handle, _) in self.active_formatting_end_to_marker() {
+                    // Every <a> start tag removes earlier ones from the list,
+                    // so there can be at most one since the last marker.
+                    let mut prev = None;
+                    for (_, handle, _) in self.active_formatting_end_to_marker() {
                         if self.html_elem_named(handle.clone(), atom!(a)) {
-                            to_remove.push((i, handle.clone()));
+                            prev = Some(handle.clone());
+                            break;
                         }
                     }
 
-                    if !to_remove.is_empty() {
-                        unexpected!(tag);
-                        self.adoption_agency(atom!(a));
-                        // FIXME: quadratic time
-                        for (i, handle) in to_remove.move_iter() {
-                            self.remove_from_stack(&handle);
-                            self.active_formatting.remove(i);
-                            // We iterated backwards from the end above, so
-                            // we don't need to adjust the indices after each
-                            // removal.
+                    match prev {
+                        None => (),
+                        Some(prev) => {
+                            unexpected!(tag);
+                            self.adoption_agency(atom!(a));
+
+                            // The adoption agency may have already removed it.
+                            self.remove_from_stack(&prev);
+                            let pos = self.active_formatting.iter().position(|e| match *e {
+                                Element(ref h, _) => self.sink.same_node(h.clone(), prev.clone()),
+                                Marker => false,
+                            });
+                            match pos {
+                                Some(i) => { self.active_formatting.remove(i); }
+                                None => (),
+                            }
                         }
                     }
 
@@ -703,34 +716,7 @@ impl<'sink, Handle: Clone, Sink: TreeSink<Handle>>
                 }
 
                 tag @ </_> => {
-                    // Look back for a matching open element.
-                    let mut match_idx = None;
-                    for (i, elem) in self.open_elems.iter().enumerate().rev() {
-                        if self.html_elem_named(elem.clone(), tag.name.clone()) {
-                            match_idx = Some(i);
-                            break;
-                        }
-
-                        if self.elem_in(elem.clone(), special_tag) {
-                            unexpected!(tag);
-                            return Done;
-                        }
-                    }
-
-                    let match_idx = unwrap_or_return!(match_idx, {
-                        // I believe this is impossible, because the root
-                        // <html> element is in special_tag.
-                        unexpected!(tag);
-                        Done
-                    });
-
-                    self.generate_implied_end_except(tag.name.clone());
-
-                    if match_idx != self.open_elems.len() - 1 {
-                        // mis-nested tags
-                        unexpected!(tag);
-                    }
-                    self.open_elems.truncate(match_idx);
+                    self.process_end_tag_in_body(tag);
                     Done
                 }
 
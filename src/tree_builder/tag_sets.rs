@@ -11,8 +11,8 @@
 
 #![macro_escape]
 
-use util::atom::Atom;
-use util::namespace::{Namespace, HTML};
+use atom::Atom;
+use atom::namespace::{Namespace, HTML};
 
 //§ the-stack-of-open-elements
 pub type TagSet<'a> = |(Namespace, Atom)|: 'a -> bool;
@@ -63,6 +63,7 @@ declare_tag_set!(pub select_scope = full_set - optgroup option)
 declare_tag_set!(pub table_body_context = tbody tfoot thead template html)
 declare_tag_set!(pub table_row_context = tr template html)
 declare_tag_set!(pub td_th = td th)
+declare_tag_set!(pub table_outer = table tbody tfoot thead tr)
 
 declare_tag_set!(pub cursory_implied_end = dd dt li option optgroup p rp rt)
 
@@ -71,6 +72,9 @@ declare_tag_set!(pub thorough_implied_end = cursory_implied_end
 
 declare_tag_set!(pub heading_tag = h1 h2 h3 h4 h5 h6)
 
+declare_tag_set!(pub listed_tag = button fieldset input object output select textarea)
+declare_tag_set!(pub form_associatable_tag = listed_tag + img)
+
 declare_tag_set!(pub special_tag =
     address applet area article aside base basefont bgsound blockquote body br button caption
     center col colgroup dd details dir div dl dt embed fieldset figcaption figure footer form
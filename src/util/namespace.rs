@@ -1,18 +0,0 @@
-// Copyright 2014 The html5ever Project Developers. See the
-// COPYRIGHT file at the top-level directory of this distribution.
-//
-// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
-// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
-// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
-// option. This file may not be copied, modified, or distributed
-// except according to those terms.
-
-// FIXME: Unify with Servo's namespace.rs.
-
-/// A name / attribute namespace.
-#[deriving(PartialEq, Clone, Show)]
-pub enum Namespace {
-    HTML,
-    // FIXME: add others. Put one here so we don't get unreachable pattern errors.
-    Unknown,
-}
@@ -0,0 +1,38 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use tokenizer::{TokenSink, Token, TagToken};
+use tokenizer::states::State;
+
+/// Drops the tags the tree builder doesn't support yet and would fail
+/// on: `<svg>`, `<math>`, `<template>` and `<isindex>`.  Their contents
+/// are parsed as HTML.
+///
+/// Used where a failure can't be allowed, such as on untrusted input or
+/// across the C API.
+pub struct DropUnsupported<Sink> {
+    pub inner: Sink,
+}
+
+impl<Sink: TokenSink> TokenSink for DropUnsupported<Sink> {
+    fn process_token(&mut self, token: Token) {
+        match token {
+            TagToken(ref tag) => match tag.name {
+                atom!(svg) | atom!(math) | atom!(template) | atom!(isindex) => return,
+                _ => (),
+            },
+            _ => (),
+        }
+        self.inner.process_token(token);
+    }
+
+    fn query_state_change(&mut self) -> Option<State> {
+        self.inner.query_state_change()
+    }
+}
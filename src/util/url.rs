@@ -0,0 +1,257 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Resolving relative URLs.
+//!
+//! This is RFC 3986 reference resolution, with a few of the URL
+//! Standard's fixups for what browsers accept in HTML: surrounding
+//! whitespace is trimmed, tabs and newlines are removed, backslashes act
+//! as slashes in `http` and friends, and `http:foo` against an `http`
+//! base is relative.  There's no percent-encoding or IDNA processing.
+
+use std::ascii::StrAsciiExt;
+
+struct Url<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+fn is_special(scheme: &str) -> bool {
+    match scheme.to_ascii_lower().as_slice() {
+        "http" | "https" | "ftp" | "ws" | "wss" | "file" => true,
+        _ => false,
+    }
+}
+
+fn split_scheme<'a>(s: &'a str) -> (Option<&'a str>, &'a str) {
+    let end = match s.find(':') {
+        Some(i) => i,
+        None => return (None, s),
+    };
+    let scheme = s.slice_to(end);
+    let valid = scheme.chars().next().map_or(false, |c| match c {
+        'a'..'z' | 'A'..'Z' => true,
+        _ => false,
+    }) && scheme.chars().all(|c| match c {
+        'a'..'z' | 'A'..'Z' | '0'..'9' | '+' | '-' | '.' => true,
+        _ => false,
+    });
+    if valid {
+        (Some(scheme), s.slice_from(end + 1))
+    } else {
+        (None, s)
+    }
+}
+
+fn parse<'a>(s: &'a str) -> Url<'a> {
+    let (scheme, rest) = split_scheme(s);
+
+    let (rest, fragment) = match rest.find('#') {
+        Some(i) => (rest.slice_to(i), Some(rest.slice_from(i + 1))),
+        None => (rest, None),
+    };
+    let (rest, query) = match rest.find('?') {
+        Some(i) => (rest.slice_to(i), Some(rest.slice_from(i + 1))),
+        None => (rest, None),
+    };
+    let (authority, path) = if rest.starts_with("//") {
+        let rest = rest.slice_from(2);
+        match rest.find('/') {
+            Some(i) => (Some(rest.slice_to(i)), rest.slice_from(i)),
+            None => (Some(rest), ""),
+        }
+    } else {
+        (None, rest)
+    };
+
+    Url {
+        scheme: scheme,
+        authority: authority,
+        path: path,
+        query: query,
+        fragment: fragment,
+    }
+}
+
+// RFC 3986 section 5.2.4.
+fn remove_dot_segments(path: &str) -> String {
+    let absolute = path.starts_with("/");
+    let segments: Vec<&str> = if absolute {
+        path.slice_from(1).split('/').collect()
+    } else {
+        path.split('/').collect()
+    };
+
+    let mut out: Vec<&str> = vec!();
+    let last = segments.len() - 1;
+    for (i, &seg) in segments.iter().enumerate() {
+        match seg {
+            "." | ".." => {
+                if seg == ".." {
+                    out.pop();
+                }
+                if i == last {
+                    out.push("");
+                }
+            }
+            seg => out.push(seg),
+        }
+    }
+
+    let joined = out.connect("/");
+    if absolute {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+// RFC 3986 section 5.2.3.
+fn merge(base: &Url, path: &str) -> String {
+    if base.authority.is_some() && base.path.is_empty() {
+        return format!("/{}", path);
+    }
+    match base.path.rfind('/') {
+        Some(i) => format!("{}{}", base.path.slice_to(i + 1), path),
+        None => path.to_string(),
+    }
+}
+
+fn lower_host(authority: &str) -> String {
+    match authority.rfind('@') {
+        Some(i) => format!("{}{}", authority.slice_to(i + 1),
+            authority.slice_from(i + 1).to_ascii_lower()),
+        None => authority.to_ascii_lower(),
+    }
+}
+
+/// Resolve `reference` against the absolute URL `base`.  Returns `None`
+/// if `base` is not absolute, or can't have relative URLs resolved
+/// against it (like `mailto:x`) and `reference` isn't absolute either.
+pub fn resolve(base: &str, reference: &str) -> Option<String> {
+    let base = parse(base);
+    let base_scheme = unwrap_or_return!(base.scheme, None);
+
+    let cleaned: String = reference
+        .trim_chars(|c: char| c <= ' ')
+        .chars().filter(|&c| c != '\t' && c != '\n' && c != '\r')
+        .collect();
+
+    let special = {
+        let (ref_scheme, _) = split_scheme(cleaned.as_slice());
+        is_special(ref_scheme.unwrap_or(base_scheme))
+    };
+    let cleaned = if special {
+        // Backslashes are slashes, up to the query or fragment.
+        let end = cleaned.as_slice().find(|c: char| c == '?' || c == '#').unwrap_or(cleaned.len());
+        format!("{}{}", cleaned.as_slice().slice_to(end).replace("\\", "/"),
+            cleaned.as_slice().slice_from(end))
+    } else {
+        cleaned
+    };
+
+    let mut r = parse(cleaned.as_slice());
+    let same_scheme = r.scheme.map_or(false, |s| s.eq_ignore_ascii_case(base_scheme));
+    if same_scheme && special && r.authority.is_none() {
+        r.scheme = None;
+    }
+
+    let (scheme, authority, path, query) = match r.scheme {
+        Some(scheme) => (scheme, r.authority, remove_dot_segments(r.path), r.query),
+        None => {
+            let opaque = base.authority.is_none() && !base.path.starts_with("/");
+            if opaque {
+                // Only a fragment can be resolved against e.g. `about:blank`.
+                if r.authority.is_some() || !r.path.is_empty() || r.query.is_some() {
+                    return None;
+                }
+                (base_scheme, None, base.path.to_string(), base.query)
+            } else if r.authority.is_some() {
+                (base_scheme, r.authority, remove_dot_segments(r.path), r.query)
+            } else if r.path.is_empty() {
+                (base_scheme, base.authority, base.path.to_string(), r.query.or(base.query))
+            } else if r.path.starts_with("/") {
+                (base_scheme, base.authority, remove_dot_segments(r.path), r.query)
+            } else {
+                (base_scheme, base.authority, remove_dot_segments(merge(&base, r.path).as_slice()), r.query)
+            }
+        }
+    };
+
+    let special = is_special(scheme);
+    let mut out = scheme.to_ascii_lower();
+    out.push_char(':');
+    match authority {
+        Some(a) => {
+            out.push_str("//");
+            out.push_str(if special { lower_host(a) } else { a.to_string() }.as_slice());
+            if special && path.is_empty() {
+                out.push_char('/');
+            }
+        }
+        None => (),
+    }
+    out.push_str(path.as_slice());
+    match query {
+        Some(q) => {
+            out.push_char('?');
+            out.push_str(q);
+        }
+        None => (),
+    }
+    match r.fragment {
+        Some(f) => {
+            out.push_char('#');
+            out.push_str(f);
+        }
+        None => (),
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod test {
+    use super::resolve;
+
+    fn res(reference: &str) -> Option<String> {
+        resolve("http://a/b/c/d;p?q", reference)
+    }
+
+    fn some(s: &str) -> Option<String> {
+        Some(s.to_string())
+    }
+
+    // From RFC 3986 section 5.4.
+    test_eq!(rfc_scheme, res("g:h"), some("g:h"))
+    test_eq!(rfc_g, res("g"), some("http://a/b/c/g"))
+    test_eq!(rfc_dot_g, res("./g"), some("http://a/b/c/g"))
+    test_eq!(rfc_g_slash, res("g/"), some("http://a/b/c/g/"))
+    test_eq!(rfc_slash_g, res("/g"), some("http://a/g"))
+    test_eq!(rfc_authority, res("//g"), some("http://g/"))
+    test_eq!(rfc_query, res("?y"), some("http://a/b/c/d;p?y"))
+    test_eq!(rfc_g_query, res("g?y"), some("http://a/b/c/g?y"))
+    test_eq!(rfc_fragment, res("#s"), some("http://a/b/c/d;p?q#s"))
+    test_eq!(rfc_empty, res(""), some("http://a/b/c/d;p?q"))
+    test_eq!(rfc_dot_dot, res(".."), some("http://a/b/"))
+    test_eq!(rfc_dot_dot_g, res("../g"), some("http://a/b/g"))
+    test_eq!(rfc_too_many_dot_dots, res("../../../g"), some("http://a/g"))
+    test_eq!(rfc_slash_dot, res("/./g"), some("http://a/g"))
+    test_eq!(rfc_dot_dot_in_middle, res("g;x=1/../y"), some("http://a/b/c/y"))
+
+    test_eq!(whitespace, res(" \tg\n "), some("http://a/b/c/g"))
+    test_eq!(backslashes, res("\\x\\y?a\\b"), some("http://a/x/y?a\\b"))
+    test_eq!(same_scheme, res("http:g"), some("http://a/b/c/g"))
+    test_eq!(lowercase, res("HTTP://User@EXAMPLE.com/X"), some("http://User@example.com/X"))
+    test_eq!(relative_base, resolve("/a/b", "c"), None)
+    test_eq!(opaque_base, resolve("about:blank", "c"), None)
+    test_eq!(opaque_base_fragment, resolve("about:blank", "#c"), some("about:blank#c"))
+}
@@ -0,0 +1,105 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::string::String;
+
+/// Incremental UTF-8 decoder.
+///
+/// A multi-byte sequence split across chunks is held back until the
+/// rest of it arrives.  Invalid bytes become U+FFFD REPLACEMENT CHARACTER.
+pub struct Utf8Decoder {
+    /// Bytes of an incomplete sequence at the end of the last chunk.
+    pending: Vec<u8>,
+}
+
+// Expected length of a sequence starting with this byte,
+// or 1 for continuation bytes and invalid lead bytes.
+fn sequence_len(b: u8) -> uint {
+    match b {
+        0xC0..0xDF => 2,
+        0xE0..0xEF => 3,
+        0xF0..0xF7 => 4,
+        _ => 1,
+    }
+}
+
+// How many bytes at the end of `buf` begin a sequence
+// which isn't finished yet?
+fn incomplete_tail(buf: &[u8]) -> uint {
+    let len = buf.len();
+    let mut i = len;
+    while i > 0 && len - i < 3 {
+        i -= 1;
+        let b = buf[i];
+        if b & 0xC0 != 0x80 {
+            // Found the start of the last sequence.
+            return if len - i < sequence_len(b) { len - i } else { 0 };
+        }
+    }
+    0
+}
+
+impl Utf8Decoder {
+    pub fn new() -> Utf8Decoder {
+        Utf8Decoder {
+            pending: vec!(),
+        }
+    }
+
+    /// Decode a chunk of input.
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        let mut buf = if self.pending.is_empty() {
+            Vec::from_slice(bytes)
+        } else {
+            let mut buf = self.pending.clone();
+            buf.push_all(bytes);
+            buf
+        };
+
+        let keep = buf.len() - incomplete_tail(buf.as_slice());
+        self.pending = Vec::from_slice(buf.slice_from(keep));
+        buf.truncate(keep);
+
+        match String::from_utf8(buf) {
+            Ok(s) => s,
+            Err(buf) => String::from_utf8_lossy(buf.as_slice()).into_string(),
+        }
+    }
+
+    /// Signal the end of input, and decode anything left over.
+    pub fn finish(&mut self) -> String {
+        let rest = String::from_utf8_lossy(self.pending.as_slice()).into_string();
+        self.pending.truncate(0);
+        rest
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Utf8Decoder;
+
+    fn decode_chunks(chunks: &[&[u8]]) -> String {
+        let mut dec = Utf8Decoder::new();
+        let mut out = String::new();
+        for c in chunks.iter() {
+            out.push_str(dec.decode(*c).as_slice());
+        }
+        out.push_str(dec.finish().as_slice());
+        out
+    }
+
+    test_eq!(ascii, decode_chunks([b"ab", b"cd"]), "abcd".to_string())
+    test_eq!(whole, decode_chunks([b"\xe2\x98\x83"]), "☃".to_string())
+    test_eq!(split_2, decode_chunks([b"x\xe2", b"\x98\x83y"]), "x☃y".to_string())
+    test_eq!(split_3, decode_chunks([b"\xf0\x9f", b"\x92", b"\xa9"]), "\U0001f4a9".to_string())
+    test_eq!(split_empty, decode_chunks([b"\xe2", b"", b"\x98\x83"]), "☃".to_string())
+    test_eq!(invalid, decode_chunks([b"a\xffb"]), "a\ufffdb".to_string())
+    test_eq!(truncated_at_end, decode_chunks([b"a\xe2\x98"]), "a\ufffd".to_string())
+    test_eq!(bad_continuation, decode_chunks([b"\xe2", b"ab"]), "\ufffdab".to_string())
+}
@@ -0,0 +1,344 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conformance checking beyond syntax errors.
+//!
+//! The tree builder only reports errors in the markup's syntax.  This
+//! pass checks a parsed tree against some of the document conformance
+//! requirements: where elements may appear, nesting of interactive
+//! content, required attributes, enumerated and numeric attribute values,
+//! and duplicate IDs.  It is not a complete validator.
+//!
+//! Problems are reported through `TreeSink::parse_error`, so they end up
+//! with the parse errors.
+//!
+//! ## Example
+//!
+//! ```rust
+//! let mut dom: RcDom = parse(one_input(my_str), Default::default());
+//! let doc = dom.document.clone();
+//! validate(&doc, &mut dom);
+//! for err in dom.errors.iter() {
+//!     ...
+//! }
+//! ```
+
+use atom::Atom;
+use sink::rcdom::{Handle, NodeHandle, Element};
+use tokenizer::Attribute;
+use tree_builder::TreeSink;
+use util::str::is_ascii_whitespace;
+
+use std::ascii::StrAsciiExt;
+use std::collections::hashmap::HashSet;
+
+// Elements which may only appear as a child of certain elements.
+static permitted_parents: &'static [(&'static str, &'static [&'static str])] = &[
+    ("li", &["ul", "ol", "menu"]),
+    ("dt", &["dl", "div"]),
+    ("dd", &["dl", "div"]),
+    ("figcaption", &["figure"]),
+    ("legend", &["fieldset"]),
+    ("summary", &["details"]),
+    ("option", &["select", "datalist", "optgroup"]),
+    ("optgroup", &["select"]),
+    ("caption", &["table"]),
+    ("colgroup", &["table"]),
+    ("col", &["colgroup"]),
+    ("thead", &["table"]),
+    ("tbody", &["table"]),
+    ("tfoot", &["table"]),
+    ("tr", &["table", "thead", "tbody", "tfoot"]),
+    ("td", &["tr"]),
+    ("th", &["tr"]),
+    ("source", &["video", "audio", "picture"]),
+    ("track", &["video", "audio"]),
+    ("param", &["object"]),
+];
+
+// Attributes with a fixed set of ASCII case-insensitive values, by
+// element, or `*` for global attributes.
+static enumerated_values: &'static [(&'static str, &'static str, &'static [&'static str])] = &[
+    ("*", "dir", &["ltr", "rtl", "auto"]),
+    ("*", "draggable", &["true", "false"]),
+    ("*", "contenteditable", &["", "true", "false", "plaintext-only"]),
+    ("*", "spellcheck", &["", "true", "false"]),
+    ("*", "translate", &["", "yes", "no"]),
+    ("*", "hidden", &["", "hidden", "until-found"]),
+    ("input", "type", &["hidden", "text", "search", "tel", "url", "email", "password",
+        "date", "month", "week", "time", "datetime-local", "number", "range", "color",
+        "checkbox", "radio", "file", "submit", "image", "reset", "button"]),
+    ("button", "type", &["submit", "reset", "button"]),
+    ("form", "method", &["get", "post", "dialog"]),
+    ("form", "enctype", &["application/x-www-form-urlencoded", "multipart/form-data", "text/plain"]),
+    ("form", "autocomplete", &["on", "off"]),
+    ("img", "crossorigin", &["", "anonymous", "use-credentials"]),
+    ("script", "crossorigin", &["", "anonymous", "use-credentials"]),
+    ("link", "crossorigin", &["", "anonymous", "use-credentials"]),
+    ("img", "loading", &["lazy", "eager"]),
+    ("iframe", "loading", &["lazy", "eager"]),
+    ("img", "decoding", &["sync", "async", "auto"]),
+    ("th", "scope", &["row", "col", "rowgroup", "colgroup"]),
+    ("track", "kind", &["subtitles", "captions", "descriptions", "chapters", "metadata"]),
+    ("video", "preload", &["", "none", "metadata", "auto"]),
+    ("audio", "preload", &["", "none", "metadata", "auto"]),
+];
+
+// Attributes whose value is an integer, by element, and whether it must
+// be positive (not just non-negative).  `tabindex` may be negative.
+static integer_attributes: &'static [(&'static str, &'static str, bool)] = &[
+    ("img", "width", false),
+    ("img", "height", false),
+    ("iframe", "width", false),
+    ("iframe", "height", false),
+    ("video", "width", false),
+    ("video", "height", false),
+    ("canvas", "width", false),
+    ("canvas", "height", false),
+    ("td", "colspan", true),
+    ("th", "colspan", true),
+    ("td", "rowspan", false),
+    ("th", "rowspan", false),
+    ("col", "span", true),
+    ("colgroup", "span", true),
+    ("textarea", "rows", true),
+    ("textarea", "cols", true),
+];
+
+fn attr<'a>(attrs: &'a [Attribute], name: &str) -> Option<&'a str> {
+    attrs.iter().find(|a| a.name.as_slice() == name).map(|a| a.value.as_slice())
+}
+
+fn elem_name(node: &Handle) -> Option<Atom> {
+    match node.borrow().node {
+        Element(ref name, _) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+// Interactive content, which may not be nested inside <a> or <button>.
+fn is_interactive(name: &Atom, attrs: &[Attribute]) -> bool {
+    match *name {
+        atom!(a) | atom!(button) | atom!(details) | atom!(embed) | atom!(iframe)
+        | atom!(label) | atom!(select) | atom!(textarea) => true,
+        atom!(input) => !attr(attrs, "type").map_or(false, |t| t.eq_ignore_ascii_case("hidden")),
+        atom!(audio) | atom!(video) => attr(attrs, "controls").is_some(),
+        atom!(img) | atom!(object) => attr(attrs, "usemap").is_some(),
+        _ => false,
+    }
+}
+
+fn is_integer(value: &str, allow_negative: bool) -> bool {
+    let digits = if allow_negative && value.starts_with("-") {
+        value.slice_from(1)
+    } else {
+        value
+    };
+    !digits.is_empty() && digits.chars().all(|c| c.is_digit())
+}
+
+struct Validator<'a, Sink: 'a> {
+    sink: &'a mut Sink,
+    ids: HashSet<String>,
+}
+
+impl<'a, Sink: TreeSink<Handle>> Validator<'a, Sink> {
+    fn error(&mut self, msg: String) {
+        self.sink.parse_error(msg);
+    }
+
+    fn check_parent(&mut self, node: &Handle, name: &Atom) {
+        let parents = match permitted_parents.iter().find(|&&(n, _)| n == name.as_slice()) {
+            Some(&(_, parents)) => parents,
+            None => return,
+        };
+        let parent = node.parent().and_then(|p| elem_name(&p));
+        let ok = match parent {
+            Some(ref p) => parents.iter().any(|&allowed| allowed == p.as_slice()),
+            None => false,
+        };
+        if !ok {
+            let parent = parent.map_or("the document".to_string(), |p| format!("<{}>", p.as_slice()));
+            self.error(format!("Element <{}> not allowed as child of {}", name.as_slice(), parent));
+        }
+    }
+
+    fn check_nesting(&mut self, node: &Handle, name: &Atom, attrs: &[Attribute]) {
+        if !is_interactive(name, attrs) {
+            return;
+        }
+        for anc in node.ancestors() {
+            match elem_name(&anc) {
+                Some(atom!(a)) | Some(atom!(button)) => {
+                    let outer = elem_name(&anc).unwrap();
+                    self.error(format!("Interactive element <{}> not allowed inside <{}>",
+                        name.as_slice(), outer.as_slice()));
+                    return;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn require(&mut self, name: &Atom, attrs: &[Attribute], required: &str) {
+        if attr(attrs, required).is_none() {
+            self.error(format!("Element <{}> is missing required attribute {}",
+                name.as_slice(), required));
+        }
+    }
+
+    fn check_required(&mut self, name: &Atom, attrs: &[Attribute]) {
+        match *name {
+            atom!(html) => self.require(name, attrs, "lang"),
+            atom!(img) => self.require(name, attrs, "alt"),
+            atom!(area) if attr(attrs, "href").is_some() => self.require(name, attrs, "alt"),
+            atom!(input) if attr(attrs, "type").map_or(false, |t| t.eq_ignore_ascii_case("image"))
+                => self.require(name, attrs, "alt"),
+            atom!(optgroup) => self.require(name, attrs, "label"),
+            atom!(link) => {
+                self.require(name, attrs, "href");
+                if attr(attrs, "itemprop").is_none() {
+                    self.require(name, attrs, "rel");
+                }
+            }
+            atom!(meta) if attr(attrs, "name").is_some() || attr(attrs, "http-equiv").is_some()
+                => self.require(name, attrs, "content"),
+            _ => (),
+        }
+    }
+
+    fn check_values(&mut self, name: &Atom, attrs: &[Attribute]) {
+        for a in attrs.iter() {
+            let an = a.name.as_slice();
+            let value = a.value.as_slice();
+
+            let allowed = enumerated_values.iter().find(|&&(e, n, _)|
+                n == an && (e == "*" || e == name.as_slice()));
+            match allowed {
+                Some(&(_, _, values)) if !values.iter().any(|v| v.eq_ignore_ascii_case(value)) => {
+                    self.error(format!("Bad value \"{}\" for attribute {} on <{}>",
+                        value, an, name.as_slice()));
+                }
+                _ => (),
+            }
+
+            let integer = integer_attributes.iter().find(|&&(e, n, _)| n == an && e == name.as_slice());
+            let bad_integer = match integer {
+                Some(&(_, _, positive)) => !is_integer(value, false)
+                    || (positive && value.chars().all(|c| c == '0')),
+                None if an == "tabindex" => !is_integer(value, true),
+                None => false,
+            };
+            if bad_integer {
+                self.error(format!("Bad value \"{}\" for attribute {} on <{}>: expected {} integer",
+                    value, an, name.as_slice(),
+                    if integer.map_or(false, |&(_, _, p)| p) { "a positive" } else { "an" }));
+            }
+        }
+    }
+
+    fn check_id(&mut self, attrs: &[Attribute]) {
+        let id = match attr(attrs, "id") {
+            Some(id) => id,
+            None => return,
+        };
+        if id.is_empty() {
+            self.error("Attribute id must not be empty".to_string());
+        } else if id.chars().any(is_ascii_whitespace) {
+            self.error(format!("Attribute id \"{}\" must not contain whitespace", id));
+        } else if !self.ids.insert(id.to_string()) {
+            self.error(format!("Duplicate id \"{}\"", id));
+        }
+    }
+
+    fn element(&mut self, node: &Handle) {
+        let (name, attrs) = match node.borrow().node {
+            Element(ref name, ref attrs) => (name.clone(), attrs.clone()),
+            _ => return,
+        };
+        let attrs = attrs.as_slice();
+        self.check_parent(node, &name);
+        self.check_nesting(node, &name, attrs);
+        self.check_required(&name, attrs);
+        self.check_values(&name, attrs);
+        self.check_id(attrs);
+    }
+}
+
+/// Check a parsed document or fragment, reporting each problem to
+/// `sink.parse_error`.
+pub fn validate<Sink: TreeSink<Handle>>(document: &Handle, sink: &mut Sink) {
+    let mut v = Validator {
+        sink: sink,
+        ids: HashSet::new(),
+    };
+    for node in document.descendants() {
+        v.element(&node);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::validate;
+
+    use driver::{parse, one_input};
+    use sink::rcdom::RcDom;
+
+    use std::default::Default;
+
+    // Conformance errors only, for a document with the required doctype
+    // and `lang`.
+    fn errors(body: &str) -> Vec<String> {
+        let input = format!("<!DOCTYPE html><html lang=en><title>t</title>{}", body);
+        let mut dom: RcDom = parse(one_input(input), Default::default());
+        let parse_errors = dom.errors.len();
+        let doc = dom.document.clone();
+        validate(&doc, &mut dom);
+        dom.errors.slice_from(parse_errors).to_vec()
+    }
+
+    fn strs(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    test_eq!(valid, errors("<ul><li>a</li></ul><img src=x alt=\"\"><a href=/><span>x</span></a>"),
+        strs([]))
+
+    test_eq!(li_outside_list, errors("<div><li>a</li></div>"),
+        strs(["Element <li> not allowed as child of <div>"]))
+
+    test_eq!(interactive_in_a, errors("<a href=x><button>b</button></a><button><input type=hidden></button>"),
+        strs(["Interactive element <button> not allowed inside <a>"]))
+
+    test_eq!(required, errors("<img src=x><input type=image src=y><link href=s.css>"),
+        strs(["Element <img> is missing required attribute alt",
+              "Element <input> is missing required attribute alt",
+              "Element <link> is missing required attribute rel"]))
+
+    #[test]
+    fn html_lang() {
+        let mut dom: RcDom = parse(one_input("<!DOCTYPE html><title>t</title>".to_string()), Default::default());
+        let doc = dom.document.clone();
+        validate(&doc, &mut dom);
+        assert_eq!(dom.errors, vec!("Element <html> is missing required attribute lang".to_string()));
+    }
+
+    test_eq!(values, errors("<p dir=LTR hidden>a</p><p dir=up>b</p><button type=go>c</button>"),
+        strs(["Bad value \"up\" for attribute dir on <p>",
+              "Bad value \"go\" for attribute type on <button>"]))
+
+    test_eq!(integers, errors("<img alt=\"\" width=10px><table><tr><td colspan=0 tabindex=-1>x</td></tr></table>"),
+        strs(["Bad value \"10px\" for attribute width on <img>: expected an integer",
+              "Bad value \"0\" for attribute colspan on <td>: expected a positive integer"]))
+
+    test_eq!(ids, errors("<p id=a>1</p><p id=a>2</p><p id=\"\">3</p><p id=\"b c\">4</p>"),
+        strs(["Duplicate id \"a\"",
+              "Attribute id must not be empty",
+              "Attribute id \"b c\" must not contain whitespace"]))
+}
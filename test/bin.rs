@@ -24,6 +24,7 @@ use test::test_main;
 
 mod tokenizer;
 mod tree_builder;
+mod sanitize;
 mod util;
 
 fn main() {
@@ -38,7 +39,11 @@ fn main() {
     }
 
     if os::getenv("HTML5EVER_NO_TB_TEST").is_none() {
-        tests.push_all_move(tree_builder::tests(src_dir));
+        tests.push_all_move(tree_builder::tests(src_dir.clone()));
+    }
+
+    if os::getenv("HTML5EVER_NO_SANITIZE_TEST").is_none() {
+        tests.push_all_move(sanitize::tests(src_dir));
     }
 
     let args: Vec<String> = os::args().move_iter().collect();
@@ -0,0 +1,69 @@
+// Copyright 2014 The html5ever Project Developers. See the
+// COPYRIGHT file at the top-level directory of this distribution.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use util::parse_tests;
+
+use std::io;
+use std::default::Default;
+use std::path::Path;
+use std::collections::hashmap::HashMap;
+use test::{TestDesc, TestDescAndFn, DynTestName, DynTestFn};
+
+use html5ever::sanitize::{Policy, Escape, sanitize};
+
+fn make_test(
+        tests: &mut Vec<TestDescAndFn>,
+        idx: uint,
+        fields: HashMap<String, String>) {
+
+    let get_field = |key| {
+        let field = fields.find_equiv(&key).expect("missing field");
+        field.as_slice().trim_right_chars('\n').to_string()
+    };
+
+    let data = get_field("data");
+    let expected = get_field("output");
+    let escape = fields.find_equiv(&"escape").is_some();
+
+    tests.push(TestDescAndFn {
+        desc: TestDesc {
+            name: DynTestName(format!("sanitize: {}", idx)),
+            ignore: false,
+            should_fail: false,
+        },
+        testfn: DynTestFn(proc() {
+            let mut policy: Policy = Default::default();
+            if escape {
+                policy.disallowed = Escape;
+            }
+
+            let result = sanitize(data.as_slice(), &policy);
+            if result != expected {
+                fail!("\ninput: {}\ngot:      {}\nexpected: {}\n",
+                    data, result, expected);
+            }
+        }),
+    });
+}
+
+pub fn tests(src_dir: Path) -> Vec<TestDescAndFn> {
+    let mut tests = vec!();
+
+    let path = src_dir.join_many(["data", "test", "sanitize.dat"]);
+    let file = io::File::open(&path).ok().expect("can't open sanitizer tests");
+    let mut buf = io::BufferedReader::new(file);
+    let lines = buf.lines()
+        .map(|res| res.ok().expect("couldn't read"));
+
+    for (i, test) in parse_tests(lines).move_iter().enumerate() {
+        make_test(&mut tests, i, test);
+    }
+
+    tests
+}
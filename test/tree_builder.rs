@@ -7,10 +7,9 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use util::foreach_html5lib_test;
+use util::{foreach_html5lib_test, parse_tests};
 
 use std::io;
-use std::mem::replace;
 use std::default::Default;
 use std::path::Path;
 use std::collections::hashmap::HashMap;
@@ -19,48 +18,6 @@ use test::{TestDesc, TestDescAndFn, DynTestName, DynTestFn};
 use html5ever::sink::rcdom::{RcDom, Handle, Document, Doctype, Text, Comment, Element};
 use html5ever::{parse, one_input};
 
-fn parse_tests<It: Iterator<String>>(mut lines: It) -> Vec<HashMap<String, String>> {
-    let mut tests = vec!();
-    let mut test = HashMap::new();
-    let mut key = None;
-    let mut val = String::new();
-
-    macro_rules! finish_val ( () => (
-        match key.take() {
-            None => (),
-            Some(key) => assert!(test.insert(key, replace(&mut val, String::new()))),
-        }
-    ))
-
-    macro_rules! finish_test ( () => (
-        if !test.is_empty() {
-            tests.push(replace(&mut test, HashMap::new()));
-        }
-    ))
-
-    loop {
-        match lines.next() {
-            None => break,
-            Some(line) => {
-                if line.as_slice().starts_with("#") {
-                    finish_val!();
-                    if line.as_slice() == "#data\n" {
-                        finish_test!();
-                    }
-                    key = Some(line.as_slice().slice_from(1)
-                        .trim_right_chars('\n').to_string());
-                } else {
-                    val.push_str(line.as_slice());
-                }
-            }
-        }
-    }
-
-    finish_val!();
-    finish_test!();
-    tests
-}
-
 fn serialize(buf: &mut String, indent: uint, handle: Handle) {
     buf.push_str("|");
     buf.grow(indent, ' ');
@@ -9,6 +9,8 @@
 
 use std::io;
 use std::path::Path;
+use std::mem::replace;
+use std::collections::hashmap::HashMap;
 
 pub fn foreach_html5lib_test(
         src_dir: Path,
@@ -25,3 +27,47 @@ pub fn foreach_html5lib_test(
         }
     }
 }
+
+// Parse a file in the html5lib `.dat` format: tests start with `#data`,
+// and each `#key` line starts a field which runs until the next one.
+pub fn parse_tests<It: Iterator<String>>(mut lines: It) -> Vec<HashMap<String, String>> {
+    let mut tests = vec!();
+    let mut test = HashMap::new();
+    let mut key = None;
+    let mut val = String::new();
+
+    macro_rules! finish_val ( () => (
+        match key.take() {
+            None => (),
+            Some(key) => assert!(test.insert(key, replace(&mut val, String::new()))),
+        }
+    ))
+
+    macro_rules! finish_test ( () => (
+        if !test.is_empty() {
+            tests.push(replace(&mut test, HashMap::new()));
+        }
+    ))
+
+    loop {
+        match lines.next() {
+            None => break,
+            Some(line) => {
+                if line.as_slice().starts_with("#") {
+                    finish_val!();
+                    if line.as_slice() == "#data\n" {
+                        finish_test!();
+                    }
+                    key = Some(line.as_slice().slice_from(1)
+                        .trim_right_chars('\n').to_string());
+                } else {
+                    val.push_str(line.as_slice());
+                }
+            }
+        }
+    }
+
+    finish_val!();
+    finish_test!();
+    tests
+}